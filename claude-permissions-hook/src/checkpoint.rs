@@ -0,0 +1,467 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+
+/// Name of the metadata file written into every snapshot directory
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Metadata describing one snapshot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Unix timestamp (seconds) when the snapshot was taken
+    pub created: u64,
+    /// The tool invocation that triggered the snapshot (a bash command or "Write <path>")
+    pub trigger: String,
+    /// Working directory of the tool invocation
+    pub cwd: String,
+    /// Files copied into the snapshot
+    pub files: Vec<SnapshotFile>,
+    /// Files that existed but were not copied because the size or file-count cap was reached
+    #[serde(default)]
+    pub skipped: Vec<String>,
+}
+
+/// A single file stored in a snapshot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Absolute path the file was copied from
+    pub original: String,
+    /// Path of the copy, relative to the snapshot directory
+    pub stored: String,
+}
+
+/// Copy `targets` (files, or directories recursively) into a new snapshot for the session,
+/// then prune the oldest snapshots beyond `checkpoint_keep`.
+/// Returns the snapshot directory, or `None` if none of the targets exist.
+pub fn save(
+    config: &Config,
+    session_id: Option<&str>,
+    trigger: &str,
+    cwd: &str,
+    targets: &[PathBuf],
+) -> io::Result<Option<PathBuf>> {
+    let mut collected = Collected::default();
+    for target in targets {
+        collect_files(target, config, &mut collected);
+    }
+    let files = collected.files;
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let snapshot_dir = config
+        .checkpoint_root()
+        .join(sanitize_session_id(session_id))
+        .join(format!("{}-{}", now.as_millis(), std::process::id()));
+    fs::create_dir_all(snapshot_dir.join("files"))?;
+
+    let mut manifest = Manifest {
+        created: now.as_secs(),
+        trigger: trigger.to_string(),
+        cwd: cwd.to_string(),
+        files: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    let mut total_bytes = 0;
+    for (i, file) in files.iter().enumerate() {
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        if i >= config.checkpoint_max_files || total_bytes + size > config.checkpoint_max_bytes {
+            manifest.skipped.push(file.to_string_lossy().to_string());
+            continue;
+        }
+        let stored = format!("files/{}", i);
+        if fs::copy(file, snapshot_dir.join(&stored)).is_ok() {
+            total_bytes += size;
+            manifest.files.push(SnapshotFile {
+                original: file.to_string_lossy().to_string(),
+                stored,
+            });
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    fs::write(snapshot_dir.join(MANIFEST_FILE), json)?;
+    prune(config);
+    Ok(Some(snapshot_dir))
+}
+
+/// Delete all but the newest `checkpoint_keep` snapshots, and the session directories
+/// they leave empty. Best effort: a snapshot that can't be deleted is left in place.
+fn prune(config: &Config) {
+    if config.checkpoint_keep == 0 {
+        return;
+    }
+    for snapshot in list(config, None).into_iter().skip(config.checkpoint_keep) {
+        if fs::remove_dir_all(&snapshot.dir).is_ok() {
+            if let Some(session_dir) = snapshot.dir.parent() {
+                // Only succeeds once the session has no snapshots left
+                let _ = fs::remove_dir(session_dir);
+            }
+        }
+    }
+}
+
+/// Files gathered for a snapshot
+#[derive(Default)]
+struct Collected {
+    files: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+    bytes: u64,
+}
+
+impl Collected {
+    /// Past the size or file-count cap
+    fn is_full(&self, config: &Config) -> bool {
+        self.bytes > config.checkpoint_max_bytes || self.files.len() > config.checkpoint_max_files
+    }
+}
+
+/// Recursively gather regular files under `path`, without following symlinks. The walk
+/// stops once the gathered files add up to more than `checkpoint_max_bytes` or number
+/// more than `checkpoint_max_files`; the file that crossed the cap is kept so it's
+/// listed as skipped.
+fn collect_files(path: &Path, config: &Config, collected: &mut Collected) {
+    if collected.is_full(config) {
+        return;
+    }
+    let Ok(meta) = path.symlink_metadata() else {
+        return;
+    };
+    if meta.is_file() {
+        if collected.seen.insert(path.to_path_buf()) {
+            collected.bytes += meta.len();
+            collected.files.push(path.to_path_buf());
+        }
+    } else if meta.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        let mut children: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        children.sort();
+        for child in children {
+            collect_files(&child, config, collected);
+            if collected.is_full(config) {
+                break;
+            }
+        }
+    }
+}
+
+/// Session IDs become directory names, so keep only filename-safe characters
+fn sanitize_session_id(session_id: Option<&str>) -> String {
+    let cleaned: String = session_id
+        .unwrap_or("")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    if cleaned.is_empty() {
+        "unknown-session".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// A snapshot found on disk, identified as `<session>/<snapshot>`
+#[derive(Debug)]
+pub struct Snapshot {
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+/// All snapshots under the checkpoint root, newest first, optionally limited to one session
+pub fn list(config: &Config, session: Option<&str>) -> Vec<Snapshot> {
+    let mut snapshots = Vec::new();
+    let Ok(sessions) = fs::read_dir(config.checkpoint_root()) else {
+        return snapshots;
+    };
+
+    for session_entry in sessions.flatten() {
+        let session_name = session_entry.file_name().to_string_lossy().to_string();
+        if session.is_some_and(|s| s != session_name) {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(session_entry.path()) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let Some(manifest) = fs::read_to_string(dir.join(MANIFEST_FILE))
+                .ok()
+                .and_then(|s| serde_json::from_str::<Manifest>(&s).ok())
+            else {
+                continue;
+            };
+            snapshots.push(Snapshot {
+                id: format!("{}/{}", session_name, entry.file_name().to_string_lossy()),
+                dir,
+                manifest,
+            });
+        }
+    }
+
+    snapshots.sort_by(|a, b| b.dir.file_name().cmp(&a.dir.file_name()));
+    snapshots
+}
+
+/// Find a snapshot by full `<session>/<snapshot>` id or by the bare snapshot name
+pub fn find(config: &Config, id: &str) -> Option<Snapshot> {
+    list(config, None)
+        .into_iter()
+        .find(|s| s.id == id || s.id.rsplit('/').next() == Some(id))
+}
+
+/// Copy files from a snapshot back to their original locations.
+/// If `only` is non-empty, restore just the files whose original path is listed.
+/// Returns the restored paths.
+pub fn restore(snapshot: &Snapshot, only: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut restored = Vec::new();
+    for file in &snapshot.manifest.files {
+        let original = PathBuf::from(&file.original);
+        if !only.is_empty() && !only.contains(&original) {
+            continue;
+        }
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(snapshot.dir.join(&file.stored), &original)?;
+        restored.push(original);
+    }
+    Ok(restored)
+}
+
+/// Entry point for `claude-hook restore`. Returns the process exit code.
+///
+/// ```text
+/// claude-hook restore [--session <id>]          # list snapshots, newest first
+/// claude-hook restore <snapshot> [<path>...]    # restore all (or some) files
+/// ```
+pub fn run_restore_command(args: &[String], config: &Config) -> i32 {
+    let mut session = None;
+    let mut positional = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--session" if i + 1 < args.len() => {
+                session = Some(args[i + 1].as_str());
+                i += 2;
+                continue;
+            }
+            "list" if positional.is_empty() => {}
+            other => positional.push(other),
+        }
+        i += 1;
+    }
+
+    let Some(id) = positional.first() else {
+        print_snapshots(&list(config, session));
+        return 0;
+    };
+
+    let Some(snapshot) = find(config, id) else {
+        eprintln!("No checkpoint found with id {}", id);
+        return 1;
+    };
+
+    let cwd = std::env::current_dir()
+        .map(|d| d.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let only: Vec<PathBuf> = positional[1..]
+        .iter()
+        .map(|p| crate::paths::normalize_path(p, &cwd))
+        .collect();
+
+    match restore(&snapshot, &only) {
+        Ok(restored) if restored.is_empty() => {
+            eprintln!("Nothing to restore from {}", snapshot.id);
+            1
+        }
+        Ok(restored) => {
+            for path in restored {
+                println!("Restored {}", path.display());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Restore failed: {}", e);
+            1
+        }
+    }
+}
+
+fn print_snapshots(snapshots: &[Snapshot]) {
+    if snapshots.is_empty() {
+        println!("No checkpoints found");
+        return;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    for s in snapshots {
+        println!(
+            "{}  {} ago  {} file(s)  {}",
+            s.id,
+            format_age(now.saturating_sub(s.manifest.created)),
+            s.manifest.files.len(),
+            s.manifest.trigger
+        );
+    }
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn test_config(root: &TempDir) -> Config {
+        Config {
+            checkpoint_dir: Some(root.path().join("checkpoints").to_string_lossy().to_string()),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let dir = TempDir::new("checkpoint_roundtrip");
+        let config = test_config(&dir);
+        let file = dir.write_file("work/notes.txt", "original");
+        dir.write_file("work/sub/data.txt", "nested");
+
+        let snapshot_dir = save(&config, Some("sess-1"), "rm -r work", &dir.path_str(), &[dir.path().join("work")])
+            .unwrap()
+            .unwrap();
+        assert!(snapshot_dir.starts_with(config.checkpoint_root().join("sess-1")));
+
+        fs::write(&file, "clobbered").unwrap();
+        fs::remove_file(dir.path().join("work/sub/data.txt")).unwrap();
+
+        let snapshots = list(&config, Some("sess-1"));
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].manifest.trigger, "rm -r work");
+        assert_eq!(snapshots[0].manifest.files.len(), 2);
+
+        let restored = restore(&snapshots[0], &[]).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "original");
+        assert_eq!(fs::read_to_string(dir.path().join("work/sub/data.txt")).unwrap(), "nested");
+    }
+
+    #[test]
+    fn test_restore_single_file() {
+        let dir = TempDir::new("checkpoint_single");
+        let config = test_config(&dir);
+        let a = dir.write_file("a.txt", "a");
+        let b = dir.write_file("b.txt", "b");
+        save(&config, Some("s"), "mv a.txt b.txt", &dir.path_str(), &[a.clone(), b.clone()]).unwrap();
+        fs::write(&a, "changed").unwrap();
+        fs::write(&b, "changed").unwrap();
+
+        let snapshot = list(&config, None).remove(0);
+        let found = find(&config, snapshot.id.rsplit('/').next().unwrap()).unwrap();
+        assert_eq!(restore(&found, std::slice::from_ref(&b)).unwrap(), vec![b.clone()]);
+        assert_eq!(fs::read_to_string(&a).unwrap(), "changed");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "b");
+    }
+
+    #[test]
+    fn test_save_nothing_when_targets_missing() {
+        let dir = TempDir::new("checkpoint_missing");
+        let config = test_config(&dir);
+        let result = save(&config, Some("s"), "rm nope", &dir.path_str(), &[dir.path().join("nope")]).unwrap();
+        assert!(result.is_none());
+        assert!(!config.checkpoint_root().exists());
+    }
+
+    #[test]
+    fn test_size_cap_skips_files() {
+        let dir = TempDir::new("checkpoint_cap");
+        let config = Config { checkpoint_max_bytes: 4, ..test_config(&dir) };
+        let small = dir.write_file("small.txt", "abc");
+        let big = dir.write_file("big.txt", "abcdefgh");
+        save(&config, Some("s"), "rm small.txt big.txt", &dir.path_str(), &[small, big.clone()]).unwrap();
+        let snapshot = list(&config, None).remove(0);
+        assert_eq!(snapshot.manifest.files.len(), 1);
+        assert_eq!(snapshot.manifest.skipped, vec![big.to_string_lossy().to_string()]);
+    }
+
+    #[test]
+    fn test_size_cap_stops_walk() {
+        let dir = TempDir::new("checkpoint_cap_walk");
+        let config = Config { checkpoint_max_bytes: 4, ..test_config(&dir) };
+        dir.write_file("work/a.txt", "abc");
+        let big = dir.write_file("work/b.txt", "abcdefgh");
+        dir.write_file("work/c.txt", "x");
+        dir.write_file("work/d/e.txt", "y");
+        save(&config, Some("s"), "rm -r work", &dir.path_str(), &[dir.path().join("work"), dir.path().join("other")]).unwrap();
+        let snapshot = list(&config, None).remove(0);
+        let originals: Vec<&str> = snapshot.manifest.files.iter().map(|f| f.original.as_str()).collect();
+        assert_eq!(originals, vec![dir.path().join("work/a.txt").to_string_lossy()]);
+        assert_eq!(snapshot.manifest.skipped, vec![big.to_string_lossy().to_string()]);
+    }
+
+    #[test]
+    fn test_file_cap_stops_walk() {
+        let dir = TempDir::new("checkpoint_file_cap");
+        let config = Config { checkpoint_max_files: 2, ..test_config(&dir) };
+        for name in ["a", "b", "c", "d"] {
+            dir.write_file(&format!("work/{}.txt", name), name);
+        }
+        let work = dir.path().join("work");
+        save(&config, Some("s"), "rm -r work", &dir.path_str(), &[work.clone(), work.join("a.txt")]).unwrap();
+        let snapshot = list(&config, None).remove(0);
+        let originals: Vec<String> = snapshot.manifest.files.iter().map(|f| f.original.clone()).collect();
+        let path = |name: &str| work.join(name).to_string_lossy().to_string();
+        assert_eq!(originals, vec![path("a.txt"), path("b.txt")]);
+        assert_eq!(snapshot.manifest.skipped, vec![path("c.txt")]);
+    }
+
+    #[test]
+    fn test_duplicate_targets_saved_once() {
+        let dir = TempDir::new("checkpoint_dedup");
+        let config = test_config(&dir);
+        let a = dir.write_file("work/a.txt", "a");
+        save(&config, Some("s"), "rm -r work work/a.txt", &dir.path_str(), &[dir.path().join("work"), a.clone(), a]).unwrap();
+        assert_eq!(list(&config, None).remove(0).manifest.files.len(), 1);
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let dir = TempDir::new("checkpoint_prune");
+        let config = Config { checkpoint_keep: 2, ..test_config(&dir) };
+        let a = dir.write_file("a.txt", "a");
+        for (session, trigger) in [("old", "rm 1"), ("new", "rm 2"), ("new", "rm 3")] {
+            save(&config, Some(session), trigger, &dir.path_str(), std::slice::from_ref(&a)).unwrap();
+            // Snapshot names are millisecond timestamps
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let triggers: Vec<String> = list(&config, None).into_iter().map(|s| s.manifest.trigger).collect();
+        assert_eq!(triggers, vec!["rm 3", "rm 2"]);
+        assert!(!config.checkpoint_root().join("old").exists());
+    }
+
+    #[test]
+    fn test_sanitize_session_id() {
+        assert_eq!(sanitize_session_id(Some("abc-123_x")), "abc-123_x");
+        assert_eq!(sanitize_session_id(Some("../../etc")), "etc");
+        assert_eq!(sanitize_session_id(None), "unknown-session");
+    }
+}
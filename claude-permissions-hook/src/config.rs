@@ -0,0 +1,170 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::package_policy::PackagePolicy;
+use crate::paths;
+use crate::risk::RiskThresholds;
+use crate::rules::database::DatabasePolicy;
+use crate::rules::publish::PublishPolicy;
+use crate::sandbox::SandboxPolicy;
+use crate::tools::cloud::CloudPolicy;
+use crate::tools::iac::InfrastructurePolicy;
+use crate::tools::kube::KubernetesPolicy;
+use crate::tools::remote::RemotePolicy;
+use crate::tools::system_packages::SystemPackagePolicy;
+
+/// Environment variable that overrides the config file location
+const CONFIG_ENV_VAR: &str = "CLAUDE_HOOK_CONFIG";
+
+/// Directory names that are safe to remove recursively: they only hold build
+/// output and can be regenerated by rebuilding
+const DEFAULT_REGENERABLE_DIRS: &[&str] = &[
+    "target", "node_modules", "dist", "build", ".next", ".nuxt", ".turbo",
+    ".parcel-cache", "__pycache__", ".pytest_cache", ".mypy_cache",
+    ".ruff_cache", "coverage",
+];
+
+/// Cap on directory entries examined when expanding a glob pattern
+const DEFAULT_GLOB_LIMIT: usize = 1000;
+
+/// Total size cap for files copied into a single checkpoint
+const DEFAULT_CHECKPOINT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// File count cap for a single checkpoint
+const DEFAULT_CHECKPOINT_MAX_FILES: usize = 10_000;
+
+/// Snapshots kept across all sessions before the oldest are pruned
+const DEFAULT_CHECKPOINT_KEEP: usize = 200;
+
+/// User-tunable settings, read from `~/.claude/hooks/claude-hook.json`.
+/// Every field is optional in the file; missing fields keep their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Directory names that `rm -rf` may remove when they sit inside the current repo
+    pub regenerable_dirs: Vec<String>,
+    /// Copy files aside before destructive commands and overwrites
+    pub checkpoints: bool,
+    /// Where checkpoints are stored (default `~/.claude/hook-checkpoints`)
+    pub checkpoint_dir: Option<String>,
+    /// Files beyond this many bytes per checkpoint are skipped
+    pub checkpoint_max_bytes: u64,
+    /// Files beyond this many per checkpoint are skipped
+    pub checkpoint_max_files: usize,
+    /// Snapshots kept across all sessions; older ones are deleted after each new
+    /// snapshot (0 keeps them all)
+    pub checkpoint_keep: usize,
+    /// Risk score cut-offs for asking and denying
+    pub risk_thresholds: RiskThresholds,
+    /// Directory entries examined per glob pattern before giving up on expanding it
+    pub glob_limit: usize,
+    /// Allow/deny lists and typosquat checking for package installs
+    pub packages: PackagePolicy,
+    /// Scan `python script.py` (and the local modules it imports) for process, file
+    /// deletion and network calls; when off, script runs fall through
+    pub analyze_python_scripts: bool,
+    /// Which publish, release and deploy commands are denied, globally and per project
+    pub publish: PublishPolicy,
+    /// Extra environment settings that mark the database as local for migrations and resets
+    pub database: DatabasePolicy,
+    /// Kube contexts where reads ask and changes are denied
+    pub kubernetes: KubernetesPolicy,
+    /// Terraform workspaces and pulumi stacks where unattended applies ask rather than deny
+    pub infrastructure: InfrastructurePolicy,
+    /// Cloud profiles, projects and apps where reads ask and changes are denied
+    pub cloud: CloudPolicy,
+    /// Packages brew, apt, dnf, pacman and port may install without asking
+    pub system_packages: SystemPackagePolicy,
+    /// Hosts ssh, scp and rsync may connect to without asking
+    pub remote: RemotePolicy,
+    /// Commands that may run with the sandbox disabled without asking
+    pub sandbox: SandboxPolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            regenerable_dirs: to_strings(DEFAULT_REGENERABLE_DIRS),
+            checkpoints: true,
+            checkpoint_dir: None,
+            checkpoint_max_bytes: DEFAULT_CHECKPOINT_MAX_BYTES,
+            checkpoint_max_files: DEFAULT_CHECKPOINT_MAX_FILES,
+            checkpoint_keep: DEFAULT_CHECKPOINT_KEEP,
+            risk_thresholds: RiskThresholds::default(),
+            glob_limit: DEFAULT_GLOB_LIMIT,
+            packages: PackagePolicy::default(),
+            analyze_python_scripts: true,
+            publish: PublishPolicy::default(),
+            database: DatabasePolicy::default(),
+            kubernetes: KubernetesPolicy::default(),
+            infrastructure: InfrastructurePolicy::default(),
+            cloud: CloudPolicy::default(),
+            system_packages: SystemPackagePolicy::default(),
+            remote: RemotePolicy::default(),
+            sandbox: SandboxPolicy::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults if it is missing or malformed
+    pub fn load() -> Self {
+        std::fs::read_to_string(config_path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Check if a directory name is on the regenerable list
+    pub fn is_regenerable_dir(&self, name: &str) -> bool {
+        self.regenerable_dirs.iter().any(|d| d == name)
+    }
+
+    /// Directory holding per-session checkpoint snapshots
+    pub fn checkpoint_root(&self) -> PathBuf {
+        match &self.checkpoint_dir {
+            Some(dir) => PathBuf::from(paths::expand_tilde(dir)),
+            None => PathBuf::from(paths::home_dir()).join(".claude/hook-checkpoints"),
+        }
+    }
+}
+
+/// Location of the config file: `$CLAUDE_HOOK_CONFIG` or `~/.claude/hooks/claude-hook.json`
+fn config_path() -> PathBuf {
+    match std::env::var(CONFIG_ENV_VAR) {
+        Ok(p) if !p.is_empty() => PathBuf::from(p),
+        _ => PathBuf::from(paths::home_dir()).join(".claude/hooks/claude-hook.json"),
+    }
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_regenerable_dirs() {
+        let config = Config::default();
+        assert!(config.is_regenerable_dir("target"));
+        assert!(config.is_regenerable_dir("node_modules"));
+        assert!(config.is_regenerable_dir("__pycache__"));
+        assert!(!config.is_regenerable_dir("src"));
+        assert!(!config.is_regenerable_dir(".git"));
+    }
+
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let config: Config = serde_json::from_str("{}").unwrap();
+        assert!(config.is_regenerable_dir("dist"));
+    }
+
+    #[test]
+    fn test_regenerable_dirs_override() {
+        let config: Config = serde_json::from_str(r#"{"regenerable_dirs": ["out"]}"#).unwrap();
+        assert!(config.is_regenerable_dir("out"));
+        assert!(!config.is_regenerable_dir("target"));
+    }
+}
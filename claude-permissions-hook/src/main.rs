@@ -1,16 +1,37 @@
 mod types;
+mod checkpoint;
+mod config;
+mod package_policy;
+mod package_scripts;
 mod paths;
+mod risk;
+mod rules;
+mod sandbox;
+mod sql;
+mod task_runners;
+mod toml;
+mod tools;
 mod bash_hook;
 mod read_hook;
 mod write_hook;
 mod web_hook;
+mod yaml;
+#[cfg(test)]
+mod test_util;
 
+use config::Config;
 use std::io::Read;
 use types::{Decision, HookInput, HookOutput};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let hook_type = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let config = Config::load();
+
+    // `claude-hook restore` is run by hand, not as a hook — no stdin
+    if hook_type == "restore" {
+        std::process::exit(checkpoint::run_restore_command(&args[2..], &config));
+    }
 
     // Read JSON from stdin
     let mut input_str = String::new();
@@ -28,7 +49,7 @@ fn main() {
     };
 
     let decision = match hook_type {
-        "bash" => bash_hook::evaluate(&input),
+        "bash" => bash_hook::evaluate(&input, &config),
         "read" => read_hook::evaluate(&input),
         "write" => write_hook::evaluate(&input),
         "web" => web_hook::evaluate(&input),
@@ -38,6 +59,10 @@ fn main() {
         }
     };
 
+    if config.checkpoints {
+        save_checkpoint(hook_type, &input, &decision, &config);
+    }
+
     match decision {
         Decision::Allow(reason) => {
             let output = HookOutput::new("allow", &reason);
@@ -56,3 +81,35 @@ fn main() {
         }
     }
 }
+
+/// Snapshot files that the tool call is about to delete or overwrite.
+/// Anything not denied may still run, so Bash commands with a destructive segment
+/// are checkpointed even when the hook abstains on the rest, and so are writes.
+fn save_checkpoint(hook_type: &str, input: &HookInput, decision: &Decision, config: &Config) {
+    if matches!(decision, Decision::Deny(_)) {
+        return;
+    }
+    let (targets, trigger) = match hook_type {
+        "bash" => (
+            bash_hook::checkpoint_targets(input, config),
+            input.get_input_str("command").unwrap_or("").to_string(),
+        ),
+        "write" => (
+            write_hook::checkpoint_targets(input),
+            format!(
+                "{} {}",
+                input.tool_name.as_deref().unwrap_or("Write"),
+                input.get_input_str("file_path").unwrap_or("")
+            ),
+        ),
+        _ => return,
+    };
+    if targets.is_empty() {
+        return;
+    }
+
+    let cwd = input.cwd.as_deref().unwrap_or("");
+    if let Err(e) = checkpoint::save(config, input.session_id.as_deref(), &trigger, cwd, &targets) {
+        eprintln!("Failed to save checkpoint: {}", e);
+    }
+}
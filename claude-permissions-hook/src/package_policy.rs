@@ -0,0 +1,340 @@
+//! Package install policy.
+//!
+//! `npm install <pkg>` and friends run arbitrary install scripts from the registry, so
+//! named packages are checked against the configured allow and deny lists, and names
+//! one edit away from a popular package ask first as possible typosquats (the popular
+//! list can't name every legitimate neighbour, like `color` next to `colors`).
+//! Lockfile-only installs (`npm ci`, `--frozen-lockfile`) fetch nothing new and are safe.
+//! PyPI installs are checked the same way against their own popular list.
+
+use serde::Deserialize;
+
+use crate::paths;
+
+/// Widely used npm packages, the usual targets of typosquatting
+pub const POPULAR_NPM_PACKAGES: &[&str] = &[
+    "react", "react-dom", "react-router", "react-router-dom", "react-redux", "redux",
+    "next", "vue", "vue-router", "svelte", "angular", "jquery", "lodash", "underscore",
+    "ramda", "immer", "mobx", "rxjs", "zod", "yup", "joi", "ajv", "express", "koa",
+    "fastify", "hapi", "body-parser", "cookie-parser", "cors", "helmet", "morgan",
+    "axios", "node-fetch", "got", "superagent", "request", "chalk", "colors", "commander",
+    "yargs", "minimist", "inquirer", "ora", "debug", "dotenv", "cross-env", "uuid",
+    "nanoid", "moment", "dayjs", "date-fns", "luxon", "classnames", "semver", "glob",
+    "rimraf", "mkdirp", "fs-extra", "chokidar", "execa", "concurrently", "nodemon",
+    "typescript", "ts-node", "tsx", "tslib", "eslint", "prettier", "jest", "vitest",
+    "mocha", "chai", "sinon", "webpack", "vite", "rollup", "esbuild", "parcel", "babel",
+    "terser", "uglify-js", "postcss", "autoprefixer", "tailwindcss", "sass", "less",
+    "styled-components", "bootstrap", "husky", "lint-staged", "mongoose", "mongodb",
+    "mysql", "mysql2", "redis", "ioredis", "sequelize", "typeorm", "knex", "prisma",
+    "graphql", "socket.io", "socket.io-client", "jsonwebtoken", "bcrypt", "bcryptjs",
+    "passport", "nodemailer", "multer", "validator", "crypto-js", "winston", "pino",
+    "cheerio", "puppeteer", "playwright", "sharp", "electron", "d3", "three", "chart.js",
+    "ethers", "web3", "openai", "stripe", "aws-sdk", "firebase", "core-js", "qs", "async",
+    "bluebird", "formik", "swr", "preact", "vuex", "pinia",
+];
+
+/// Widely used PyPI packages, in normalized form
+pub const POPULAR_PYPI_PACKAGES: &[&str] = &[
+    "requests", "urllib3", "certifi", "idna", "charset-normalizer", "numpy", "pandas",
+    "scipy", "matplotlib", "seaborn", "plotly", "scikit-learn", "torch", "tensorflow",
+    "keras", "transformers", "tokenizers", "datasets", "huggingface-hub", "safetensors",
+    "accelerate", "xgboost", "lightgbm", "polars", "pyarrow", "dask", "numba", "sympy",
+    "networkx", "pillow", "opencv-python", "django", "flask", "fastapi", "starlette",
+    "pydantic", "uvicorn", "gunicorn", "celery", "redis", "pymongo", "sqlalchemy",
+    "psycopg2", "psycopg2-binary", "psycopg", "alembic", "boto", "boto3", "botocore",
+    "s3transfer", "awscli", "google-cloud-storage", "azure-core", "httpx", "aiohttp",
+    "websockets", "websocket-client", "beautifulsoup4", "lxml", "selenium", "playwright",
+    "scrapy", "openai", "anthropic", "langchain", "jinja2", "markupsafe", "click", "typer",
+    "rich", "tqdm", "colorama", "pyyaml", "toml", "tomli", "python-dateutil", "pytz",
+    "tzdata", "attrs", "typing-extensions", "packaging", "setuptools", "wheel", "pip",
+    "virtualenv", "poetry", "pipenv", "filelock", "platformdirs", "cryptography",
+    "pyopenssl", "pyjwt", "paramiko", "cffi", "pycparser", "protobuf", "grpcio", "psutil",
+    "pytest", "pytest-cov", "coverage", "mypy", "black", "ruff", "flake8", "pylint",
+    "isort", "pre-commit", "sphinx", "docutils", "pygments", "markdown", "jupyter",
+    "ipython", "notebook", "streamlit", "gradio", "python-dotenv", "jmespath", "decorator",
+    "wrapt",
+];
+
+/// Names this short collide with each other (`ws`/`qs`, `vue`/`vuex`), so typosquat
+/// checks skip them
+const MIN_TYPOSQUAT_LEN: usize = 4;
+
+/// npm subcommands (and aliases) that add packages
+const NPM_INSTALL_SUBCOMMANDS: &[&str] = &[
+    "install", "i", "in", "ins", "inst", "insta", "instal", "isnt", "isnta", "isntal",
+    "isntall", "add",
+];
+
+/// npm subcommands that install exactly what the lockfile says
+const NPM_CLEAN_INSTALL_SUBCOMMANDS: &[&str] = &["ci", "clean-install", "ic", "install-clean", "isntall-clean"];
+
+/// Flags that pin an install to the lockfile
+const FROZEN_LOCKFILE_FLAGS: &[&str] = &["--frozen-lockfile", "--immutable", "--ci"];
+
+/// Install flags that take a separate value
+const INSTALL_VALUE_FLAGS: &[&str] = &["--tag", "--registry", "-w", "--workspace", "--filter", "-F", "-C", "--dir", "--cwd"];
+
+/// Allow and deny lists for installed packages. Entries may use `*` globs (`@acme/*`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PackagePolicy {
+    /// Packages that may be installed without asking
+    pub allow: Vec<String>,
+    /// Packages that are never installed
+    pub deny: Vec<String>,
+    /// Ask before installing names one edit away from a popular package
+    pub typosquat_check: bool,
+}
+
+impl Default for PackagePolicy {
+    fn default() -> Self {
+        PackagePolicy { allow: Vec::new(), deny: Vec::new(), typosquat_check: true }
+    }
+}
+
+/// What an install command installs
+#[derive(Debug, PartialEq, Eq)]
+pub enum Install<'a> {
+    /// Exactly what the lockfile pins
+    Lockfile,
+    /// Whatever the manifest asks for, possibly updating the lockfile
+    Manifest,
+    /// The named package specs
+    Packages(Vec<&'a str>),
+}
+
+/// The outcome of checking packages against the policy
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Every package is on the allow list
+    Allowed,
+    /// At least one package is denied, with the reason
+    Denied(String),
+    /// Nothing denied, but a package looks like a typosquat, with the reason
+    Suspicious(String),
+    /// Nothing denied, but not everything is on the allow list
+    Unlisted,
+}
+
+/// Parse an npm/pnpm/yarn/bun install command
+pub fn parse_js_install<'a>(tokens: &[&'a str]) -> Option<Install<'a>> {
+    let (&cmd, args) = tokens.split_first()?;
+    let (subcommand, args) = match args.split_first() {
+        Some((&sub, rest)) if !sub.starts_with('-') => (sub, rest),
+        // Bare `yarn` (or `yarn --frozen-lockfile`) installs
+        _ if cmd == "yarn" => ("install", args),
+        _ => return None,
+    };
+
+    let frozen = args.iter().any(|a| FROZEN_LOCKFILE_FLAGS.contains(a));
+    let packages = install_operands(args);
+    let adds = match cmd {
+        "npm" if NPM_CLEAN_INSTALL_SUBCOMMANDS.contains(&subcommand) => return Some(Install::Lockfile),
+        "npm" => NPM_INSTALL_SUBCOMMANDS.contains(&subcommand),
+        "pnpm" => matches!(subcommand, "add" | "install" | "i"),
+        "yarn" => matches!(subcommand, "add" | "install"),
+        "bun" => matches!(subcommand, "add" | "a" | "install" | "i"),
+        _ => false,
+    };
+    if !adds {
+        return None;
+    }
+
+    Some(if !packages.is_empty() {
+        Install::Packages(packages)
+    } else if frozen {
+        Install::Lockfile
+    } else {
+        Install::Manifest
+    })
+}
+
+/// Package specs among install arguments, skipping flags and their values
+fn install_operands<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    let mut operands = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        if INSTALL_VALUE_FLAGS.contains(&arg) {
+            i += 1;
+        } else if !arg.starts_with('-') {
+            operands.push(paths::strip_quotes(arg));
+        }
+    }
+    operands
+}
+
+/// The registry name in an install spec: `lodash@^4` → `lodash`, `@types/node@20` →
+/// `@types/node`, `alias@npm:real@1` → `real`
+pub fn npm_package_name(spec: &str) -> &str {
+    let version_at = if let Some(scoped) = spec.strip_prefix('@') {
+        scoped.find('@').map(|i| i + 1)
+    } else {
+        spec.find('@')
+    };
+    match version_at {
+        Some(at) => match spec[at + 1..].strip_prefix("npm:") {
+            Some(real) => npm_package_name(real),
+            None => &spec[..at],
+        },
+        None => spec,
+    }
+}
+
+/// The normalized PyPI name in a requirement spec (`Django>=4` → `django`,
+/// `typing_extensions[x]` → `typing-extensions`); `None` for paths, URLs and archives
+pub fn python_package_name(spec: &str) -> Option<String> {
+    if spec.starts_with(['.', '/', '~']) || spec.contains("://") || spec.ends_with(".whl") || spec.ends_with(".tar.gz") || spec.ends_with(".zip") {
+        return None;
+    }
+    let end = spec.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))).unwrap_or(spec.len());
+    let name = &spec[..end];
+    if name.is_empty() {
+        return None;
+    }
+    // PEP 503: case-insensitive, with runs of `-`, `_` and `.` equivalent
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    Some(normalized)
+}
+
+/// Check package names against the policy; `popular` seeds the typosquat check
+pub fn check(names: &[&str], popular: &[&str], policy: &PackagePolicy) -> Verdict {
+    let mut all_allowed = true;
+    let mut suspicious = None;
+    for name in names {
+        if policy.deny.iter().any(|p| paths::glob_match(p, name)) {
+            return Verdict::Denied(format!("Package `{}` is on the deny list", name));
+        }
+        if policy.allow.iter().any(|p| paths::glob_match(p, name)) {
+            continue;
+        }
+        all_allowed = false;
+        if suspicious.is_none() && policy.typosquat_check && name.len() >= MIN_TYPOSQUAT_LEN && !popular.contains(name) {
+            let nearby = popular.iter().filter(|p| p.len() >= MIN_TYPOSQUAT_LEN).find(|p| within_one_edit(name, p));
+            suspicious = nearby.map(|target| {
+                format!("Package `{}` is one edit away from popular package `{}` (possible typosquat)", name, target)
+            });
+        }
+    }
+    if let Some(reason) = suspicious {
+        Verdict::Suspicious(reason)
+    } else if all_allowed {
+        Verdict::Allowed
+    } else {
+        Verdict::Unlisted
+    }
+}
+
+/// True if `a` and `b` differ by exactly one insertion, deletion, substitution or
+/// swap of adjacent characters
+fn within_one_edit(a: &str, b: &str) -> bool {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 || a == b {
+        return false;
+    }
+
+    let prefix = short.iter().zip(long).take_while(|(x, y)| x == y).count();
+    if short.len() == long.len() {
+        let (s, l) = (&short[prefix..], &long[prefix..]);
+        // One substitution, or one adjacent swap
+        s[1..] == l[1..] || (s.len() >= 2 && s[0] == l[1] && s[1] == l[0] && s[2..] == l[2..])
+    } else {
+        short[prefix..] == long[prefix + 1..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_js_install() {
+        assert_eq!(parse_js_install(&["npm", "ci"]), Some(Install::Lockfile));
+        assert_eq!(parse_js_install(&["pnpm", "install", "--frozen-lockfile"]), Some(Install::Lockfile));
+        assert_eq!(parse_js_install(&["yarn", "--immutable"]), Some(Install::Lockfile));
+        assert_eq!(parse_js_install(&["npm", "install"]), Some(Install::Manifest));
+        assert_eq!(parse_js_install(&["yarn"]), Some(Install::Manifest));
+        assert_eq!(
+            parse_js_install(&["npm", "i", "-D", "lodash@^4", "--tag", "next", "@types/node"]),
+            Some(Install::Packages(vec!["lodash@^4", "@types/node"]))
+        );
+        assert_eq!(parse_js_install(&["bun", "add", "zod"]), Some(Install::Packages(vec!["zod"])));
+        assert_eq!(parse_js_install(&["npm", "publish"]), None);
+        assert_eq!(parse_js_install(&["npm", "--version"]), None);
+    }
+
+    #[test]
+    fn test_npm_package_name() {
+        assert_eq!(npm_package_name("lodash"), "lodash");
+        assert_eq!(npm_package_name("lodash@^4.17.0"), "lodash");
+        assert_eq!(npm_package_name("@types/node"), "@types/node");
+        assert_eq!(npm_package_name("@types/node@20"), "@types/node");
+        assert_eq!(npm_package_name("my-react@npm:react@18"), "react");
+    }
+
+    #[test]
+    fn test_python_package_name() {
+        assert_eq!(python_package_name("Django>=4.2"), Some("django".to_string()));
+        assert_eq!(python_package_name("typing_extensions[x]==4"), Some("typing-extensions".to_string()));
+        assert_eq!(python_package_name("zope.interface; python_version > '3'"), Some("zope-interface".to_string()));
+        assert_eq!(python_package_name("ruff@0.5"), Some("ruff".to_string()));
+        assert_eq!(python_package_name("./local/pkg"), None);
+        assert_eq!(python_package_name("git+https://github.com/x/y"), None);
+        assert_eq!(python_package_name("dist/pkg-1.0.whl"), None);
+    }
+
+    #[test]
+    fn test_within_one_edit() {
+        assert!(within_one_edit("expresss", "express"));
+        assert!(within_one_edit("expres", "express"));
+        assert!(within_one_edit("exprezs", "express"));
+        assert!(within_one_edit("lodahs", "lodash"));
+        assert!(!within_one_edit("express", "express"));
+        assert!(!within_one_edit("exprss", "exp"));
+        assert!(!within_one_edit("react", "preact-x"));
+        assert!(!within_one_edit("lodash", "dolash"));
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = PackagePolicy {
+            allow: vec!["@acme/*".to_string(), "zod".to_string()],
+            deny: vec!["event-stream".to_string()],
+            typosquat_check: true,
+        };
+        assert_eq!(check(&["zod", "@acme/ui"], POPULAR_NPM_PACKAGES, &policy), Verdict::Allowed);
+        assert_eq!(check(&["zod", "left-pad"], POPULAR_NPM_PACKAGES, &policy), Verdict::Unlisted);
+        assert_eq!(
+            check(&["event-stream"], POPULAR_NPM_PACKAGES, &policy),
+            Verdict::Denied("Package `event-stream` is on the deny list".to_string())
+        );
+        assert_eq!(
+            check(&["axois"], POPULAR_NPM_PACKAGES, &policy),
+            Verdict::Suspicious("Package `axois` is one edit away from popular package `axios` (possible typosquat)".to_string())
+        );
+        // The deny list still wins over a typosquat earlier in the list
+        assert_eq!(
+            check(&["axois", "event-stream"], POPULAR_NPM_PACKAGES, &policy),
+            Verdict::Denied("Package `event-stream` is on the deny list".to_string())
+        );
+        // Allowlisted neighbours of popular packages install without asking
+        let neighbours = PackagePolicy { allow: vec!["color".to_string(), "mssql".to_string()], ..PackagePolicy::default() };
+        assert_eq!(check(&["color", "mssql"], POPULAR_NPM_PACKAGES, &neighbours), Verdict::Allowed);
+        // Popular names themselves, and short names, aren't typosquats
+        assert_eq!(check(&["react", "pgs"], POPULAR_NPM_PACKAGES, &policy), Verdict::Unlisted);
+        let off = PackagePolicy { typosquat_check: false, ..PackagePolicy::default() };
+        assert_eq!(check(&["axois"], POPULAR_NPM_PACKAGES, &off), Verdict::Unlisted);
+    }
+}
@@ -0,0 +1,890 @@
+//! Package manager script resolution.
+//!
+//! `npm run build` runs whatever `package.json` says `build` is, so the hook looks the
+//! script up (with its `pre`/`post` hooks) and evaluates the body instead of trusting
+//! the script name. Workspace filters (`npm -w`, `pnpm --filter`, `yarn workspace`) and
+//! turbo/nx task indirection are followed where the configuration is static.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// npm subcommands (and aliases) that run a named script
+const NPM_RUN_SUBCOMMANDS: &[&str] = &["run", "run-script", "rum", "urn"];
+
+/// Subcommands that run the lifecycle script of the same name (`npm t` runs `test`)
+const LIFECYCLE_SUBCOMMANDS: &[(&str, &str)] = &[
+    ("test", "test"), ("t", "test"), ("tst", "test"),
+    ("start", "start"), ("stop", "stop"), ("restart", "restart"),
+];
+
+/// pnpm's own subcommands; any other first argument runs the script of that name
+const PNPM_BUILTINS: &[&str] = &[
+    "add", "install", "i", "update", "up", "upgrade", "remove", "rm", "uninstall", "un",
+    "link", "ln", "unlink", "import", "rebuild", "rb", "prune", "fetch", "patch",
+    "patch-commit", "patch-remove", "audit", "list", "ls", "outdated", "why", "publish",
+    "pack", "exec", "dlx", "create", "init", "store", "env", "setup", "server", "root",
+    "bin", "config", "c", "get", "set", "licenses", "deploy", "doctor", "recursive",
+    "help", "dedupe", "approve-builds", "self-update", "cat-file", "cat-index",
+    "find-hash", "ignored-builds", "version",
+];
+
+/// Yarn's own subcommands; any other first argument runs the script of that name
+const YARN_BUILTINS: &[&str] = &[
+    "add", "install", "remove", "upgrade", "up", "info", "init", "link", "unlink", "pack",
+    "publish", "why", "config", "cache", "bin", "dlx", "exec", "node", "npm", "plugin",
+    "set", "version", "constraints", "dedupe", "explain", "patch", "patch-commit",
+    "rebuild", "search", "stage", "unplug", "audit", "autoclean", "check", "create",
+    "generate-lock-entry", "global", "help", "import", "licenses", "list", "login",
+    "logout", "outdated", "owner", "policies", "tag", "team", "upgrade-interactive",
+    "versions",
+];
+
+/// turbo subcommands other than `run`; anything else is a task name
+const TURBO_BUILTINS: &[&str] = &[
+    "prune", "login", "logout", "link", "unlink", "daemon", "gen", "generate", "info",
+    "query", "ls", "bin", "boundaries", "scan", "telemetry", "watch", "completion",
+    "devtools", "docs", "config",
+];
+
+/// nx subcommands that don't run a project target
+const NX_BUILTINS: &[&str] = &[
+    "graph", "show", "list", "report", "reset", "daemon", "format", "format:check",
+    "format:write", "generate", "g", "migrate", "add", "init", "release", "repair", "sync",
+    "sync:check", "connect", "watch", "exec", "view-logs", "login", "logout", "import",
+    "mcp", "affected:graph", "print-affected",
+];
+
+/// Flags that take a separate value, per tool, so the value isn't mistaken for a script
+const NPM_VALUE_FLAGS: &[&str] = &["-w", "--workspace", "--prefix", "--loglevel", "--userconfig", "--cache", "--registry"];
+const PNPM_VALUE_FLAGS: &[&str] = &["-F", "--filter", "--filter-prod", "-C", "--dir", "--reporter", "--loglevel", "--workspace-concurrency"];
+const YARN_VALUE_FLAGS: &[&str] = &["--cwd"];
+const BUN_VALUE_FLAGS: &[&str] = &["-F", "--filter", "--cwd"];
+const TURBO_VALUE_FLAGS: &[&str] = &["-F", "--filter", "--concurrency", "--output-logs", "--cache-dir", "--log-order", "--env-mode", "--log-prefix"];
+const NX_VALUE_FLAGS: &[&str] = &["-t", "--target", "--targets", "-p", "--projects", "-c", "--configuration", "--parallel", "--exclude", "--base", "--head"];
+
+/// How deep below the nx workspace root to look for projects
+const NX_PROJECT_DEPTH: usize = 3;
+
+/// Cap on directory entries examined when expanding workspace globs
+const WORKSPACE_GLOB_LIMIT: usize = 1000;
+
+/// A command a script invocation runs, and the directory it runs in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptCommand {
+    /// Where the command came from, e.g. ``packages/web/package.json script `build` ``
+    pub label: String,
+    pub command: String,
+    pub dir: PathBuf,
+}
+
+/// What a command segment resolves to
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// Not a script invocation
+    NotScript,
+    /// A script invocation whose script can't be found statically
+    Missing,
+    /// The commands the invocation runs, in order
+    Commands(Vec<ScriptCommand>),
+    /// An invocation whose commands can't be determined statically, with why
+    Unresolved(String),
+}
+
+/// Which packages a script invocation runs in
+enum Scope {
+    /// The nearest package containing the working directory
+    Current,
+    /// The workspace root package
+    Root,
+    /// Workspace packages matching any of the filters
+    Filtered(Vec<String>),
+    /// Every workspace package
+    All,
+}
+
+struct Invocation {
+    script: String,
+    args: Vec<String>,
+    dir: PathBuf,
+    scope: Scope,
+    if_present: bool,
+    /// pnpm and yarn run a binary of the same name when no script matches
+    bin_fallback: bool,
+}
+
+/// Resolve a segment's tokens (quotes stripped) to the commands a package script invocation runs
+pub fn resolve(tokens: &[&str], current_dir: &Path) -> Resolution {
+    let Some((&cmd, args)) = tokens.split_first() else {
+        return Resolution::NotScript;
+    };
+
+    let invocation = match cmd {
+        "npm" => parse_npm(args, current_dir),
+        "pnpm" => parse_pnpm(args, current_dir),
+        "yarn" => parse_yarn(args, current_dir, Scope::Current),
+        "bun" => parse_bun(args, current_dir),
+        "turbo" => return resolve_turbo(args, current_dir),
+        "nx" => return resolve_nx(args, current_dir),
+        _ => None,
+    };
+    match invocation {
+        Some(invocation) => resolve_invocation(&invocation, current_dir),
+        None => Resolution::NotScript,
+    }
+}
+
+/// A tool's arguments split into options and positionals. Parsing stops at `--`, or
+/// once `done` says the script name has been seen; what follows is passed through.
+struct ParsedArgs<'a> {
+    options: Vec<(&'a str, Option<&'a str>)>,
+    positionals: Vec<&'a str>,
+    rest: Vec<&'a str>,
+}
+
+impl<'a> ParsedArgs<'a> {
+    fn parse(args: &[&'a str], value_flags: &[&str], done: impl Fn(&[&'a str]) -> bool) -> Self {
+        let mut parsed = ParsedArgs { options: Vec::new(), positionals: Vec::new(), rest: Vec::new() };
+        let mut i = 0;
+        while i < args.len() {
+            let arg = args[i];
+            i += 1;
+            if arg == "--" {
+                parsed.rest.extend(&args[i..]);
+                break;
+            }
+            if arg.len() > 1 && arg.starts_with('-') {
+                match arg.split_once('=') {
+                    Some((flag, value)) if flag.starts_with("--") => parsed.options.push((flag, Some(value))),
+                    _ if value_flags.contains(&arg) => {
+                        parsed.options.push((arg, args.get(i).copied()));
+                        i += 1;
+                    }
+                    _ => parsed.options.push((arg, None)),
+                }
+                continue;
+            }
+            parsed.positionals.push(arg);
+            if done(&parsed.positionals) {
+                let rest = &args[i..];
+                parsed.rest.extend(rest.strip_prefix(&["--"][..]).unwrap_or(rest));
+                break;
+            }
+        }
+        parsed
+    }
+
+    fn has(&self, flags: &[&str]) -> bool {
+        self.options.iter().any(|(flag, _)| flags.contains(flag))
+    }
+
+    fn values(&self, flags: &[&str]) -> Vec<&'a str> {
+        self.options
+            .iter()
+            .filter(|(flag, _)| flags.contains(flag))
+            .filter_map(|(_, value)| *value)
+            .collect()
+    }
+
+    /// The directory given by any of `flags`, resolved against `dir`
+    fn dir(&self, flags: &[&str], dir: &Path) -> PathBuf {
+        match self.values(flags).last() {
+            Some(value) => paths::normalize_path(value, &dir.to_string_lossy()),
+            None => dir.to_path_buf(),
+        }
+    }
+}
+
+/// Script name and arguments from positionals: `run <script> args...` or a lifecycle
+/// shorthand (`test`, `start`, ...)
+fn script_and_args(parsed: &ParsedArgs, run_subcommands: &[&str]) -> Option<(String, Vec<String>)> {
+    let (&first, others) = parsed.positionals.split_first()?;
+    let (script, args) = if run_subcommands.contains(&first) {
+        let (&script, args) = others.split_first()?;
+        (script, args)
+    } else {
+        let (_, script) = LIFECYCLE_SUBCOMMANDS.iter().find(|(sub, _)| *sub == first)?;
+        (*script, others)
+    };
+    let args = args.iter().chain(&parsed.rest).map(|a| a.to_string()).collect();
+    Some((script.to_string(), args))
+}
+
+/// `npm run <script>`, `npm test`, with `-w`/`--workspaces` and `--prefix`
+fn parse_npm(args: &[&str], current_dir: &Path) -> Option<Invocation> {
+    let parsed = ParsedArgs::parse(args, NPM_VALUE_FLAGS, |_| false);
+    let (script, args) = script_and_args(&parsed, NPM_RUN_SUBCOMMANDS)?;
+    let filters = parsed.values(&["-w", "--workspace"]);
+    let scope = if parsed.has(&["-ws", "--workspaces"]) {
+        Scope::All
+    } else if !filters.is_empty() {
+        Scope::Filtered(filters.iter().map(|f| f.to_string()).collect())
+    } else {
+        Scope::Current
+    };
+    Some(Invocation {
+        script,
+        args,
+        dir: parsed.dir(&["--prefix"], current_dir),
+        scope,
+        if_present: parsed.has(&["--if-present"]),
+        bin_fallback: false,
+    })
+}
+
+/// Stop parsing once the script name is known: `run <script>` or `<script>`
+fn script_seen(positionals: &[&str]) -> bool {
+    match positionals {
+        ["run" | "run-script", _] => true,
+        [first] => !matches!(*first, "run" | "run-script" | "workspace" | "workspaces"),
+        _ => positionals.len() > 2,
+    }
+}
+
+/// `pnpm run <script>`, `pnpm <script>`, with `--filter`, `-r`, `-w` and `-C`
+fn parse_pnpm(args: &[&str], current_dir: &Path) -> Option<Invocation> {
+    let parsed = ParsedArgs::parse(args, PNPM_VALUE_FLAGS, script_seen);
+    let first = *parsed.positionals.first()?;
+    let bin_fallback = !PNPM_BUILTINS.contains(&first) && !matches!(first, "run" | "run-script") && !is_lifecycle(first);
+    let (script, args) = if bin_fallback {
+        (first.to_string(), parsed.rest.iter().map(|a| a.to_string()).collect())
+    } else {
+        script_and_args(&parsed, &["run", "run-script"])?
+    };
+    let filters = parsed.values(&["-F", "--filter", "--filter-prod"]);
+    let scope = if !filters.is_empty() {
+        Scope::Filtered(filters.iter().map(|f| f.to_string()).collect())
+    } else if parsed.has(&["-r", "--recursive"]) {
+        Scope::All
+    } else if parsed.has(&["-w", "--workspace-root"]) {
+        Scope::Root
+    } else {
+        Scope::Current
+    };
+    Some(Invocation {
+        script,
+        args,
+        dir: parsed.dir(&["-C", "--dir"], current_dir),
+        scope,
+        if_present: parsed.has(&["--if-present"]),
+        bin_fallback,
+    })
+}
+
+/// `yarn run <script>`, `yarn <script>`, `yarn workspace <name> <script>`,
+/// `yarn workspaces foreach ... run <script>`, with `--cwd`
+fn parse_yarn(args: &[&str], current_dir: &Path, scope: Scope) -> Option<Invocation> {
+    let parsed = ParsedArgs::parse(args, YARN_VALUE_FLAGS, script_seen);
+    let dir = parsed.dir(&["--cwd"], current_dir);
+    match parsed.positionals.as_slice() {
+        ["workspace", name, ..] => {
+            let at = args.iter().position(|a| a == name)?;
+            return parse_yarn(&args[at + 1..], &dir, Scope::Filtered(vec![name.to_string()]));
+        }
+        ["workspaces", ..] => {
+            let at = args.iter().position(|a| *a == "run")?;
+            return parse_yarn(&args[at..], &dir, Scope::All);
+        }
+        _ => {}
+    }
+
+    let first = *parsed.positionals.first()?;
+    let bin_fallback = !YARN_BUILTINS.contains(&first) && first != "run" && !is_lifecycle(first);
+    let (script, args) = if bin_fallback {
+        (first.to_string(), parsed.rest.iter().map(|a| a.to_string()).collect())
+    } else {
+        script_and_args(&parsed, &["run"])?
+    };
+    Some(Invocation { script, args, dir, scope, if_present: false, bin_fallback })
+}
+
+/// `bun run <script>`, with `--filter` and `--cwd`. Other bun subcommands (`bun test`,
+/// `bun build`) are bun's own tools, not scripts.
+fn parse_bun(args: &[&str], current_dir: &Path) -> Option<Invocation> {
+    let parsed = ParsedArgs::parse(args, BUN_VALUE_FLAGS, |p| p.len() == 2);
+    if parsed.positionals.first() != Some(&"run") {
+        return None;
+    }
+    let (script, args) = script_and_args(&parsed, &["run"])?;
+    let filters = parsed.values(&["-F", "--filter"]);
+    let scope = if filters.is_empty() {
+        Scope::Current
+    } else {
+        Scope::Filtered(filters.iter().map(|f| f.to_string()).collect())
+    };
+    Some(Invocation {
+        script,
+        args,
+        dir: parsed.dir(&["--cwd"], current_dir),
+        scope,
+        if_present: parsed.has(&["--if-present"]),
+        bin_fallback: false,
+    })
+}
+
+fn is_lifecycle(subcommand: &str) -> bool {
+    LIFECYCLE_SUBCOMMANDS.iter().any(|(sub, _)| *sub == subcommand)
+}
+
+fn resolve_invocation(invocation: &Invocation, current_dir: &Path) -> Resolution {
+    let packages: Vec<PathBuf> = match &invocation.scope {
+        Scope::Current => nearest_package(&invocation.dir).into_iter().collect(),
+        Scope::Root => workspace_root(&invocation.dir).into_iter().collect(),
+        Scope::All => workspace_root(&invocation.dir)
+            .map(|root| workspace_packages(&root))
+            .unwrap_or_default(),
+        Scope::Filtered(filters) => select_packages(&invocation.dir, filters),
+    };
+    let single = matches!(invocation.scope, Scope::Current | Scope::Root);
+
+    let mut commands = Vec::new();
+    for package in &packages {
+        match script_commands(package, &invocation.script, &invocation.args, current_dir) {
+            Some(found) => commands.extend(found),
+            None if single && invocation.bin_fallback => {
+                // `pnpm tsc --noEmit` runs the `tsc` binary when there's no `tsc` script
+                let mut command = invocation.script.clone();
+                for arg in &invocation.args {
+                    command.push(' ');
+                    command.push_str(arg);
+                }
+                commands.push(ScriptCommand {
+                    label: format!("`{}` binary", invocation.script),
+                    command,
+                    dir: invocation.dir.clone(),
+                });
+            }
+            None if single && !invocation.if_present => return Resolution::Missing,
+            None => {}
+        }
+    }
+
+    if commands.is_empty() && !invocation.if_present {
+        Resolution::Missing
+    } else {
+        Resolution::Commands(commands)
+    }
+}
+
+/// The commands `script` runs in the package at `dir`: `pre<script>`, the script itself
+/// with `args` appended, then `post<script>`. `None` if the package has no such script.
+fn script_commands(dir: &Path, script: &str, args: &[String], current_dir: &Path) -> Option<Vec<ScriptCommand>> {
+    let manifest = dir.join("package.json");
+    let json = read_json(&manifest)?;
+    let scripts = json.get("scripts")?.as_object()?;
+    let body = scripts.get(script)?.as_str()?;
+
+    let mut commands = Vec::new();
+    let mut push = |name: &str, command: String| {
+        commands.push(ScriptCommand {
+            label: format!("{} script `{}`", paths::display_path(&manifest, current_dir), name),
+            command,
+            dir: dir.to_path_buf(),
+        });
+    };
+
+    let pre = format!("pre{}", script);
+    if let Some(body) = scripts.get(&pre).and_then(Value::as_str) {
+        push(&pre, body.to_string());
+    }
+    let mut command = body.to_string();
+    for arg in args {
+        command.push(' ');
+        command.push_str(arg);
+    }
+    push(script, command);
+    let post = format!("post{}", script);
+    if let Some(body) = scripts.get(&post).and_then(Value::as_str) {
+        push(&post, body.to_string());
+    }
+    Some(commands)
+}
+
+/// `turbo run <tasks>` / `turbo <tasks>`: each task's script in every workspace package,
+/// plus the tasks it `dependsOn` in `turbo.json`. Filters are ignored because `^task`
+/// dependencies pull in other packages anyway.
+fn resolve_turbo(args: &[&str], current_dir: &Path) -> Resolution {
+    let parsed = ParsedArgs::parse(args, TURBO_VALUE_FLAGS, |_| false);
+    let mut requested = parsed.positionals.as_slice();
+    match requested.first() {
+        Some(&"run") => requested = &requested[1..],
+        Some(first) if TURBO_BUILTINS.contains(first) => return Resolution::NotScript,
+        _ => {}
+    }
+    if requested.is_empty() {
+        return Resolution::NotScript;
+    }
+    let Some(root) = workspace_root(current_dir) else {
+        return Resolution::Missing;
+    };
+
+    let pipeline = read_json(&root.join("turbo.json"))
+        .and_then(|turbo| turbo.get("tasks").or_else(|| turbo.get("pipeline")).cloned())
+        .unwrap_or(Value::Null);
+    let mut tasks: Vec<String> = Vec::new();
+    let mut pending: Vec<String> = requested.iter().map(|t| t.to_string()).collect();
+    while let Some(task) = pending.pop() {
+        if tasks.contains(&task) {
+            continue;
+        }
+        let depends_on = pipeline.get(&task).and_then(|t| t.get("dependsOn")).and_then(Value::as_array);
+        for dependency in depends_on.into_iter().flatten().filter_map(Value::as_str) {
+            let name = dependency.trim_start_matches('^');
+            let name = name.rsplit_once('#').map_or(name, |(_, task)| task);
+            if !name.starts_with('$') {
+                pending.push(name.to_string());
+            }
+        }
+        tasks.push(task);
+    }
+
+    let packages = workspace_packages(&root);
+    let mut commands = Vec::new();
+    for task in &tasks {
+        // `//#task` is a root task, `pkg#task` runs in one package
+        let (package, task) = match task.rsplit_once('#') {
+            Some(("//", task)) => (Some(root.clone()), task),
+            Some((name, task)) => (packages.iter().find(|p| package_name(p).as_deref() == Some(name)).cloned(), task),
+            None => (None, task.as_str()),
+        };
+        let args: Vec<String> = parsed.rest.iter().map(|a| a.to_string()).collect();
+        let candidates = match package {
+            Some(package) => vec![package],
+            None => packages.clone(),
+        };
+        for package in candidates {
+            commands.extend(script_commands(&package, task, &args, current_dir).unwrap_or_default());
+        }
+    }
+
+    if commands.is_empty() {
+        Resolution::Missing
+    } else {
+        Resolution::Commands(commands)
+    }
+}
+
+/// An nx project: its name and directory
+struct NxProject {
+    name: String,
+    dir: PathBuf,
+}
+
+/// `nx run <project>:<target>`, `nx <target> [project]`, `nx run-many -t <targets>`,
+/// `nx affected -t <targets>`. Targets come from `project.json` (`nx:run-commands`,
+/// `nx:run-script`) or `package.json` scripts; other executors can't be evaluated.
+fn resolve_nx(args: &[&str], current_dir: &Path) -> Resolution {
+    let parsed = ParsedArgs::parse(args, NX_VALUE_FLAGS, |_| false);
+    let Some(&first) = parsed.positionals.first() else {
+        return Resolution::NotScript;
+    };
+    if NX_BUILTINS.contains(&first) {
+        return Resolution::NotScript;
+    }
+    let Some(root) = current_dir.ancestors().find(|d| d.join("nx.json").is_file()) else {
+        return Resolution::Missing;
+    };
+    let projects = nx_projects(root);
+    let split = |values: Vec<&str>| -> Vec<String> {
+        values.iter().flat_map(|v| v.split(',')).filter(|v| !v.is_empty()).map(str::to_string).collect()
+    };
+
+    let (targets, selected, all): (Vec<String>, Vec<String>, bool) = match first {
+        "run" => {
+            let Some((project, rest)) = parsed.positionals.get(1).and_then(|spec| spec.split_once(':')) else {
+                return Resolution::Missing;
+            };
+            let target = rest.split(':').next().unwrap_or(rest);
+            (vec![target.to_string()], vec![project.to_string()], false)
+        }
+        "run-many" | "affected" => {
+            let mut targets = split(parsed.values(&["-t", "--target", "--targets"]));
+            targets.extend(parsed.positionals[1..].iter().map(|t| t.to_string()));
+            let selected = split(parsed.values(&["-p", "--projects"]));
+            let all = selected.is_empty();
+            (targets, selected, all)
+        }
+        target => {
+            let project = match parsed.positionals.get(1) {
+                Some(project) => Some(project.to_string()),
+                None => projects
+                    .iter()
+                    .filter(|p| current_dir.starts_with(&p.dir))
+                    .max_by_key(|p| p.dir.components().count())
+                    .map(|p| p.name.clone()),
+            };
+            let Some(project) = project else {
+                return Resolution::Missing;
+            };
+            (vec![target.to_string()], vec![project], false)
+        }
+    };
+
+    let mut commands = Vec::new();
+    for project in projects.iter().filter(|p| all || selected.contains(&p.name)) {
+        for target in &targets {
+            match nx_target_commands(project, target, root, current_dir) {
+                Some(found) => commands.extend(found),
+                // A single named target must exist; run-many skips projects without it
+                None if !all => return Resolution::Missing,
+                None => {}
+            }
+        }
+    }
+
+    if commands.is_empty() {
+        Resolution::Missing
+    } else {
+        Resolution::Commands(commands)
+    }
+}
+
+/// Commands an nx target runs, or `None` if the target is missing or uses an executor
+/// whose behavior isn't visible in the config
+fn nx_target_commands(project: &NxProject, target: &str, root: &Path, current_dir: &Path) -> Option<Vec<ScriptCommand>> {
+    let project_json = project.dir.join("project.json");
+    let Some(config) = read_json(&project_json)
+        .and_then(|json| json.get("targets").and_then(|t| t.get(target)).cloned())
+    else {
+        return script_commands(&project.dir, target, &[], current_dir);
+    };
+
+    let options = config.get("options").cloned().unwrap_or(Value::Null);
+    match config.get("executor").and_then(Value::as_str) {
+        Some("nx:run-script") => {
+            let script = options.get("script").and_then(Value::as_str)?;
+            script_commands(&project.dir, script, &[], current_dir)
+        }
+        Some("nx:run-commands") | None => {
+            // run-commands runs from the workspace root unless `cwd` says otherwise
+            let dir = match options.get("cwd").and_then(Value::as_str) {
+                Some(cwd) => paths::normalize_path(cwd, &root.to_string_lossy()),
+                None => root.to_path_buf(),
+            };
+            let mut bodies: Vec<&str> = Vec::new();
+            for source in [&config, &options] {
+                bodies.extend(source.get("command").and_then(Value::as_str));
+                for command in source.get("commands").and_then(Value::as_array).into_iter().flatten() {
+                    bodies.extend(command.as_str().or_else(|| command.get("command").and_then(Value::as_str)));
+                }
+            }
+            if bodies.is_empty() {
+                return None;
+            }
+            let label = format!("{} target `{}`", paths::display_path(&project_json, current_dir), target);
+            Some(
+                bodies
+                    .into_iter()
+                    .map(|body| ScriptCommand { label: label.clone(), command: body.to_string(), dir: dir.clone() })
+                    .collect(),
+            )
+        }
+        Some(_) => None,
+    }
+}
+
+/// Projects under an nx workspace root: directories with a `project.json` or
+/// `package.json`. The root counts only with its own `project.json`, since root
+/// `package.json` scripts usually call back into nx.
+fn nx_projects(root: &Path) -> Vec<NxProject> {
+    let mut dirs = Vec::new();
+    if root.join("project.json").is_file() {
+        dirs.push(root.to_path_buf());
+    }
+    collect_project_dirs(root, NX_PROJECT_DEPTH, &mut dirs);
+    dirs.into_iter()
+        .map(|dir| {
+            let name = read_json(&dir.join("project.json"))
+                .and_then(|json| json.get("name").and_then(Value::as_str).map(str::to_string))
+                .or_else(|| package_name(&dir))
+                .unwrap_or_else(|| dir.file_name().unwrap_or_default().to_string_lossy().to_string());
+            NxProject { name, dir }
+        })
+        .collect()
+}
+
+fn collect_project_dirs(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    if depth == 0 {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut children: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            !name.starts_with('.') && name != "node_modules" && name != "dist"
+        })
+        .map(|e| e.path())
+        .collect();
+    children.sort();
+    for child in children {
+        if child.join("project.json").is_file() || child.join("package.json").is_file() {
+            found.push(child.clone());
+        }
+        collect_project_dirs(&child, depth - 1, found);
+    }
+}
+
+/// The nearest directory at or above `dir` with a `package.json`
+fn nearest_package(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find(|d| d.join("package.json").is_file()).map(Path::to_path_buf)
+}
+
+/// The nearest directory at or above `dir` that declares workspaces, either in
+/// `package.json` or `pnpm-workspace.yaml`
+fn workspace_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| {
+            d.join("pnpm-workspace.yaml").is_file()
+                || read_json(&d.join("package.json")).is_some_and(|json| json.get("workspaces").is_some())
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Package directories matched by the workspace root's globs
+fn workspace_packages(root: &Path) -> Vec<PathBuf> {
+    let mut globs: Vec<String> = Vec::new();
+    if let Some(json) = read_json(&root.join("package.json")) {
+        let workspaces = json.get("workspaces");
+        let list = workspaces.and_then(|w| w.get("packages")).or(workspaces);
+        globs.extend(list.and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str).map(str::to_string));
+    }
+    if let Ok(yaml) = std::fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        globs.extend(pnpm_workspace_globs(&yaml));
+    }
+
+    let root_str = root.to_string_lossy();
+    let mut packages = Vec::new();
+    for glob in globs.iter().filter(|g| !g.starts_with('!')) {
+        let pattern = glob.trim_end_matches('/');
+        let dirs = if paths::has_glob_chars(pattern) {
+            paths::expand_glob(pattern, &root_str, WORKSPACE_GLOB_LIMIT).unwrap_or_default()
+        } else {
+            vec![paths::normalize_path(pattern, &root_str)]
+        };
+        for dir in dirs {
+            if dir.join("package.json").is_file() && !packages.contains(&dir) {
+                packages.push(dir);
+            }
+        }
+    }
+    packages.sort();
+    packages
+}
+
+/// Entries of the `packages:` list in `pnpm-workspace.yaml`
+fn pnpm_workspace_globs(yaml: &str) -> Vec<String> {
+    let mut globs = Vec::new();
+    let mut in_packages = false;
+    for line in yaml.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = trimmed.starts_with("packages:");
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            globs.push(paths::strip_quotes(item.trim()).to_string());
+        }
+    }
+    globs
+}
+
+/// Workspace packages (and the root) selected by name, name glob or path filters.
+/// Dependency selectors (`foo...`, `...^foo`) can't be followed statically, so they
+/// select every package.
+fn select_packages(dir: &Path, filters: &[String]) -> Vec<PathBuf> {
+    let Some(root) = workspace_root(dir) else {
+        return Vec::new();
+    };
+    let packages = workspace_packages(&root);
+    if filters.iter().any(|f| f.contains("...")) {
+        return packages;
+    }
+
+    let dir_str = dir.to_string_lossy();
+    let root_str = root.to_string_lossy();
+    std::iter::once(root.clone())
+        .chain(packages)
+        .filter(|package| {
+            let name = package_name(package);
+            filters.iter().any(|filter| {
+                let filter = filter.trim_start_matches('{').trim_end_matches('}');
+                if filter.starts_with('.') || filter.starts_with('/') {
+                    paths::normalize_path(filter, &dir_str) == *package
+                } else {
+                    name.as_deref().is_some_and(|n| paths::glob_match(filter, n))
+                        || paths::normalize_path(filter, &root_str) == *package
+                }
+            })
+        })
+        .collect()
+}
+
+fn package_name(dir: &Path) -> Option<String> {
+    read_json(&dir.join("package.json"))?.get("name")?.as_str().map(str::to_string)
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    let text = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn commands(tokens: &str, dir: &Path) -> Vec<(String, String)> {
+        let tokens: Vec<&str> = tokens.split_whitespace().map(paths::strip_quotes).collect();
+        match resolve(&tokens, dir) {
+            Resolution::Commands(commands) => commands.into_iter().map(|c| (c.label, c.command)).collect(),
+            other => panic!("expected commands, got {:?}", other),
+        }
+    }
+
+    fn resolution(tokens: &str, dir: &Path) -> Resolution {
+        let tokens: Vec<&str> = tokens.split_whitespace().map(paths::strip_quotes).collect();
+        resolve(&tokens, dir)
+    }
+
+    fn pair(label: &str, command: &str) -> (String, String) {
+        (label.to_string(), command.to_string())
+    }
+
+    #[test]
+    fn test_npm_run_with_hooks_and_args() {
+        let dir = TempDir::new("scripts_npm");
+        dir.write_file(
+            "package.json",
+            r#"{"scripts": {"prebuild": "tsc --noEmit", "build": "tsc -b", "postbuild": "echo done", "test": "eslint ."}}"#,
+        );
+        assert_eq!(
+            commands("npm run build -- --verbose", dir.path()),
+            vec![
+                pair("package.json script `prebuild`", "tsc --noEmit"),
+                pair("package.json script `build`", "tsc -b --verbose"),
+                pair("package.json script `postbuild`", "echo done"),
+            ]
+        );
+        assert_eq!(commands("npm t", dir.path()), vec![pair("package.json script `test`", "eslint .")]);
+        assert_eq!(commands("yarn build", dir.path()).len(), 3);
+        assert_eq!(commands("bun run test", dir.path()), vec![pair("package.json script `test`", "eslint .")]);
+    }
+
+    #[test]
+    fn test_nearest_package_from_subdir() {
+        let dir = TempDir::new("scripts_nearest");
+        dir.write_file("package.json", r#"{"scripts": {"lint": "eslint ."}}"#);
+        let sub = dir.create_dir("src/components");
+        assert_eq!(
+            commands("pnpm lint", &sub),
+            vec![(format!("{}/package.json script `lint`", dir.path_str()), "eslint .".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_missing_and_not_scripts() {
+        let dir = TempDir::new("scripts_missing");
+        dir.write_file("package.json", r#"{"scripts": {"lint": "eslint ."}}"#);
+        assert_eq!(resolution("npm run deploy", dir.path()), Resolution::Missing);
+        assert_eq!(resolution("npm run deploy --if-present", dir.path()), Resolution::Commands(Vec::new()));
+        assert_eq!(resolution("npm install", dir.path()), Resolution::NotScript);
+        assert_eq!(resolution("npm run", dir.path()), Resolution::NotScript);
+        assert_eq!(resolution("bun test", dir.path()), Resolution::NotScript);
+        assert_eq!(resolution("yarn add react", dir.path()), Resolution::NotScript);
+        // No package.json at all
+        let empty = TempDir::new("scripts_none");
+        assert_eq!(resolution("npm test", empty.path()), Resolution::Missing);
+    }
+
+    #[test]
+    fn test_pnpm_bin_fallback() {
+        let dir = TempDir::new("scripts_bin_fallback");
+        dir.write_file("package.json", r#"{"scripts": {}}"#);
+        assert_eq!(commands("pnpm tsc --noEmit", dir.path()), vec![pair("`tsc` binary", "tsc --noEmit")]);
+    }
+
+    fn make_workspace() -> TempDir {
+        let dir = TempDir::new("scripts_workspace");
+        dir.write_file("package.json", r#"{"name": "root", "workspaces": ["packages/*"], "scripts": {"build": "turbo run build"}}"#);
+        dir.write_file("packages/web/package.json", r#"{"name": "@acme/web", "scripts": {"build": "tsc -b", "codegen": "echo gen"}}"#);
+        dir.write_file("packages/api/package.json", r#"{"name": "@acme/api", "scripts": {"build": "cargo build", "release": "wrangler deploy"}}"#);
+        dir.write_file("turbo.json", r#"{"tasks": {"build": {"dependsOn": ["^build", "codegen"]}}}"#);
+        dir
+    }
+
+    #[test]
+    fn test_workspace_filters() {
+        let dir = make_workspace();
+        let cwd = dir.path();
+        assert_eq!(
+            commands("pnpm --filter @acme/web build", cwd),
+            vec![pair("packages/web/package.json script `build`", "tsc -b")]
+        );
+        assert_eq!(
+            commands("npm run build -w packages/api", cwd),
+            vec![pair("packages/api/package.json script `build`", "cargo build")]
+        );
+        assert_eq!(
+            commands("yarn workspace @acme/api run release", cwd),
+            vec![pair("packages/api/package.json script `release`", "wrangler deploy")]
+        );
+        assert_eq!(
+            commands("pnpm -r release", cwd),
+            vec![pair("packages/api/package.json script `release`", "wrangler deploy")]
+        );
+        assert_eq!(commands("pnpm -F '@acme/*' build", cwd).len(), 2);
+        assert_eq!(resolution("pnpm --filter @acme/web release", cwd), Resolution::Missing);
+    }
+
+    #[test]
+    fn test_turbo_follows_depends_on() {
+        let dir = make_workspace();
+        assert_eq!(
+            commands("turbo run build", dir.path()),
+            vec![
+                pair("packages/api/package.json script `build`", "cargo build"),
+                pair("packages/web/package.json script `build`", "tsc -b"),
+                pair("packages/web/package.json script `codegen`", "echo gen"),
+            ]
+        );
+        assert_eq!(resolution("turbo run nope", dir.path()), Resolution::Missing);
+        assert_eq!(resolution("turbo prune web", dir.path()), Resolution::NotScript);
+    }
+
+    #[test]
+    fn test_nx_targets() {
+        let dir = TempDir::new("scripts_nx");
+        dir.write_file("nx.json", "{}");
+        dir.write_file("package.json", r#"{"scripts": {"build": "nx run-many -t build"}}"#);
+        dir.write_file(
+            "apps/web/project.json",
+            r#"{"name": "web", "targets": {"build": {"executor": "nx:run-commands", "options": {"commands": ["tsc -b", {"command": "echo ok"}]}}, "serve": {"executor": "@nx/vite:dev-server"}}}"#,
+        );
+        dir.write_file("libs/util/package.json", r#"{"name": "util", "scripts": {"build": "tsc"}}"#);
+        let cwd = dir.path();
+
+        assert_eq!(
+            commands("nx run web:build", cwd),
+            vec![pair("apps/web/project.json target `build`", "tsc -b"), pair("apps/web/project.json target `build`", "echo ok")]
+        );
+        assert_eq!(commands("nx build util", cwd), vec![pair("libs/util/package.json script `build`", "tsc")]);
+        assert_eq!(commands("nx run-many -t build", cwd).len(), 3);
+        assert_eq!(commands("nx build", &dir.path().join("libs/util")).len(), 1);
+        assert_eq!(resolution("nx serve web", cwd), Resolution::Missing);
+        assert_eq!(resolution("nx graph", cwd), Resolution::NotScript);
+    }
+
+    #[test]
+    fn test_pnpm_workspace_yaml() {
+        let yaml = "packages:\n  - 'apps/*'\n  - \"libs/*\"\n  - '!**/test/**'\ncatalog:\n  - nope\n";
+        assert_eq!(pnpm_workspace_globs(yaml), vec!["apps/*", "libs/*", "!**/test/**"]);
+    }
+}
@@ -56,6 +56,15 @@ pub fn is_in_allowed_dir(path: &Path, cwd: &str) -> bool {
     false
 }
 
+/// Check if a path falls outside the current repo, or outside the allowed
+/// directories when not in a repo
+pub fn is_outside_project(path: &Path, repo_root: Option<&Path>, current_dir: &str) -> bool {
+    match repo_root {
+        Some(root) => !path.starts_with(root),
+        None => !is_in_allowed_dir(path, current_dir),
+    }
+}
+
 /// Check if a filename is a secrets file (.env, .env.*, .dev.vars)
 pub fn is_secrets_file(path: &str) -> bool {
     let basename = Path::new(path)
@@ -82,17 +91,331 @@ pub fn glob_targets_secrets(pattern: &str) -> bool {
 }
 
 /// Strip matching surrounding quotes (single or double) from a string
-fn strip_quotes(s: &str) -> &str {
-    if s.len() >= 2 {
-        if (s.starts_with('"') && s.ends_with('"'))
-            || (s.starts_with('\'') && s.ends_with('\''))
-        {
-            return &s[1..s.len() - 1];
-        }
+pub fn strip_quotes(s: &str) -> &str {
+    if s.len() >= 2
+        && ((s.starts_with('"') && s.ends_with('"'))
+            || (s.starts_with('\'') && s.ends_with('\'')))
+    {
+        return &s[1..s.len() - 1];
     }
     s
 }
 
+/// Split a command segment into shell words, removing quotes and backslash escapes
+/// while keeping the whitespace inside quoted words (SQL, heredoc bodies)
+pub fn shell_words(segment: &str) -> Vec<String> {
+    shell_words_quoted(segment).into_iter().map(|(word, _)| word).collect()
+}
+
+/// `shell_words`, with whether any part of each word was quoted or escaped (and so
+/// isn't glob- or brace-expanded)
+pub fn shell_words_quoted(segment: &str) -> Vec<(String, bool)> {
+    let mut words = Vec::new();
+    let mut word: Option<(String, bool)> = None;
+    let mut chars = segment.chars();
+    let mut quote: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => match chars.next() {
+                Some(next @ ('"' | '\\' | '$' | '`')) => word.get_or_insert_default().0.push(next),
+                Some(next) => word.get_or_insert_default().0.extend(['\\', next]),
+                None => word.get_or_insert_default().0.push('\\'),
+            },
+            (Some(_), c) => word.get_or_insert_default().0.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_default().1 = true;
+            }
+            (None, '\\') => {
+                let (text, quoted) = word.get_or_insert_default();
+                text.extend(chars.next());
+                *quoted = true;
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().0.push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Quote a word for the shell when it has whitespace or special characters, so a
+/// command rebuilt from `shell_words` splits the same way
+pub fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_alphanumeric() || "-_./=:,@%+".contains(c)) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// The user's home directory
+pub fn home_dir() -> String {
+    std::env::var("HOME").unwrap_or_else(|_| "/Users/araftery".to_string())
+}
+
+/// Expand a leading `~` to the user's home directory
+pub fn expand_tilde(path: &str) -> String {
+    if path == "~" {
+        home_dir()
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        format!("{}/{}", home_dir(), rest)
+    } else {
+        path.to_string()
+    }
+}
+
+/// Find the root of the git repository containing `dir` (the nearest ancestor with a `.git` entry)
+pub fn find_repo_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Canonicalize the nearest existing ancestor of `path`, resolving any symlinks along the way
+pub fn canonicalize_existing(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find_map(|p| p.canonicalize().ok())
+}
+
+/// Check if a token contains shell glob metacharacters
+pub fn has_glob_chars(s: &str) -> bool {
+    s.bytes().any(|b| matches!(b, b'*' | b'?' | b'['))
+}
+
+/// Match a single path component against a glob pattern (`*`, `?`, `[...]`)
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    // On a mismatch, backtrack only to the last `*` and let it take one more character,
+    // which keeps matching linear per star instead of exponential
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if p.get(pi) == Some(&'*') {
+            pi += 1;
+            star = Some((pi, ni));
+        } else if let Some(next) = match_one(&p, pi, n[ni]) {
+            pi = next;
+            ni += 1;
+        } else if let Some((after_star, start)) = star {
+            pi = after_star;
+            ni = start + 1;
+            star = Some((after_star, ni));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// The index after the pattern element at `p[pi]` if it matches `c`
+fn match_one(p: &[char], pi: usize, c: char) -> Option<usize> {
+    match *p.get(pi)? {
+        '?' => Some(pi + 1),
+        '[' => {
+            let Some(close) = p[pi..].iter().skip(2).position(|c| *c == ']').map(|i| pi + i + 2) else {
+                // Unterminated class — treat `[` literally
+                return (c == '[').then_some(pi + 1);
+            };
+            let (negated, class) = match p[pi + 1] {
+                '!' | '^' => (true, &p[pi + 2..close]),
+                _ => (false, &p[pi + 1..close]),
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            (matched != negated).then_some(close + 1)
+        }
+        literal => (literal == c).then_some(pi + 1),
+    }
+}
+
+/// Expand a glob pattern against the filesystem, relative to `cwd`.
+/// Returns `None` if more than `limit` entries would need to be examined.
+/// Like the shell, `*` and `?` don't match a leading `.` unless the pattern does.
+pub fn expand_glob(pattern: &str, cwd: &str, limit: usize) -> Option<Vec<PathBuf>> {
+    let absolute = normalize_path(pattern, cwd);
+    let absolute = absolute.to_string_lossy();
+
+    // Leading literal directories need no listing; a missing base matches nothing
+    let first_glob = absolute.bytes().position(|b| matches!(b, b'*' | b'?' | b'[')).unwrap_or(absolute.len());
+    let base_end = absolute[..first_glob].rfind('/').unwrap_or(0);
+    let base = PathBuf::from(if base_end == 0 { "/" } else { &absolute[..base_end] });
+    if !base.is_dir() {
+        return Some(Vec::new());
+    }
+
+    let mut candidates = vec![base];
+    let mut examined = 0;
+
+    for part in absolute[base_end..].split('/').filter(|p| !p.is_empty()) {
+        if !has_glob_chars(part) {
+            for c in candidates.iter_mut() {
+                c.push(part);
+            }
+            continue;
+        }
+
+        let mut next = Vec::new();
+        for dir in &candidates {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                examined += 1;
+                if examined > limit {
+                    return None;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') && !part.starts_with('.') {
+                    continue;
+                }
+                if glob_match(part, &name) {
+                    next.push(dir.join(&name));
+                }
+            }
+        }
+        candidates = next;
+    }
+
+    let mut matches: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter(|p| p.symlink_metadata().is_ok())
+        .collect();
+    matches.sort();
+    Some(matches)
+}
+
+/// Cap on words produced by brace expansion
+const BRACE_EXPANSION_LIMIT: usize = 1024;
+
+/// Check if a word contains a brace expansion (`{a,b}` or `{1..3}`)
+pub fn has_brace_expansion(word: &str) -> bool {
+    word.bytes().any(|b| b == b'{') && find_brace_group(word).is_some()
+}
+
+/// Expand `{a,b}` alternations and `{1..3}` / `{a..c}` sequences like the shell does.
+/// Words without a valid brace group come back unchanged.
+pub fn expand_braces(word: &str) -> Vec<String> {
+    let mut done = Vec::new();
+    let mut pending = vec![word.to_string()];
+    while let Some(w) = pending.pop() {
+        match find_brace_group(&w) {
+            Some((open, close, alternatives)) if done.len() + pending.len() < BRACE_EXPANSION_LIMIT => {
+                for alt in alternatives.into_iter().rev() {
+                    pending.push(format!("{}{}{}", &w[..open], alt, &w[close + 1..]));
+                }
+            }
+            _ => done.push(w),
+        }
+    }
+    done
+}
+
+/// Locate the first brace group in a word, returning its byte span and alternatives
+fn find_brace_group(word: &str) -> Option<(usize, usize, Vec<String>)> {
+    let bytes = word.as_bytes();
+    for open in (0..bytes.len()).filter(|&i| bytes[i] == b'{') {
+        if open > 0 && bytes[open - 1] == b'$' {
+            continue; // `${VAR}` is parameter expansion
+        }
+        let mut depth = 0;
+        let mut commas = Vec::new();
+        for (i, &b) in bytes.iter().enumerate().skip(open) {
+            match b {
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let inner = &word[open + 1..i];
+                        if !commas.is_empty() {
+                            let mut alternatives = Vec::new();
+                            let mut start = open + 1;
+                            for &c in &commas {
+                                alternatives.push(word[start..c].to_string());
+                                start = c + 1;
+                            }
+                            alternatives.push(word[start..i].to_string());
+                            return Some((open, i, alternatives));
+                        }
+                        if let Some(seq) = expand_sequence(inner) {
+                            return Some((open, i, seq));
+                        }
+                        break;
+                    }
+                }
+                b',' if depth == 1 => commas.push(i),
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Expand the inside of a `{1..5}` or `{a..e}` sequence
+fn expand_sequence(inner: &str) -> Option<Vec<String>> {
+    let (start, end) = inner.split_once("..")?;
+    if let (Ok(a), Ok(b)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        if (a - b).unsigned_abs() as usize >= BRACE_EXPANSION_LIMIT {
+            return None;
+        }
+        let seq: Vec<String> = if a <= b {
+            (a..=b).map(|n| n.to_string()).collect()
+        } else {
+            (b..=a).rev().map(|n| n.to_string()).collect()
+        };
+        return Some(seq);
+    }
+    let (mut a, mut b) = (start.chars(), end.chars());
+    match (a.next(), a.next(), b.next(), b.next()) {
+        (Some(x), None, Some(y), None) if x.is_ascii_alphabetic() && y.is_ascii_alphabetic() => {
+            let seq: Vec<String> = if x <= y {
+                (x..=y).map(String::from).collect()
+            } else {
+                (y..=x).rev().map(String::from).collect()
+            };
+            Some(seq)
+        }
+        _ => None,
+    }
+}
+
+/// Expand a shell word against the filesystem: braces first, then globs.
+/// Brace alternatives without glob characters are kept whether or not they exist;
+/// glob patterns yield only existing matches. Returns `None` past the entry limit.
+pub fn expand_word(word: &str, cwd: &str, limit: usize) -> Option<Vec<PathBuf>> {
+    let mut results = Vec::new();
+    let mut remaining = limit;
+    let alternatives = if has_brace_expansion(word) {
+        expand_braces(word)
+    } else {
+        vec![word.to_string()]
+    };
+    for alternative in alternatives {
+        if has_glob_chars(&alternative) {
+            let matches = expand_glob(&alternative, cwd, remaining)?;
+            remaining = remaining.saturating_sub(matches.len());
+            results.extend(matches);
+        } else {
+            results.push(normalize_path(&alternative, cwd));
+        }
+    }
+    Some(results)
+}
+
+/// `path` relative to `base` when it's underneath, otherwise absolute
+pub fn display_path(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).to_string_lossy().to_string()
+}
+
 /// Check if any argument in a list of tokens references a secrets file
 pub fn args_reference_secrets(args: &[&str]) -> bool {
     for arg in args {
@@ -112,6 +435,23 @@ pub fn args_reference_secrets(args: &[&str]) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_shell_words() {
+        assert_eq!(shell_words(r#"psql -c "select  1;" x"#), vec!["psql", "-c", "select  1;", "x"]);
+        assert_eq!(shell_words("a 'it'\\''s' \"q\\\"\\d\" b\\ c ''"), vec!["a", "it's", "q\"\\d", "b c", ""]);
+        assert_eq!(shell_words("x <<< 'line one\nline two'"), vec!["x", "<<<", "line one\nline two"]);
+        let quoted = |segment: &str| shell_words_quoted(segment).into_iter().map(|(_, quoted)| quoted).collect::<Vec<_>>();
+        assert_eq!(quoted(r#"grep "a * b" src/*.rs x\*"#), vec![false, true, false, true]);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("deploy/web-1"), "deploy/web-1");
+        assert_eq!(shell_quote("ls /data"), "'ls /data'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
     #[test]
     fn test_normalize_path_absolute() {
         let result = normalize_path("/Users/araftery/workspace/src/index.ts", "/tmp");
@@ -156,6 +496,86 @@ mod tests {
         assert!(!is_secrets_file("env.txt"));
     }
 
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.log", "app.log"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(glob_match("[abc]x", "bx"));
+        assert!(glob_match("[a-c]x", "cx"));
+        assert!(glob_match("[!a]x", "bx"));
+        assert!(!glob_match("[!a]x", "ax"));
+        assert!(!glob_match("*.log", "app.txt"));
+        assert!(!glob_match("file?.txt", "file.txt"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("*[", "x["));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("a*b", "acb c"));
+    }
+
+    #[test]
+    fn test_glob_match_many_stars() {
+        // Recursive backtracking took minutes on this
+        let start = std::time::Instant::now();
+        assert!(!glob_match("*a*a*a*a*a*a*a*a*a*b", &"a".repeat(40)));
+        assert!(glob_match("*a*a*a*a*a*a*a*a*a*b", &format!("{}b", "a".repeat(40))));
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = crate::test_util::TempDir::new("expand_glob");
+        dir.create_file("a.log");
+        dir.create_file("b.log");
+        dir.create_file(".hidden.log");
+        dir.create_file("sub/c.log");
+        let cwd = dir.path_str();
+
+        let matches = expand_glob("*.log", &cwd, 100).unwrap();
+        assert_eq!(matches, vec![dir.path().join("a.log"), dir.path().join("b.log")]);
+
+        let matches = expand_glob("*/c.log", &cwd, 100).unwrap();
+        assert_eq!(matches, vec![dir.path().join("sub/c.log")]);
+
+        assert!(expand_glob("*.rs", &cwd, 100).unwrap().is_empty());
+        assert!(expand_glob("*.log", &cwd, 2).is_none());
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("src/{a,b}.rs"), vec!["src/a.rs", "src/b.rs"]);
+        assert_eq!(expand_braces("{x,y}{1,2}"), vec!["x1", "x2", "y1", "y2"]);
+        assert_eq!(expand_braces("a{b,{c,d}}e"), vec!["abe", "ace", "ade"]);
+        assert_eq!(expand_braces("f{1..3}"), vec!["f1", "f2", "f3"]);
+        assert_eq!(expand_braces("{c..a}"), vec!["c", "b", "a"]);
+        assert_eq!(expand_braces("${HOME}/x"), vec!["${HOME}/x"]);
+        assert_eq!(expand_braces("{single}"), vec!["{single}"]);
+        assert!(has_brace_expansion(".{env,bashrc}"));
+        assert!(!has_brace_expansion("find . -exec {} ;"));
+    }
+
+    #[test]
+    fn test_expand_word() {
+        let dir = crate::test_util::TempDir::new("expand_word");
+        dir.create_file("a.log");
+        dir.create_file("b.txt");
+        let cwd = dir.path_str();
+        assert_eq!(
+            expand_word("{*.log,missing.md}", &cwd, 100).unwrap(),
+            vec![dir.path().join("a.log"), dir.path().join("missing.md")]
+        );
+        assert_eq!(expand_word("b.txt", &cwd, 100).unwrap(), vec![dir.path().join("b.txt")]);
+    }
+
+    #[test]
+    fn test_find_repo_root() {
+        let dir = crate::test_util::TempDir::new("find_repo_root");
+        dir.create_dir(".git");
+        dir.create_dir("a/b");
+        assert_eq!(find_repo_root(&dir.path().join("a/b")), Some(dir.path().to_path_buf()));
+        assert_eq!(find_repo_root(Path::new("/nonexistent/dir")), None);
+    }
+
     #[test]
     fn test_glob_targets_secrets() {
         assert!(glob_targets_secrets("**/.env*"));
@@ -91,6 +91,7 @@ mod tests {
             tool_name: Some("Read".to_string()),
             tool_input: Some(tool_input),
             cwd: Some(cwd.to_string()),
+            session_id: None,
         }
     }
 
@@ -104,6 +105,7 @@ mod tests {
             tool_name: Some("Glob".to_string()),
             tool_input: Some(tool_input),
             cwd: Some(cwd.to_string()),
+            session_id: None,
         }
     }
 
@@ -115,6 +117,7 @@ mod tests {
             tool_name: Some("Grep".to_string()),
             tool_input: Some(tool_input),
             cwd: Some(cwd.to_string()),
+            session_id: None,
         }
     }
 
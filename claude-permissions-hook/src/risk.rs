@@ -0,0 +1,354 @@
+//! Risk scoring for recognized bash commands.
+//!
+//! Each segment gets an additive score from the verb's severity, how many files it
+//! touches, where those files live, whether git can bring them back, and whether it
+//! talks to the network. `Config::risk_thresholds` maps the score to allow/ask/deny.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::paths;
+
+/// Base severity of destructive verbs, keyed by the name `bash_hook` assigns them
+const VERB_SEVERITY: &[(&str, u32)] = &[
+    ("rm", 30),
+    ("rm -r", 40),
+    ("mv", 20),
+    ("chmod", 15),
+    ("chmod -R", 25),
+    ("chown", 20),
+    ("chown -R", 30),
+    ("sed -i", 15),
+    ("git rm", 15),
+    ("git rebase", 20),
+    ("git reset --hard", 35),
+    ("git checkout", 25),
+    ("git clean", 35),
+    ("git push --force", 40),
+    ("git --force", 25),
+];
+
+/// Verbs whose damage is to file contents, so git history can undo it
+const CONTENT_VERBS: &[&str] = &["rm", "rm -r", "mv", "sed -i", "git rm"];
+
+/// Points added for talking to the network
+const NETWORK_POINTS: u32 = 15;
+
+/// Cap on directory entries walked when counting files under a recursive target
+const FILE_COUNT_LIMIT: usize = 1000;
+
+/// Score cut-offs: below `ask` is allowed, `ask..deny` asks, `deny` and above is denied
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RiskThresholds {
+    pub ask: u32,
+    pub deny: u32,
+}
+
+impl Default for RiskThresholds {
+    fn default() -> Self {
+        RiskThresholds { ask: 20, deny: 80 }
+    }
+}
+
+/// What the score maps to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskLevel {
+    Allow,
+    Ask,
+    Deny,
+}
+
+/// Where a command's targets live, from least to most sensitive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Location {
+    Repo,
+    Temp,
+    Workspace,
+    Home,
+    System,
+}
+
+impl Location {
+    fn points(self) -> u32 {
+        match self {
+            Location::Repo | Location::Temp => 0,
+            Location::Workspace => 5,
+            Location::Home => 20,
+            Location::System => 40,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Location::Repo => "repo",
+            Location::Temp => "temp",
+            Location::Workspace => "workspace",
+            Location::Home => "home",
+            Location::System => "system",
+        }
+    }
+}
+
+/// A recognized command, described for scoring
+#[derive(Debug, Default)]
+pub struct Assessment<'a> {
+    /// Destructive verb name (a key of `VERB_SEVERITY`), or `None` for safe commands
+    pub verb: Option<&'a str>,
+    /// Files and directories the command acts on, already normalized and glob-expanded
+    pub targets: Vec<PathBuf>,
+    /// Whether directory targets are affected recursively
+    pub recursive: bool,
+    /// Whether the command talks to the network
+    pub network: bool,
+}
+
+/// A score with the factors that produced it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiskScore {
+    pub total: u32,
+    factors: Vec<(String, i32)>,
+}
+
+impl RiskScore {
+    fn add(&mut self, label: impl Into<String>, points: i32) {
+        if points == 0 {
+            return;
+        }
+        self.total = self.total.saturating_add_signed(points);
+        self.factors.push((label.into(), points));
+    }
+
+    /// Map the score to a decision level
+    pub fn level(&self, thresholds: &RiskThresholds) -> RiskLevel {
+        if self.total >= thresholds.deny {
+            RiskLevel::Deny
+        } else if self.total >= thresholds.ask {
+            RiskLevel::Ask
+        } else {
+            RiskLevel::Allow
+        }
+    }
+
+    /// `risk 45: rm +30, workspace target +5, not recoverable from git +10`
+    pub fn breakdown(&self) -> String {
+        if self.factors.is_empty() {
+            return format!("risk {}", self.total);
+        }
+        let factors: Vec<String> = self
+            .factors
+            .iter()
+            .map(|(label, points)| format!("{} {:+}", label, points))
+            .collect();
+        format!("risk {}: {}", self.total, factors.join(", "))
+    }
+}
+
+/// Score a recognized command run from `current_dir`
+pub fn score(assessment: &Assessment, current_dir: &str) -> RiskScore {
+    let mut score = RiskScore::default();
+
+    if let Some(verb) = assessment.verb {
+        let severity = VERB_SEVERITY
+            .iter()
+            .find(|(v, _)| *v == verb)
+            .map_or(20, |(_, s)| *s);
+        score.add(verb, severity as i32);
+    }
+
+    if !assessment.targets.is_empty() {
+        let repo_root = paths::find_repo_root(Path::new(current_dir));
+
+        let count = count_files(&assessment.targets, assessment.recursive);
+        let count_points = match count {
+            0..=1 => 0,
+            2..=5 => 5,
+            6..=50 => 10,
+            51..=500 => 20,
+            _ => 30,
+        };
+        let count_label = if count >= FILE_COUNT_LIMIT {
+            format!("{}+ files", FILE_COUNT_LIMIT)
+        } else {
+            format!("{} files", count)
+        };
+        score.add(count_label, count_points);
+
+        let location = assessment
+            .targets
+            .iter()
+            .map(|t| classify_location(t, repo_root.as_deref(), current_dir))
+            .max()
+            .unwrap_or(Location::Repo);
+        score.add(format!("{} target", location.label()), location.points() as i32);
+
+        if assessment.verb.is_some_and(|v| CONTENT_VERBS.contains(&v)) {
+            match repo_root {
+                Some(root) if git_can_restore(&root, &assessment.targets) => {
+                    score.add("recoverable from git", -10)
+                }
+                _ => score.add("not recoverable from git", 10),
+            }
+        }
+    }
+
+    if assessment.network {
+        score.add("network", NETWORK_POINTS as i32);
+    }
+
+    score
+}
+
+/// Count files under the targets, walking directories when the command is recursive
+fn count_files(targets: &[PathBuf], recursive: bool) -> usize {
+    let mut count = 0;
+    for target in targets {
+        if recursive && target.is_dir() {
+            count_dir_entries(target, &mut count);
+        } else {
+            count += 1;
+        }
+        if count >= FILE_COUNT_LIMIT {
+            return FILE_COUNT_LIMIT;
+        }
+    }
+    count
+}
+
+fn count_dir_entries(dir: &Path, count: &mut usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if *count >= FILE_COUNT_LIMIT {
+            return;
+        }
+        *count += 1;
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            count_dir_entries(&entry.path(), count);
+        }
+    }
+}
+
+fn classify_location(path: &Path, repo_root: Option<&Path>, current_dir: &str) -> Location {
+    if repo_root.is_some_and(|root| path.starts_with(root)) {
+        return Location::Repo;
+    }
+    let path_str = path.to_string_lossy();
+    let temp = std::env::temp_dir();
+    if path.starts_with("/tmp") || path.starts_with("/private/tmp") || path.starts_with(&temp) {
+        return Location::Temp;
+    }
+    if paths::is_in_allowed_dir(path, current_dir) {
+        return Location::Workspace;
+    }
+    let home = paths::home_dir();
+    if path_str.starts_with(&format!("{}/", home.trim_end_matches('/'))) {
+        return Location::Home;
+    }
+    Location::System
+}
+
+/// True if every target is tracked by git with no uncommitted changes
+fn git_can_restore(repo_root: &Path, targets: &[PathBuf]) -> bool {
+    if targets.iter().any(|t| !t.starts_with(repo_root)) {
+        return false;
+    }
+    let tracked = Command::new("git")
+        .args(["ls-files", "--error-unmatch", "--"])
+        .args(targets)
+        .current_dir(repo_root)
+        .output()
+        .is_ok_and(|out| out.status.success());
+    tracked
+        && Command::new("git")
+            .args(["diff", "--quiet", "HEAD", "--"])
+            .args(targets)
+            .current_dir(repo_root)
+            .output()
+            .is_ok_and(|out| out.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.email=t@t", "-c", "user.name=t"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_levels() {
+        let thresholds = RiskThresholds { ask: 20, deny: 80 };
+        let mut score = RiskScore::default();
+        score.add("x", 19);
+        assert_eq!(score.level(&thresholds), RiskLevel::Allow);
+        score.add("y", 1);
+        assert_eq!(score.level(&thresholds), RiskLevel::Ask);
+        score.add("z", 60);
+        assert_eq!(score.level(&thresholds), RiskLevel::Deny);
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let assessment = Assessment {
+            verb: Some("rm"),
+            targets: vec![PathBuf::from("/etc/hosts")],
+            ..Default::default()
+        };
+        let score = score(&assessment, "/nonexistent");
+        assert_eq!(score.breakdown(), "risk 80: rm +30, system target +40, not recoverable from git +10");
+    }
+
+    #[test]
+    fn test_network_only() {
+        let assessment = Assessment { network: true, ..Default::default() };
+        assert_eq!(score(&assessment, "/nonexistent").breakdown(), "risk 15: network +15");
+    }
+
+    #[test]
+    fn test_file_count_and_recursive() {
+        let dir = TempDir::new("risk_count");
+        for i in 0..8 {
+            dir.create_file(&format!("d/f{}.txt", i));
+        }
+        let assessment = Assessment {
+            verb: Some("chmod -R"),
+            targets: vec![dir.path().join("d")],
+            recursive: true,
+            network: false,
+        };
+        let score = score(&assessment, &dir.path_str());
+        assert_eq!(score.breakdown(), "risk 35: chmod -R +25, 8 files +10");
+    }
+
+    #[test]
+    fn test_git_tracked_is_recoverable() {
+        let dir = TempDir::new("risk_git");
+        git(dir.path(), &["init", "-q"]);
+        let tracked = dir.write_file("tracked.txt", "v1");
+        git(dir.path(), &["add", "tracked.txt"]);
+        git(dir.path(), &["commit", "-qm", "init"]);
+        let untracked = dir.write_file("untracked.txt", "scratch");
+
+        let rm = |target: &PathBuf| {
+            let assessment = Assessment { verb: Some("rm"), targets: vec![target.clone()], ..Default::default() };
+            score(&assessment, &dir.path_str()).breakdown()
+        };
+        assert_eq!(rm(&tracked), "risk 20: rm +30, recoverable from git -10");
+        assert_eq!(rm(&untracked), "risk 40: rm +30, not recoverable from git +10");
+
+        // Uncommitted edits to a tracked file are not recoverable
+        std::fs::write(&tracked, "v2").unwrap();
+        assert_eq!(rm(&tracked), "risk 40: rm +30, not recoverable from git +10");
+    }
+}
@@ -0,0 +1,389 @@
+//! Catalog of irreversible or system-wrecking commands that are denied outright.
+
+use std::path::Path;
+
+use super::Rule;
+use crate::paths;
+
+/// How a catalog entry inspects a command
+enum Check {
+    /// Looks at the whole command line, before it is split into segments
+    Command(fn(&str) -> bool),
+    /// Looks at one segment's tokens (quotes stripped, wrappers like `sudo` removed)
+    Segment(fn(&[&str], &str) -> bool),
+}
+
+struct Entry {
+    rule: Rule,
+    check: Check,
+}
+
+const CATALOG: &[Entry] = &[
+    Entry {
+        rule: Rule { id: "fork-bomb", reason: "Fork bombs exhaust the process table and hang the machine" },
+        check: Check::Command(is_fork_bomb),
+    },
+    Entry {
+        rule: Rule { id: "dd-device", reason: "dd writing to a device can overwrite a whole disk" },
+        check: Check::Segment(is_dd_to_device),
+    },
+    Entry {
+        rule: Rule { id: "mkfs", reason: "Creating a filesystem erases the target device" },
+        check: Check::Segment(is_mkfs),
+    },
+    Entry {
+        rule: Rule { id: "diskutil-erase", reason: "diskutil erase/partition verbs wipe disks and volumes" },
+        check: Check::Segment(is_diskutil_erase),
+    },
+    Entry {
+        rule: Rule { id: "block-device-redirect", reason: "Writing to a block device corrupts the disk" },
+        check: Check::Segment(writes_block_device),
+    },
+    Entry {
+        rule: Rule { id: "chmod-recursive-root", reason: "Recursive chmod on / or $HOME breaks system and ssh permissions" },
+        check: Check::Segment(is_chmod_recursive_root),
+    },
+    Entry {
+        rule: Rule { id: "chown-recursive-root", reason: "Recursive chown on / or $HOME breaks system ownership" },
+        check: Check::Segment(is_chown_recursive_root),
+    },
+    Entry {
+        rule: Rule { id: "git-push-mirror", reason: "git push --mirror overwrites and deletes every remote ref" },
+        check: Check::Segment(is_git_push_mirror),
+    },
+    Entry {
+        rule: Rule { id: "find-root-delete", reason: "find from / or $HOME with -delete removes files across the system" },
+        check: Check::Segment(is_find_root_delete),
+    },
+    Entry {
+        rule: Rule { id: "shred", reason: "shred irrecoverably destroys file contents" },
+        check: Check::Segment(is_shred),
+    },
+    Entry {
+        rule: Rule { id: "truncate-outside-repo", reason: "Truncating files outside the repo to zero bytes destroys their contents" },
+        check: Check::Segment(is_truncate_outside_repo),
+    },
+    Entry {
+        rule: Rule { id: "crontab-remove", reason: "crontab -r deletes every scheduled job without confirmation" },
+        check: Check::Segment(is_crontab_remove),
+    },
+];
+
+/// Programs the segment entries check by name, sorted for `binary_search`
+const PROGRAMS: &[&str] = &[
+    "chgrp", "chmod", "chown", "crontab", "dd", "diskutil", "find", "git", "shred", "tee",
+    "truncate", "wipefs",
+];
+
+/// Device paths that are harmless to write to
+const SAFE_DEVICES: &[&str] = &["/dev/null", "/dev/zero", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+/// Prefixes of block device paths on Linux and macOS
+const BLOCK_DEVICE_PREFIXES: &[&str] = &[
+    "/dev/sd", "/dev/hd", "/dev/vd", "/dev/xvd", "/dev/nvme", "/dev/mmcblk",
+    "/dev/disk", "/dev/rdisk", "/dev/loop", "/dev/md", "/dev/dm-", "/dev/mapper/",
+];
+
+/// diskutil verbs that destroy data
+const DISKUTIL_ERASE_VERBS: &[&str] = &[
+    "eraseDisk", "eraseVolume", "zeroDisk", "randomDisk", "secureErase",
+    "partitionDisk", "reformat",
+];
+
+/// Check the whole command line against command-level entries
+pub fn check_command(command: &str) -> Option<Rule> {
+    CATALOG.iter().find_map(|entry| match entry.check {
+        Check::Command(f) if f(command) => Some(entry.rule),
+        _ => None,
+    })
+}
+
+/// Check one command segment's tokens (quotes stripped) against segment-level entries
+pub fn check_segment(raw: &[&str], current_dir: &str) -> Option<Rule> {
+    let tokens = super::strip_wrappers(raw);
+    if tokens.is_empty() || !may_match(tokens) {
+        return None;
+    }
+    CATALOG.iter().find_map(|entry| match entry.check {
+        Check::Segment(f) if f(tokens, current_dir) => Some(entry.rule),
+        _ => None,
+    })
+}
+
+/// Programs some segment entry checks, or a redirect that could reach a device;
+/// anything else is skipped without running the checks
+fn may_match(tokens: &[&str]) -> bool {
+    let program = tokens[0];
+    PROGRAMS.binary_search(&program).is_ok()
+        || program.starts_with("mkfs")
+        || program.starts_with("newfs")
+        || tokens.iter().any(|t| t.contains('>'))
+}
+
+/// `:(){ :|:& };:` and renamed variants: a function whose body pipes itself into itself
+fn is_fork_bomb(command: &str) -> bool {
+    if !command.contains('(') {
+        return false;
+    }
+    let compact: String = command.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut rest = compact.as_str();
+    while let Some(pos) = rest.find("(){") {
+        let name_start = rest[..pos]
+            .rfind([';', '&', '|', '{', '}', '('])
+            .map_or(0, |i| i + 1);
+        let name = &rest[name_start..pos];
+        let body = &rest[pos + 3..];
+        let name = name.strip_prefix("function").unwrap_or(name);
+        if !name.is_empty() && body.contains(&format!("{}|{}", name, name)) {
+            return true;
+        }
+        rest = body;
+    }
+    false
+}
+
+fn is_block_device(path: &str) -> bool {
+    !SAFE_DEVICES.contains(&path) && BLOCK_DEVICE_PREFIXES.iter().any(|p| path.starts_with(p))
+}
+
+fn is_dd_to_device(tokens: &[&str], _cwd: &str) -> bool {
+    tokens[0] == "dd"
+        && tokens.iter().any(|t| {
+            t.strip_prefix("of=")
+                .is_some_and(|dev| dev.starts_with("/dev/") && !SAFE_DEVICES.contains(&dev))
+        })
+}
+
+fn is_mkfs(tokens: &[&str], _cwd: &str) -> bool {
+    tokens[0].starts_with("mkfs") || tokens[0].starts_with("newfs") || tokens[0] == "wipefs"
+}
+
+fn is_diskutil_erase(tokens: &[&str], _cwd: &str) -> bool {
+    if tokens[0] != "diskutil" {
+        return false;
+    }
+    // `diskutil apfs deleteContainer ...` nests the verb one level deeper
+    let verbs = if tokens.get(1) == Some(&"apfs") { &tokens[2..] } else { &tokens[1..] };
+    verbs.first().is_some_and(|v| {
+        DISKUTIL_ERASE_VERBS.contains(v) || v.starts_with("delete") || v.starts_with("erase")
+    })
+}
+
+/// `> /dev/sda`, `>>/dev/disk2`, `2> /dev/nvme0n1`, or `tee /dev/sdb`
+fn writes_block_device(tokens: &[&str], _cwd: &str) -> bool {
+    for (i, token) in tokens.iter().enumerate() {
+        let after_redirect = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '&');
+        if let Some(target) = after_redirect.strip_prefix('>') {
+            let target = target.trim_start_matches(['>', '|']);
+            let target = if target.is_empty() { tokens.get(i + 1).copied().unwrap_or("") } else { target };
+            if is_block_device(target) {
+                return true;
+            }
+        }
+    }
+    tokens[0] == "tee" && tokens[1..].iter().any(|t| is_block_device(t))
+}
+
+/// A path argument that names `/`, `/*`, or the user's home directory
+fn is_root_or_home(arg: &str) -> bool {
+    let home = paths::home_dir();
+    let expanded = expand_home(arg);
+    let trimmed = expanded.trim_end_matches(['/', '*']);
+    trimmed.is_empty() || trimmed == home.trim_end_matches('/')
+}
+
+/// Expand `~`, `$HOME` and `${HOME}` to the home directory
+fn expand_home(arg: &str) -> String {
+    let home = paths::home_dir();
+    paths::expand_tilde(arg).replace("${HOME}", &home).replace("$HOME", &home)
+}
+
+fn has_recursive_flag(args: &[&str]) -> bool {
+    args.iter().any(|a| {
+        *a == "--recursive" || (a.starts_with('-') && !a.starts_with("--") && a.contains('R'))
+    })
+}
+
+fn is_chmod_recursive_root(tokens: &[&str], _cwd: &str) -> bool {
+    tokens[0] == "chmod"
+        && has_recursive_flag(&tokens[1..])
+        && tokens[1..].iter().filter(|t| !t.starts_with('-')).skip(1).any(|t| is_root_or_home(t))
+}
+
+fn is_chown_recursive_root(tokens: &[&str], _cwd: &str) -> bool {
+    matches!(tokens[0], "chown" | "chgrp")
+        && has_recursive_flag(&tokens[1..])
+        && tokens[1..].iter().filter(|t| !t.starts_with('-')).skip(1).any(|t| is_root_or_home(t))
+}
+
+fn is_git_push_mirror(tokens: &[&str], _cwd: &str) -> bool {
+    tokens[0] == "git" && tokens.get(1) == Some(&"push") && tokens.contains(&"--mirror")
+}
+
+fn is_find_root_delete(tokens: &[&str], _cwd: &str) -> bool {
+    if tokens[0] != "find" {
+        return false;
+    }
+    // Start paths come before the first expression token (`-name`, `(`, `!`, ...)
+    let starts_at_root = tokens[1..]
+        .iter()
+        .take_while(|t| !t.starts_with('-') && !matches!(**t, "(" | "!" | "\\("))
+        .any(|t| is_root_or_home(t));
+    let deletes = tokens.iter().enumerate().any(|(i, t)| {
+        *t == "-delete"
+            || (matches!(*t, "-exec" | "-execdir" | "-ok")
+                && tokens.get(i + 1).is_some_and(|c| matches!(*c, "rm" | "shred" | "unlink")))
+    });
+    starts_at_root && deletes
+}
+
+fn is_shred(tokens: &[&str], _cwd: &str) -> bool {
+    tokens[0] == "shred"
+}
+
+/// `truncate -s 0` (or `--size=0`) on any file outside the current repo
+fn is_truncate_outside_repo(tokens: &[&str], cwd: &str) -> bool {
+    if tokens[0] != "truncate" {
+        return false;
+    }
+
+    let mut to_zero = false;
+    let mut files = Vec::new();
+    let mut i = 1;
+    while i < tokens.len() {
+        let t = tokens[i];
+        let size = if t == "-s" || t == "--size" {
+            i += 1;
+            tokens.get(i).copied()
+        } else {
+            t.strip_prefix("--size=").or_else(|| t.strip_prefix("-s"))
+        };
+        match size {
+            Some(s) => to_zero |= s == "0",
+            None if t.starts_with('-') => {}
+            None => files.push(t),
+        }
+        i += 1;
+    }
+    if !to_zero {
+        return false;
+    }
+
+    let repo_root = paths::find_repo_root(Path::new(cwd));
+    files.iter().any(|f| {
+        let expanded = expand_home(f);
+        // Any other variable could point anywhere
+        if expanded.contains('$') {
+            return true;
+        }
+        let normalized = paths::normalize_path(&expanded, cwd);
+        repo_root.as_ref().is_none_or(|root| !normalized.starts_with(root))
+    })
+}
+
+fn is_crontab_remove(tokens: &[&str], _cwd: &str) -> bool {
+    tokens[0] == "crontab"
+        && tokens[1..].iter().any(|t| t.starts_with('-') && !t.starts_with("--") && t.contains('r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unquoted(segment: &str) -> Vec<&str> {
+        segment.split_whitespace().map(paths::strip_quotes).collect()
+    }
+
+    fn segment_rule(segment: &str) -> Option<&'static str> {
+        check_segment(&unquoted(segment), "/Users/araftery/workspace/project").map(|r| r.id)
+    }
+
+    #[test]
+    fn test_programs_sorted() {
+        assert!(PROGRAMS.is_sorted());
+    }
+
+    #[test]
+    fn test_fork_bomb() {
+        assert_eq!(check_command(":(){ :|:& };:").map(|r| r.id), Some("fork-bomb"));
+        assert_eq!(check_command("bomb() { bomb | bomb & }; bomb").map(|r| r.id), Some("fork-bomb"));
+        assert_eq!(check_command("f() { echo hi; }; f").map(|r| r.id), None);
+        assert_eq!(check_command("ls | grep x").map(|r| r.id), None);
+    }
+
+    #[test]
+    fn test_dd() {
+        assert_eq!(segment_rule("dd if=/dev/zero of=/dev/sda bs=1M"), Some("dd-device"));
+        assert_eq!(segment_rule("sudo dd if=image.iso of=/dev/disk2"), Some("dd-device"));
+        assert_eq!(segment_rule("dd if=/dev/urandom of=/dev/null count=1"), None);
+        assert_eq!(segment_rule("dd if=a.img of=b.img"), None);
+    }
+
+    #[test]
+    fn test_mkfs_and_diskutil() {
+        assert_eq!(segment_rule("mkfs.ext4 /dev/sdb1"), Some("mkfs"));
+        assert_eq!(segment_rule("mkfs -t vfat /dev/sdc"), Some("mkfs"));
+        assert_eq!(segment_rule("diskutil eraseDisk APFS Empty disk2"), Some("diskutil-erase"));
+        assert_eq!(segment_rule("diskutil apfs deleteContainer disk3"), Some("diskutil-erase"));
+        assert_eq!(segment_rule("diskutil list"), None);
+    }
+
+    #[test]
+    fn test_block_device_redirect() {
+        assert_eq!(segment_rule("echo hi > /dev/sda"), Some("block-device-redirect"));
+        assert_eq!(segment_rule("cat img >/dev/disk2"), Some("block-device-redirect"));
+        assert_eq!(segment_rule("tee /dev/nvme0n1"), Some("block-device-redirect"));
+        assert_eq!(segment_rule("echo hi > /dev/null 2>&1"), None);
+        assert_eq!(segment_rule("echo hi > out.txt"), None);
+    }
+
+    #[test]
+    fn test_recursive_chmod_chown() {
+        assert_eq!(segment_rule("chmod -R 777 /"), Some("chmod-recursive-root"));
+        assert_eq!(segment_rule("sudo chmod --recursive 755 /*"), Some("chmod-recursive-root"));
+        assert_eq!(segment_rule("chown -R nobody /"), Some("chown-recursive-root"));
+        assert_eq!(segment_rule("chown -R me:staff $HOME"), Some("chown-recursive-root"));
+        assert_eq!(segment_rule("chown -R me ~"), Some("chown-recursive-root"));
+        assert_eq!(segment_rule("chmod -R 755 src"), None);
+        assert_eq!(segment_rule("chmod 755 /"), None);
+    }
+
+    #[test]
+    fn test_git_push_mirror() {
+        assert_eq!(segment_rule("git push --mirror origin"), Some("git-push-mirror"));
+        assert_eq!(segment_rule("git push origin main"), None);
+    }
+
+    #[test]
+    fn test_find_root_delete() {
+        assert_eq!(segment_rule("find / -name '*.log' -delete"), Some("find-root-delete"));
+        assert_eq!(segment_rule("find ~ -type f -exec rm {} ;"), Some("find-root-delete"));
+        assert_eq!(segment_rule("find . -name '*.pyc' -delete"), None);
+        assert_eq!(segment_rule("find / -name foo"), None);
+    }
+
+    #[test]
+    fn test_shred_and_crontab() {
+        assert_eq!(segment_rule("shred -u secrets.txt"), Some("shred"));
+        assert_eq!(segment_rule("crontab -r"), Some("crontab-remove"));
+        assert_eq!(segment_rule("crontab -l"), None);
+    }
+
+    #[test]
+    fn test_truncate_outside_repo() {
+        let repo = crate::test_util::TempDir::new("truncate_repo");
+        repo.create_dir(".git");
+        let cwd = repo.path_str();
+        let rule = |s: &str| check_segment(&unquoted(s), &cwd).map(|r| r.id);
+        assert_eq!(rule("truncate -s 0 /var/log/system.log"), Some("truncate-outside-repo"));
+        assert_eq!(rule("truncate --size=0 ../other/file"), Some("truncate-outside-repo"));
+        assert_eq!(rule("truncate -s 0 build.log"), None);
+        assert_eq!(rule("truncate -s 10M /tmp/file"), None);
+        assert_eq!(rule("truncate -s 0 $HOME/.zshrc"), Some("truncate-outside-repo"));
+        assert_eq!(rule("truncate -s 0 ${HOME}/.bash_history"), Some("truncate-outside-repo"));
+        assert_eq!(rule("truncate -s 0 $LOG_DIR/app.log"), Some("truncate-outside-repo"));
+        assert_eq!(rule(&format!("truncate -s 0 {}/build.log", cwd)), None);
+        // No repo: every target is outside
+        assert_eq!(segment_rule("truncate -s0 notes.txt"), Some("truncate-outside-repo"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Database migration and data-destroying commands of ORM and framework CLIs (Prisma,
+//! Rails, Django, Alembic, Sequelize, Supabase, Drizzle, Ecto, ...).
+//!
+//! Dropping or resetting a database is denied unless the environment says the target
+//! is local, where it asks. Rollbacks always ask. Up migrations are allowed against a
+//! local target and ask otherwise.
+
+use serde::Deserialize;
+
+use super::Rule;
+use crate::paths;
+
+/// What a database command does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Drops, resets or wipes data
+    Destroy,
+    /// Rolls migrations back
+    Rollback,
+    /// Applies migrations or seeds
+    Migrate,
+}
+
+const DESTROY: Rule = Rule { id: "db-destroy", reason: "Drops, resets or wipes the database" };
+const ROLLBACK: Rule = Rule { id: "db-rollback", reason: "Rolls back migrations, which can drop tables and columns" };
+const MIGRATE: Rule = Rule { id: "db-migrate", reason: "Runs migrations against a database that isn't known to be local" };
+
+/// How the hook judges a database command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Deny(String),
+    Ask(String),
+    /// An up migration against a local database
+    Allow,
+}
+
+/// Variables holding a database URL
+const URL_VARS: &[&str] = &["DATABASE_URL", "DB_URL", "POSTGRES_URL", "POSTGRES_PRISMA_URL", "MYSQL_URL", "MONGODB_URI", "SQLALCHEMY_DATABASE_URI"];
+
+/// `NAME=value` settings that mark the target as local
+const LOCAL_ENV: &[&str] = &[
+    "RAILS_ENV=development", "RAILS_ENV=test", "RACK_ENV=development", "RACK_ENV=test", "MIX_ENV=dev",
+    "MIX_ENV=test", "NODE_ENV=development", "NODE_ENV=test", "APP_ENV=local", "APP_ENV=development",
+    "APP_ENV=test", "ASPNETCORE_ENVIRONMENT=Development",
+];
+
+/// Environment variables, on top of the built-in ones, that mark the database as local
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DatabasePolicy {
+    /// Variables holding a database URL; the target is local when the host is
+    /// localhost, a unix socket or a SQLite file. A remote URL overrides `local_env`.
+    pub url_vars: Vec<String>,
+    /// `NAME=value` settings that mark the target as local, e.g. `DB_TARGET=local`
+    pub local_env: Vec<String>,
+    /// Hosts that count as local besides localhost, e.g. a compose service named `db`
+    pub local_hosts: Vec<String>,
+}
+
+/// Hosts that are the local machine
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1", "0.0.0.0", "host.docker.internal"];
+
+/// Check one command segment's tokens (quotes stripped); `None` if it isn't a database command. Settings
+/// in the segment (`DATABASE_URL=... prisma migrate deploy`) take precedence over `env`.
+pub fn check_segment(raw: &[&str], policy: &DatabasePolicy, env: &dyn Fn(&str) -> Option<String>) -> Option<Verdict> {
+    let tokens = super::strip_wrappers(raw);
+    let (program, words) = command_words(tokens)?;
+    let kind = classify(program, &words, tokens)?;
+
+    let assignments: Vec<(&str, &str)> = raw[..raw.len() - tokens.len()].iter().filter_map(|t| t.split_once('=')).map(|(n, v)| (n, paths::strip_quotes(v))).collect();
+    let lookup = |name: &str| assignments.iter().rev().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()).or_else(|| env(name));
+    let local = is_local_target(policy, &lookup);
+
+    let describe = |rule: Rule| format!("`{}`: {}", tokens.join(" "), rule.describe());
+    Some(match (kind, local) {
+        (Kind::Destroy, false) => Verdict::Deny(describe(DESTROY)),
+        (Kind::Destroy, true) => Verdict::Ask(describe(DESTROY)),
+        (Kind::Rollback, _) => Verdict::Ask(describe(ROLLBACK)),
+        (Kind::Migrate, false) => Verdict::Ask(describe(MIGRATE)),
+        (Kind::Migrate, true) => Verdict::Allow,
+    })
+}
+
+/// Check whether the environment points the database at this machine
+fn is_local_target(policy: &DatabasePolicy, lookup: &dyn Fn(&str) -> Option<String>) -> bool {
+    let url_vars = URL_VARS.iter().copied().chain(policy.url_vars.iter().map(String::as_str));
+    let urls: Vec<String> = url_vars.filter_map(lookup).filter(|u| !u.is_empty()).collect();
+    if !urls.is_empty() {
+        return urls.iter().all(|url| is_local_url(url, policy));
+    }
+    LOCAL_ENV.iter().copied().chain(policy.local_env.iter().map(String::as_str)).any(|setting| {
+        setting.split_once('=').is_some_and(|(name, value)| lookup(name).as_deref() == Some(value))
+    })
+}
+
+/// Check if a database URL points at this machine: `postgres://user:pw@localhost:5432/app`,
+/// `postgres:///app` (unix socket), `file:./dev.db`
+pub fn is_local_url(url: &str, policy: &DatabasePolicy) -> bool {
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return false;
+    };
+    if scheme.starts_with("sqlite") || scheme == "file" {
+        return true;
+    }
+    let Some(rest) = rest.strip_prefix("//") else {
+        return false;
+    };
+    let authority = rest.split(['/', '?']).next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host_port.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host_port.split(':').next().unwrap_or_default(),
+    };
+    host.is_empty() || is_local_host(host, policy)
+}
+
+/// Check if a host name is this machine, or one of the configured local hosts
+pub fn is_local_host(host: &str, policy: &DatabasePolicy) -> bool {
+    LOCAL_HOSTS.contains(&host) || policy.local_hosts.iter().any(|h| h == host)
+}
+
+/// Programs `classify` knows, after runners are stripped and binstubs normalized;
+/// sorted for `binary_search`
+const PROGRAMS: &[&str] = &[
+    "alembic", "artisan", "atlas", "console", "dbmate", "dotnet", "drizzle-kit", "flyway", "goose",
+    "knex", "liquibase", "manage.py", "migrate", "mix", "prisma", "rails", "rake", "sequelize",
+    "sequelize-cli", "supabase", "typeorm", "typeorm-ts-node-commonjs", "typeorm-ts-node-esm",
+];
+
+/// Prefixes that run the rest of the line as a project binary (`npx prisma`, `bundle exec rails`)
+const RUNNERS: &[&[&str]] = &[
+    &["npx"], &["bunx"], &["pnpx"], &["pnpm", "exec"], &["pnpm", "dlx"], &["yarn", "exec"], &["yarn", "dlx"],
+    &["bun", "x"], &["bundle", "exec"], &["uv", "run"], &["poetry", "run"], &["pdm", "run"], &["hatch", "run"],
+];
+
+/// The program (with runners like `npx` and `python manage.py` and binstubs
+/// normalized) and its non-flag words
+fn command_words<'a>(mut tokens: &[&'a str]) -> Option<(&'a str, Vec<&'a str>)> {
+    while let Some(runner) = RUNNERS.iter().find(|r| tokens.first() == r.first() && tokens.starts_with(r)) {
+        tokens = &tokens[runner.len()..];
+        while tokens.first().is_some_and(|t| t.starts_with('-')) {
+            tokens = &tokens[1..];
+        }
+    }
+    let (&first, rest) = tokens.split_first()?;
+    let (program, rest) = match paths::strip_quotes(first).trim_start_matches("./") {
+        "python" | "python3" => {
+            let script = rest.iter().position(|t| !t.starts_with('-'))?;
+            (rest[script].trim_start_matches("./"), &rest[script + 1..])
+        }
+        "php" => match rest.split_first() {
+            Some((script, rest)) => (script.trim_start_matches("./"), rest),
+            None => return None,
+        },
+        "django-admin" => ("manage.py", rest),
+        program => (program.strip_prefix("bin/").unwrap_or(program), rest),
+    };
+    if PROGRAMS.binary_search(&program).is_err() {
+        return None;
+    }
+    Some((program, rest.iter().filter(|t| !t.starts_with('-')).copied().collect()))
+}
+
+fn classify(program: &str, words: &[&str], tokens: &[&str]) -> Option<Kind> {
+    let has = |flag: &str| tokens.contains(&flag);
+    let kind = match (program, words) {
+        ("prisma", ["migrate", "reset", ..]) => Kind::Destroy,
+        ("prisma", ["db", "push", ..]) if has("--force-reset") || has("--accept-data-loss") => Kind::Destroy,
+        ("prisma", ["migrate", "deploy" | "dev", ..] | ["db", "push" | "seed", ..]) => Kind::Migrate,
+
+        ("rails" | "rake", tasks) => return tasks.iter().filter_map(|t| rails_task(t)).max_by_key(|k| severity(*k)),
+
+        ("manage.py", ["flush" | "reset_db" | "reset_schema", ..]) => Kind::Destroy,
+        ("manage.py", ["migrate", _, "zero", ..]) => Kind::Destroy,
+        ("manage.py", ["migrate", _, _, ..]) => Kind::Rollback,
+        ("manage.py", ["migrate", ..] | ["loaddata", ..]) => Kind::Migrate,
+
+        ("alembic", ["downgrade", "base", ..]) => Kind::Destroy,
+        ("alembic", ["downgrade", ..]) => Kind::Rollback,
+        ("alembic", ["upgrade", ..]) => Kind::Migrate,
+
+        ("sequelize" | "sequelize-cli", ["db:drop" | "db:migrate:undo:all" | "db:seed:undo:all", ..]) => Kind::Destroy,
+        ("sequelize" | "sequelize-cli", ["db:migrate:undo" | "db:seed:undo", ..]) => Kind::Rollback,
+        ("sequelize" | "sequelize-cli", ["db:migrate" | "db:seed:all" | "db:seed" | "db:create", ..]) => Kind::Migrate,
+
+        ("supabase", ["db", "reset", ..]) => Kind::Destroy,
+        ("supabase", ["migration", "down", ..]) => Kind::Rollback,
+        ("supabase", ["db", "push", ..] | ["migration", "up", ..]) => Kind::Migrate,
+
+        ("drizzle-kit", ["push" | "drop", ..]) => Kind::Destroy,
+        ("drizzle-kit", ["migrate", ..]) => Kind::Migrate,
+
+        ("knex", ["migrate:rollback", ..]) if has("--all") => Kind::Destroy,
+        ("knex", ["migrate:rollback" | "migrate:down", ..]) => Kind::Rollback,
+        ("knex", ["migrate:latest" | "migrate:up" | "seed:run", ..]) => Kind::Migrate,
+
+        ("typeorm" | "typeorm-ts-node-commonjs" | "typeorm-ts-node-esm", ["schema:drop" | "schema:sync", ..]) => Kind::Destroy,
+        ("typeorm" | "typeorm-ts-node-commonjs" | "typeorm-ts-node-esm", ["migration:revert", ..]) => Kind::Rollback,
+        ("typeorm" | "typeorm-ts-node-commonjs" | "typeorm-ts-node-esm", ["migration:run", ..]) => Kind::Migrate,
+
+        ("mix", tasks) => return tasks.iter().filter_map(|t| ecto_task(t.trim_end_matches(','))).max_by_key(|k| severity(*k)),
+
+        ("dotnet", ["ef", "database", "drop", ..]) => Kind::Destroy,
+        ("dotnet", ["ef", "database", "update", ..]) => Kind::Migrate,
+
+        ("artisan", ["migrate:fresh" | "migrate:reset" | "db:wipe", ..]) => Kind::Destroy,
+        ("artisan", ["migrate:rollback", ..]) => Kind::Rollback,
+        ("artisan", ["migrate" | "db:seed", ..]) => Kind::Migrate,
+        ("console", ["doctrine:database:drop" | "doctrine:schema:drop", ..]) => Kind::Destroy,
+        ("console", ["doctrine:migrations:migrate", ..]) => Kind::Migrate,
+
+        ("flyway", ["clean", ..]) => Kind::Destroy,
+        ("flyway", ["undo", ..]) => Kind::Rollback,
+        ("flyway", ["migrate", ..]) | ("liquibase", ["update", ..]) | ("atlas", ["migrate" | "schema", "apply", ..]) => Kind::Migrate,
+        ("goose" | "dbmate" | "migrate", _) if words.contains(&"drop") || words.contains(&"reset") => Kind::Destroy,
+        ("goose" | "dbmate" | "migrate", _) if words.iter().any(|w| matches!(*w, "down" | "rollback" | "down-to")) => Kind::Rollback,
+        ("goose" | "dbmate" | "migrate", _) if words.iter().any(|w| matches!(*w, "up" | "migrate" | "up-to")) => Kind::Migrate,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn severity(kind: Kind) -> u8 {
+    match kind {
+        Kind::Destroy => 2,
+        Kind::Rollback => 1,
+        Kind::Migrate => 0,
+    }
+}
+
+/// Rails and Rake `db:` tasks that drop or reload the database
+const RAILS_DESTROY_TASKS: &[&str] = &[
+    "db:drop", "db:reset", "db:schema:load", "db:structure:load", "db:purge", "db:truncate_all",
+    "db:setup", "db:seed:replant", "db:migrate:reset",
+];
+
+/// Rails and Rake `db:` tasks that roll migrations back
+const RAILS_ROLLBACK_TASKS: &[&str] = &["db:rollback", "db:migrate:down", "db:migrate:redo"];
+
+/// Rails and Rake `db:` tasks that migrate, create or seed
+const RAILS_MIGRATE_TASKS: &[&str] = &["db:migrate", "db:migrate:up", "db:prepare", "db:seed", "db:create"];
+
+/// Judge one Rails or Rake task, ignoring its `[args]`
+fn rails_task(task: &str) -> Option<Kind> {
+    let task = task.split('[').next().unwrap_or(task);
+    let tables = [(RAILS_DESTROY_TASKS, Kind::Destroy), (RAILS_ROLLBACK_TASKS, Kind::Rollback), (RAILS_MIGRATE_TASKS, Kind::Migrate)];
+    if let Some((_, kind)) = tables.iter().find(|(tasks, _)| tasks.contains(&task)) {
+        return Some(*kind);
+    }
+    // Multi-database apps suffix the database name: `db:drop:primary`
+    let (base, _) = task.rsplit_once(':')?;
+    tables.iter().find(|(tasks, _)| tasks.contains(&base)).map(|(_, kind)| *kind).filter(|_| base != "db:migrate")
+}
+
+/// Ecto's mix tasks
+fn ecto_task(task: &str) -> Option<Kind> {
+    match task {
+        "ecto.drop" | "ecto.reset" => Some(Kind::Destroy),
+        "ecto.rollback" => Some(Kind::Rollback),
+        "ecto.migrate" | "ecto.create" | "ecto.setup" => Some(Kind::Migrate),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(command: &str) -> Option<Kind> {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        let (program, words) = command_words(&tokens)?;
+        classify(program, &words, &tokens)
+    }
+
+    fn check(command: &str, env: &[(&str, &str)]) -> Option<Verdict> {
+        let lookup = |name: &str| env.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string());
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        check_segment(&tokens, &DatabasePolicy::default(), &lookup)
+    }
+
+    #[test]
+    fn test_programs_sorted() {
+        assert!(PROGRAMS.is_sorted());
+    }
+
+    #[test]
+    fn test_classify() {
+        for command in [
+            "prisma migrate reset --force", "prisma db push --force-reset", "rails db:drop", "bin/rails db:reset",
+            "rake db:schema:load", "rails db:drop:primary", "python manage.py flush --noinput", "./manage.py migrate app zero",
+            "alembic downgrade base", "sequelize db:drop", "supabase db reset", "drizzle-kit push", "mix ecto.reset",
+            "dotnet ef database drop -f", "php artisan migrate:fresh --seed", "flyway clean",
+        ] {
+            assert_eq!(kind(command), Some(Kind::Destroy), "{}", command);
+        }
+        for command in ["rails db:rollback STEP=2", "alembic downgrade -1", "python3 manage.py migrate app 0003", "knex migrate:rollback", "mix ecto.rollback -n 1"] {
+            assert_eq!(kind(command), Some(Kind::Rollback), "{}", command);
+        }
+        for command in ["prisma migrate deploy", "rake db:migrate", "rails db:migrate:up VERSION=1", "django-admin migrate", "alembic upgrade head", "sequelize-cli db:migrate", "drizzle-kit migrate", "mix do ecto.create, ecto.migrate", "goose -dir db up", "npx prisma migrate dev", "bundle exec rake db:migrate", "uv run python manage.py migrate"] {
+            assert_eq!(kind(command), Some(Kind::Migrate), "{}", command);
+        }
+        for command in ["prisma generate", "rails db:migrate:status", "python manage.py test", "alembic history", "drizzle-kit generate", "rake test", "mix test"] {
+            assert_eq!(kind(command), None, "{}", command);
+        }
+        // The most destructive task on the line wins
+        assert_eq!(kind("rails db:migrate db:drop"), Some(Kind::Destroy));
+    }
+
+    #[test]
+    fn test_local_target() {
+        let local_url = [("DATABASE_URL", "postgresql://app:pw@localhost:5432/app_dev")];
+        let remote_url = [("DATABASE_URL", "postgres://app@db.prod.example.com/app"), ("RAILS_ENV", "development")];
+        assert_eq!(check("prisma migrate deploy", &local_url), Some(Verdict::Allow));
+        assert_eq!(
+            check("prisma migrate deploy", &remote_url),
+            Some(Verdict::Ask("`prisma migrate deploy`: Runs migrations against a database that isn't known to be local [db-migrate]".to_string()))
+        );
+        assert_eq!(check("rails db:migrate", &[("RAILS_ENV", "test")]), Some(Verdict::Allow));
+        assert_eq!(check("rails db:migrate", &[("RAILS_ENV", "production")]).map(|v| matches!(v, Verdict::Ask(_))), Some(true));
+        assert_eq!(
+            check("prisma migrate reset", &[]),
+            Some(Verdict::Deny("`prisma migrate reset`: Drops, resets or wipes the database [db-destroy]".to_string()))
+        );
+        assert_eq!(
+            check("prisma migrate reset", &local_url),
+            Some(Verdict::Ask("`prisma migrate reset`: Drops, resets or wipes the database [db-destroy]".to_string()))
+        );
+        // Settings on the command line override the environment
+        assert_eq!(check("DATABASE_URL=file:./dev.db prisma migrate deploy", &remote_url), Some(Verdict::Allow));
+        assert_eq!(check("MIX_ENV=prod mix ecto.migrate", &[("MIX_ENV", "dev")]).map(|v| v == Verdict::Allow), Some(false));
+        assert_eq!(
+            check("alembic downgrade -1", &local_url),
+            Some(Verdict::Ask("`alembic downgrade -1`: Rolls back migrations, which can drop tables and columns [db-rollback]".to_string()))
+        );
+        assert_eq!(check("ls", &[]), None);
+    }
+
+    #[test]
+    fn test_is_local_url() {
+        for url in ["postgres://localhost/app", "mysql://root@127.0.0.1:3306/app", "postgres://u:p@[::1]:5432/db", "postgres:///app?host=/tmp", "sqlite:///tmp/app.db", "file:./dev.db", "mongodb://host.docker.internal:27017"] {
+            assert!(is_local_url(url, &DatabasePolicy::default()), "{}", url);
+        }
+        for url in ["postgres://db.example.com/app", "postgres://localhost.evil.com/app", "mysql://user@10.0.0.5/app", "not a url"] {
+            assert!(!is_local_url(url, &DatabasePolicy::default()), "{}", url);
+        }
+        let policy = DatabasePolicy { local_hosts: vec!["db".to_string()], ..DatabasePolicy::default() };
+        assert!(is_local_url("postgres://app@db:5432/app", &policy));
+    }
+}
@@ -0,0 +1,298 @@
+//! Commands that wait for a keyboard: pagers, editors, full-screen monitors, REPLs and
+//! git and package manager prompts. Nothing answers them in the agent's shell, so they
+//! would hang until the tool call times out. They're denied with the non-interactive
+//! way to do the same thing, so the agent can rerun it.
+
+use super::Rule;
+
+/// How a catalog entry inspects a segment: its tokens (quotes stripped, wrappers like
+/// `sudo` removed), the wrappers and environment assignments in front of it, and
+/// whether its stdin is a pipe or a redirect
+type Check = fn(&[&str], &[&str], bool) -> bool;
+
+struct Entry {
+    rule: Rule,
+    check: Check,
+}
+
+const CATALOG: &[Entry] = &[
+    Entry {
+        rule: Rule { id: "pager", reason: "Pagers (less, more) wait for keypresses; use cat, head or tail instead" },
+        check: is_pager,
+    },
+    Entry {
+        rule: Rule {
+            id: "editor",
+            reason: "Editors (vim, nano, emacs) wait for keystrokes; edit with sed or the Edit tool, or script them with vim -es or emacs --batch",
+        },
+        check: is_editor,
+    },
+    Entry {
+        rule: Rule { id: "monitor", reason: "top and htop redraw until quit; use ps, top -b -n 1 (Linux) or top -l 1 (macOS)" },
+        check: is_monitor,
+    },
+    Entry {
+        rule: Rule { id: "repl", reason: "An interpreter or shell without a script waits for input; pass the code with -c or -e, a script file, or pipe it in" },
+        check: is_repl,
+    },
+    Entry {
+        rule: Rule {
+            id: "git-rebase-interactive",
+            reason: "git rebase -i opens an editor for the todo list; script it with GIT_SEQUENCE_EDITOR (e.g. GIT_SEQUENCE_EDITOR=true to keep it as is)",
+        },
+        check: is_git_rebase_interactive,
+    },
+    Entry {
+        rule: Rule { id: "git-patch-mode", reason: "git add -p and other patch modes prompt for each hunk; stage whole paths, or apply a patch with git apply --cached" },
+        check: is_git_patch_mode,
+    },
+    Entry {
+        rule: Rule { id: "git-editor", reason: "This git command opens an editor for its message; pass it with -m or -F, use --no-edit, or prefix GIT_EDITOR=true" },
+        check: opens_git_editor,
+    },
+    Entry {
+        rule: Rule { id: "init-prompt", reason: "npm, yarn and bun init prompt for each field; pass --yes (-y), or --no-interaction (-n) for poetry init" },
+        check: is_init_prompt,
+    },
+];
+
+const PAGERS: &[&str] = &["less", "more", "most"];
+
+const EDITORS: &[&str] = &["vi", "vim", "nvim", "view", "vimdiff", "nano", "pico", "emacs", "micro", "hx", "joe", "mcedit"];
+
+const MONITORS: &[&str] = &["top", "htop", "btop", "atop", "glances", "nmon"];
+
+const REPLS: &[&str] = &[
+    "python", "python3", "ipython", "node", "deno", "irb", "pry", "ghci", "lua", "php", "R", "julia",
+    "bash", "sh", "zsh", "fish",
+];
+
+/// Interpreter flags that don't give it anything to run
+const REPL_FLAGS: &[&str] = &["-i", "--interactive", "-q", "-u", "-B", "-E", "-I", "-s", "-S", "-l", "--login", "--norc", "--noprofile"];
+
+/// Programs some catalog entry checks, sorted for `binary_search`; anything else is
+/// skipped without running the checks
+const PROGRAMS: &[&str] = &[
+    "R", "atop", "bash", "btop", "bun", "deno", "emacs", "fish", "ghci", "git", "glances", "htop",
+    "hx", "ipython", "irb", "joe", "julia", "less", "lua", "mcedit", "micro", "more", "most",
+    "nano", "nmon", "node", "npm", "nvim", "php", "pico", "poetry", "pry", "python", "python3",
+    "sh", "top", "vi", "view", "vim", "vimdiff", "yarn", "zsh",
+];
+
+/// Check one segment's tokens (quotes stripped) against the catalog. `piped` says
+/// whether its stdin comes from a pipe.
+pub fn check_segment(raw: &[&str], piped: bool) -> Option<Rule> {
+    let tokens = super::strip_wrappers(raw);
+    if tokens.is_empty() || PROGRAMS.binary_search(&tokens[0]).is_err() {
+        return None;
+    }
+    let env = &raw[..raw.len() - tokens.len()];
+    // `<file` and `<<< 'here-string'` feed stdin too
+    let fed = piped || tokens.iter().any(|t| t.starts_with('<'));
+    CATALOG.iter().find(|entry| (entry.check)(tokens, env, fed)).map(|entry| entry.rule)
+}
+
+fn is_pager(tokens: &[&str], _env: &[&str], _fed: bool) -> bool {
+    PAGERS.contains(&tokens[0])
+}
+
+fn is_editor(tokens: &[&str], _env: &[&str], _fed: bool) -> bool {
+    let scripted = |flags: &[&str]| tokens[1..].iter().any(|t| flags.contains(t));
+    match tokens[0] {
+        "vi" | "vim" | "nvim" => !scripted(&["-es", "-Es", "--headless"]),
+        "emacs" => !scripted(&["--batch", "-batch", "--script"]),
+        program => EDITORS.contains(&program),
+    }
+}
+
+fn is_monitor(tokens: &[&str], _env: &[&str], _fed: bool) -> bool {
+    match tokens[0] {
+        // Batch (Linux) and logging (macOS) modes print and exit
+        "top" => !tokens[1..].iter().any(|t| t.starts_with("-b") || t.starts_with("-l")),
+        program => MONITORS.contains(&program),
+    }
+}
+
+fn is_repl(tokens: &[&str], _env: &[&str], fed: bool) -> bool {
+    !fed && REPLS.contains(&tokens[0]) && tokens[1..].iter().all(|t| REPL_FLAGS.contains(t))
+}
+
+/// `git [-C dir] [-c key=value] SUBCOMMAND ARGS...`, with whether `-c core.editor=...`
+/// replaces the editor
+fn git_subcommand<'a>(tokens: &'a [&'a str]) -> Option<(&'a str, &'a [&'a str], bool)> {
+    if tokens[0] != "git" {
+        return None;
+    }
+    let mut editor_set = false;
+    let mut i = 1;
+    while i < tokens.len() && tokens[i].starts_with('-') {
+        if matches!(tokens[i], "-C" | "-c" | "--git-dir" | "--work-tree" | "--namespace") {
+            editor_set |= tokens[i] == "-c" && tokens.get(i + 1).is_some_and(|c| c.starts_with("core.editor=") || c.starts_with("sequence.editor="));
+            i += 1;
+        }
+        i += 1;
+    }
+    let (&subcommand, args) = tokens[i..].split_first()?;
+    Some((subcommand, args, editor_set))
+}
+
+fn sets(env: &[&str], names: &[&str]) -> bool {
+    env.iter().any(|assignment| names.iter().any(|name| assignment.split_once('=').is_some_and(|(n, _)| n == *name)))
+}
+
+fn is_git_rebase_interactive(tokens: &[&str], env: &[&str], _fed: bool) -> bool {
+    let Some(("rebase", args, editor_set)) = git_subcommand(tokens) else {
+        return false;
+    };
+    let interactive = args.iter().any(|a| matches!(*a, "-i" | "--interactive" | "--edit-todo") || is_short_cluster_with(a, 'i'));
+    interactive && !editor_set && !sets(env, &["GIT_SEQUENCE_EDITOR", "GIT_EDITOR", "EDITOR", "VISUAL"])
+}
+
+fn is_git_patch_mode(tokens: &[&str], _env: &[&str], _fed: bool) -> bool {
+    let Some((subcommand, args, _)) = git_subcommand(tokens) else {
+        return false;
+    };
+    let has = |flags: &[&str]| args.iter().any(|a| flags.contains(a));
+    match subcommand {
+        "add" => has(&["-p", "--patch", "-i", "--interactive", "-e", "--edit"]),
+        "checkout" | "reset" | "restore" | "stash" | "commit" => has(&["-p", "--patch"]) || (subcommand == "commit" && has(&["--interactive"])),
+        "clean" => has(&["-i", "--interactive"]),
+        _ => false,
+    }
+}
+
+/// `git commit` without a message, `tag -a` without one, `revert` and
+/// `rebase --continue` without `--no-edit`, and any `--edit`
+fn opens_git_editor(tokens: &[&str], env: &[&str], _fed: bool) -> bool {
+    let Some((subcommand, args, editor_set)) = git_subcommand(tokens) else {
+        return false;
+    };
+    if !matches!(subcommand, "commit" | "tag" | "revert" | "rebase" | "merge" | "cherry-pick") || editor_set || sets(env, &["GIT_EDITOR", "EDITOR", "VISUAL"]) {
+        return false;
+    }
+    let has = |flags: &[&str]| args.iter().any(|a| flags.iter().any(|f| a == f || a.starts_with(&format!("{}=", f))));
+    let has_message = args.iter().any(|a| {
+        a.starts_with("--message") || a.starts_with("--file") || a.starts_with("--reuse-message") || is_short_cluster_with(a, 'm') || is_short_cluster_with(a, 'F') || is_short_cluster_with(a, 'C')
+    });
+    let forced = has(&["--edit"]) || (subcommand != "tag" && args.iter().any(|a| is_short_cluster_with(a, 'e')));
+    match subcommand {
+        "commit" => {
+            let fixup = args.iter().any(|a| a.starts_with("--fixup") && !a.contains("amend:") && !a.contains("reword:"));
+            forced || has(&["-c", "--reedit-message"]) || (!has_message && !fixup && !has(&["--no-edit", "--dry-run"]))
+        }
+        "tag" => {
+            let annotated = args.iter().any(|a| matches!(*a, "-a" | "-s" | "--annotate" | "--sign") || a.starts_with("-u") || a.starts_with("--local-user"));
+            annotated && !has_message
+        }
+        "revert" => !has(&["--no-edit", "-n", "--no-commit", "--abort", "--skip", "--quit"]),
+        "rebase" | "merge" | "cherry-pick" => forced || (subcommand == "rebase" && has(&["--continue"])),
+        _ => false,
+    }
+}
+
+/// `-am`-style clusters of short flags that include `flag`
+fn is_short_cluster_with(arg: &str, flag: char) -> bool {
+    arg.len() > 1 && arg.starts_with('-') && !arg.starts_with("--") && arg[1..].chars().all(|c| c.is_ascii_alphabetic()) && arg.contains(flag)
+}
+
+fn is_init_prompt(tokens: &[&str], _env: &[&str], _fed: bool) -> bool {
+    let (program, args) = (tokens[0], &tokens[1..]);
+    let has = |flags: &[&str]| args.iter().any(|a| flags.contains(a));
+    match (program, args.first()) {
+        // `npm init vite` runs an initializer, which has its own flags
+        ("npm" | "yarn" | "bun", Some(&"init")) => !has(&["-y", "--yes"]) && args[1..].iter().all(|a| a.starts_with('-')),
+        ("poetry", Some(&"init")) => !has(&["-n", "--no-interaction"]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(segment: &str) -> Option<&'static str> {
+        rule_fed(segment, false)
+    }
+
+    fn rule_fed(segment: &str, piped: bool) -> Option<&'static str> {
+        let tokens: Vec<&str> = segment.split_whitespace().collect();
+        check_segment(&tokens, piped).map(|r| r.id)
+    }
+
+    #[test]
+    fn test_programs_sorted_and_complete() {
+        assert!(PROGRAMS.is_sorted());
+        for program in PAGERS.iter().chain(EDITORS).chain(MONITORS).chain(REPLS) {
+            assert!(PROGRAMS.binary_search(program).is_ok(), "{}", program);
+        }
+    }
+
+    #[test]
+    fn test_pagers_editors_and_monitors() {
+        assert_eq!(rule("less README.md"), Some("pager"));
+        assert_eq!(rule_fed("more", true), Some("pager"));
+        assert_eq!(rule("vim src/main.rs"), Some("editor"));
+        assert_eq!(rule("sudo nano /etc/hosts"), Some("editor"));
+        assert_eq!(rule("vim -es -c %s/foo/bar/g -c wq file.txt"), None);
+        assert_eq!(rule("emacs --batch -l build.el"), None);
+        assert_eq!(rule("top"), Some("monitor"));
+        assert_eq!(rule("htop -u me"), Some("monitor"));
+        assert_eq!(rule("top -b -n 1"), None);
+        assert_eq!(rule("top -l 1"), None);
+    }
+
+    #[test]
+    fn test_repls() {
+        assert_eq!(rule("python"), Some("repl"));
+        assert_eq!(rule("python3 -i"), Some("repl"));
+        assert_eq!(rule("node"), Some("repl"));
+        assert_eq!(rule("bash"), Some("repl"));
+        assert_eq!(rule("python script.py"), None);
+        assert_eq!(rule("python -c print(1)"), None);
+        assert_eq!(rule("python --version"), None);
+        assert_eq!(rule_fed("python", true), None);
+        assert_eq!(rule("python <<< 'print(1)'"), None);
+    }
+
+    #[test]
+    fn test_git() {
+        assert_eq!(rule("git rebase -i HEAD~3"), Some("git-rebase-interactive"));
+        assert_eq!(rule("git rebase --interactive main"), Some("git-rebase-interactive"));
+        assert_eq!(rule("GIT_SEQUENCE_EDITOR=true git rebase -i --autosquash main"), None);
+        assert_eq!(rule("git -c sequence.editor=true rebase -i main"), None);
+        assert_eq!(rule("git add -p"), Some("git-patch-mode"));
+        assert_eq!(rule("git checkout --patch src/"), Some("git-patch-mode"));
+        assert_eq!(rule("git add -A"), None);
+
+        assert_eq!(rule("git commit"), Some("git-editor"));
+        assert_eq!(rule("git commit --amend"), Some("git-editor"));
+        assert_eq!(rule("git -C repo commit -a"), Some("git-editor"));
+        assert_eq!(rule("git commit -m fix"), None);
+        assert_eq!(rule("git commit -am fix"), None);
+        assert_eq!(rule("git commit --message=fix"), None);
+        assert_eq!(rule("git commit -F msg.txt"), None);
+        assert_eq!(rule("git commit --amend --no-edit"), None);
+        assert_eq!(rule("git commit --fixup HEAD~1"), None);
+        assert_eq!(rule("git commit -e -m fix"), Some("git-editor"));
+        assert_eq!(rule("GIT_EDITOR=true git commit"), None);
+        assert_eq!(rule("git tag -a v1.0"), Some("git-editor"));
+        assert_eq!(rule("git tag -a v1.0 -m release"), None);
+        assert_eq!(rule("git tag v1.0"), None);
+        assert_eq!(rule("git revert HEAD"), Some("git-editor"));
+        assert_eq!(rule("git revert --no-edit HEAD"), None);
+        assert_eq!(rule("git rebase --continue"), Some("git-editor"));
+        assert_eq!(rule("GIT_EDITOR=true git rebase --continue"), None);
+        assert_eq!(rule("git merge main"), None);
+    }
+
+    #[test]
+    fn test_init_prompts() {
+        assert_eq!(rule("npm init"), Some("init-prompt"));
+        assert_eq!(rule("yarn init"), Some("init-prompt"));
+        assert_eq!(rule("poetry init"), Some("init-prompt"));
+        assert_eq!(rule("npm init -y"), None);
+        assert_eq!(rule("bun init --yes"), None);
+        assert_eq!(rule("poetry init -n"), None);
+        assert_eq!(rule("npm install"), None);
+    }
+}
@@ -0,0 +1,82 @@
+//! Rule families that classify bash commands beyond the basic safe/destructive lists.
+
+pub mod catastrophic;
+pub mod database;
+pub mod interactive;
+pub mod long_running;
+pub mod publish;
+
+/// A named rule with a human-readable reason, reported in the hook's decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Stable identifier, e.g. `dd-device`
+    pub id: &'static str,
+    /// Why the rule exists, shown to the agent
+    pub reason: &'static str,
+}
+
+impl Rule {
+    /// Format for `permissionDecisionReason`
+    pub fn describe(&self) -> String {
+        format!("{} [{}]", self.reason, self.id)
+    }
+}
+
+/// Skip prefixes that run the rest of the line as a command (`sudo`, `env`, `nohup`,
+/// `time`, `nice`, `VAR=value` assignments) so rules see the real command
+pub fn strip_wrappers<'a>(tokens: &'a [&'a str]) -> &'a [&'a str] {
+    let mut i = 0;
+    while i < tokens.len() {
+        let t = tokens[i];
+        match t {
+            "sudo" | "doas" | "nohup" | "time" | "nice" | "env" | "exec" | "command" => {
+                i += 1;
+                // Flags belonging to the wrapper (e.g. `sudo -u root`, `nice -n 10`)
+                while i < tokens.len() && tokens[i].starts_with('-') {
+                    if matches!(tokens[i], "-u" | "-g" | "-n" | "-C") {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            _ if is_env_assignment(t) => i += 1,
+            _ => break,
+        }
+    }
+    &tokens[i..]
+}
+
+/// Check if a token is a `NAME=value` environment assignment
+pub fn is_env_assignment(token: &str) -> bool {
+    match token.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_wrappers() {
+        assert_eq!(strip_wrappers(&["sudo", "dd", "if=x"]), &["dd", "if=x"]);
+        assert_eq!(strip_wrappers(&["sudo", "-u", "root", "shred", "f"]), &["shred", "f"]);
+        assert_eq!(strip_wrappers(&["FOO=1", "BAR=2", "make"]), &["make"]);
+        assert_eq!(strip_wrappers(&["env", "-i", "X=1", "ls"]), &["ls"]);
+        assert_eq!(strip_wrappers(&["ls", "-la"]), &["ls", "-la"]);
+    }
+
+    #[test]
+    fn test_is_env_assignment() {
+        assert!(is_env_assignment("FOO=bar"));
+        assert!(is_env_assignment("NODE_ENV="));
+        assert!(!is_env_assignment("--flag=1"));
+        assert!(!is_env_assignment("1X=2"));
+        assert!(!is_env_assignment("ls"));
+    }
+}
@@ -0,0 +1,352 @@
+//! Catalog of publish, release and deploy commands: they push work out to the world,
+//! where it can't be taken back. Entries are denied unless the `publish` config turns
+//! them off, globally or for one project.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::Rule;
+use crate::paths;
+
+struct Entry {
+    rule: Rule,
+    /// Looks at one segment's tokens (quotes stripped, wrappers like `sudo` removed) and
+    /// the words after the program that aren't flags
+    check: fn(&[&str], &[&str]) -> bool,
+}
+
+const CATALOG: &[Entry] = &[
+    Entry {
+        rule: Rule { id: "npm-publish", reason: "Publishing to the npm registry can't be undone" },
+        check: is_npm_publish,
+    },
+    Entry {
+        rule: Rule { id: "cargo-publish", reason: "Publishing a crate to the registry can't be undone" },
+        check: is_cargo_publish,
+    },
+    Entry {
+        rule: Rule { id: "pypi-upload", reason: "Uploading a release to PyPI can't be undone" },
+        check: is_pypi_upload,
+    },
+    Entry {
+        rule: Rule { id: "gem-push", reason: "Pushing a gem to rubygems.org can't be undone" },
+        check: is_gem_push,
+    },
+    Entry {
+        rule: Rule { id: "hex-publish", reason: "Publishing a package to hex.pm can't be undone" },
+        check: is_hex_publish,
+    },
+    Entry {
+        rule: Rule { id: "maven-deploy", reason: "Deploying artifacts to a remote Maven repository can't be undone" },
+        check: is_maven_deploy,
+    },
+    Entry {
+        rule: Rule { id: "nuget-push", reason: "Pushing a package to a NuGet feed can't be undone" },
+        check: is_nuget_push,
+    },
+    Entry {
+        rule: Rule { id: "swift-registry-publish", reason: "Publishing to a Swift package registry can't be undone" },
+        check: is_swift_registry_publish,
+    },
+    Entry {
+        rule: Rule { id: "container-push", reason: "Pushing an image to a container registry publishes it" },
+        check: is_container_push,
+    },
+    Entry {
+        rule: Rule { id: "github-release", reason: "Creating or changing a GitHub release publishes it" },
+        check: is_github_release,
+    },
+    Entry {
+        rule: Rule { id: "git-push-tags", reason: "Pushing tags publishes release points other clones will fetch" },
+        check: is_git_push_tags,
+    },
+    Entry {
+        rule: Rule { id: "extension-publish", reason: "Publishing an editor extension to the marketplace can't be undone" },
+        check: is_extension_publish,
+    },
+    Entry {
+        rule: Rule { id: "release-tool", reason: "Release tools tag, publish and announce a release" },
+        check: is_release_tool,
+    },
+    Entry {
+        rule: Rule { id: "deploy", reason: "Deploying changes what's running for real users" },
+        check: is_deploy,
+    },
+];
+
+/// Whether catalog entries are denied, configured globally and per project
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PublishPolicy {
+    /// Deny catalog commands
+    pub enabled: bool,
+    /// Catalog entry ids (`npm-publish`, `deploy`) that are never denied
+    pub allow: Vec<String>,
+    /// Overrides for projects, keyed by their directory (`~` allowed)
+    pub projects: HashMap<String, ProjectPublishPolicy>,
+}
+
+/// A project's override of the publish policy
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectPublishPolicy {
+    /// Overrides `enabled` for commands run in the project
+    pub enabled: Option<bool>,
+    /// Entry ids allowed in the project, on top of the global list
+    pub allow: Vec<String>,
+}
+
+impl Default for PublishPolicy {
+    fn default() -> Self {
+        PublishPolicy { enabled: true, allow: Vec::new(), projects: HashMap::new() }
+    }
+}
+
+impl PublishPolicy {
+    /// The override for the project containing `dir`; the innermost wins
+    fn project(&self, dir: &str) -> Option<&ProjectPublishPolicy> {
+        self.projects
+            .iter()
+            .map(|(root, policy)| (paths::normalize_path(&paths::expand_tilde(root), "/"), policy))
+            .filter(|(root, _)| Path::new(dir).starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, policy)| policy)
+    }
+
+    fn denies(&self, id: &str, dir: &str) -> bool {
+        let project = self.project(dir);
+        project.and_then(|p| p.enabled).unwrap_or(self.enabled)
+            && !self.allow.iter().any(|a| a == id)
+            && !project.is_some_and(|p| p.allow.iter().any(|a| a == id))
+    }
+}
+
+/// Check one command segment's tokens (quotes stripped), run from `current_dir`, against the catalog
+pub fn check_segment(raw: &[&str], current_dir: &str, policy: &PublishPolicy) -> Option<Rule> {
+    let tokens = super::strip_wrappers(raw);
+    let program = tokens.first().map(|t| t.rsplit('/').next().unwrap_or(t))?;
+    if PROGRAMS.binary_search(&program).is_err() || tokens.contains(&"--dry-run") {
+        return None;
+    }
+    let words = words(tokens);
+    CATALOG
+        .iter()
+        .find(|entry| (entry.check)(tokens, &words) && policy.denies(entry.rule.id, current_dir))
+        .map(|entry| entry.rule)
+}
+
+/// Programs some catalog entry checks, sorted for `binary_search`; anything else is
+/// skipped without running the checks
+const PROGRAMS: &[&str] = &[
+    "buildah", "bun", "cargo", "changeset", "crane", "docker", "dotnet", "eas", "eb", "firebase",
+    "flit", "fly", "flyctl", "gcloud", "gem", "gh", "git", "goreleaser", "gradle", "gradlew",
+    "hatch", "helm", "heroku", "lerna", "mix", "mvn", "mvnw", "nerdctl", "netlify", "np", "npm",
+    "nuget", "oras", "ovsx", "pdm", "pnpm", "podman", "poetry", "python", "python3", "rails",
+    "railway", "rake", "release-it", "release-please", "semantic-release", "serverless", "skopeo",
+    "sls", "swift", "twine", "uv", "vercel", "vsce", "wrangler", "yarn",
+];
+
+/// The words after the program that aren't flags
+fn words<'a>(tokens: &[&'a str]) -> Vec<&'a str> {
+    tokens[1..].iter().filter(|t| !t.starts_with('-')).copied().collect()
+}
+
+fn has_flag(tokens: &[&str], flags: &[&str]) -> bool {
+    tokens.iter().any(|t| flags.contains(t) || flags.iter().any(|f| t.strip_prefix(f).is_some_and(|v| v.starts_with('='))))
+}
+
+fn is_npm_publish(tokens: &[&str], words: &[&str]) -> bool {
+    matches!(
+        (tokens[0], words),
+        ("npm" | "pnpm" | "yarn" | "bun", ["publish", ..])
+            | ("npm", ["unpublish" | "deprecate", ..])
+            | ("npm" | "pnpm" | "yarn", ["dist-tag", "add" | "rm", ..])
+            | ("yarn", ["npm", "publish" | "tag", ..])
+            | ("lerna" | "changeset", ["publish", ..])
+    )
+}
+
+fn is_cargo_publish(tokens: &[&str], words: &[&str]) -> bool {
+    if tokens[0] != "cargo" {
+        return false;
+    }
+    match words.iter().find(|w| !w.starts_with('+')).copied() {
+        Some("publish" | "yank") => true,
+        Some("owner") => has_flag(tokens, &["-a", "--add", "-r", "--remove"]),
+        _ => false,
+    }
+}
+
+fn is_pypi_upload(tokens: &[&str], words: &[&str]) -> bool {
+    match (tokens[0], words) {
+        ("twine", ["upload", ..]) => true,
+        ("poetry" | "uv" | "flit" | "hatch" | "pdm", ["publish", ..]) => true,
+        ("python" | "python3", ["twine", "upload", ..]) => tokens.contains(&"-m"),
+        _ => false,
+    }
+}
+
+fn is_gem_push(tokens: &[&str], words: &[&str]) -> bool {
+    let program = tokens[0].trim_start_matches("./").trim_start_matches("bin/");
+    match (program, words) {
+        ("gem", ["push" | "yank", ..]) => true,
+        ("gem", ["owner", ..]) => has_flag(tokens, &["-a", "--add", "-r", "--remove"]),
+        ("rake" | "rails", tasks) => tasks.iter().any(|t| matches!(*t, "release" | "release:rubygem_push")),
+        _ => false,
+    }
+}
+
+fn is_hex_publish(tokens: &[&str], _words: &[&str]) -> bool {
+    tokens[0] == "mix"
+        && tokens[1..].iter().enumerate().any(|(i, t)| match t.trim_end_matches(',') {
+            "hex.publish" | "hex.retire" => true,
+            "hex.owner" => tokens.get(i + 2).is_some_and(|sub| matches!(*sub, "add" | "remove" | "transfer")),
+            _ => false,
+        })
+}
+
+fn is_maven_deploy(tokens: &[&str], words: &[&str]) -> bool {
+    let program = tokens[0].rsplit('/').next().unwrap_or(tokens[0]);
+    match program {
+        "mvn" | "mvnw" => words.iter().any(|w| matches!(*w, "deploy" | "deploy:deploy" | "deploy:deploy-file" | "site-deploy" | "site:deploy" | "release:perform")),
+        "gradle" | "gradlew" => words.iter().any(|w| {
+            let task = w.rsplit(':').next().unwrap_or(w);
+            (task.starts_with("publish") && task != "publishToMavenLocal") || task == "uploadArchives"
+        }),
+        _ => false,
+    }
+}
+
+fn is_nuget_push(tokens: &[&str], words: &[&str]) -> bool {
+    matches!((tokens[0], words), ("dotnet", ["nuget", "push" | "delete", ..]) | ("nuget", ["push" | "delete", ..]))
+}
+
+fn is_swift_registry_publish(tokens: &[&str], words: &[&str]) -> bool {
+    tokens[0] == "swift" && words.starts_with(&["package-registry", "publish"])
+}
+
+fn is_container_push(tokens: &[&str], words: &[&str]) -> bool {
+    match (tokens[0], words) {
+        ("docker" | "podman" | "nerdctl", ["push", ..] | ["image" | "manifest", "push", ..]) => true,
+        ("docker", ["buildx", "build" | "bake", ..]) | ("docker" | "podman", ["build", ..]) => has_flag(tokens, &["--push"]),
+        ("crane" | "oras", ["push" | "copy" | "cp", ..]) | ("skopeo", ["copy", ..]) | ("buildah", ["push", ..]) => true,
+        ("helm", ["push", ..]) => true,
+        _ => false,
+    }
+}
+
+fn is_github_release(tokens: &[&str], words: &[&str]) -> bool {
+    tokens[0] == "gh" && matches!(words, ["release", "create" | "edit" | "delete" | "upload" | "delete-asset", ..])
+}
+
+fn is_git_push_tags(tokens: &[&str], words: &[&str]) -> bool {
+    tokens[0] == "git"
+        && words.first() == Some(&"push")
+        && (has_flag(tokens, &["--tags", "--follow-tags"]) || words.iter().any(|w| w.contains("refs/tags/")))
+}
+
+fn is_extension_publish(tokens: &[&str], words: &[&str]) -> bool {
+    matches!((tokens[0], words), ("vsce" | "ovsx", ["publish" | "unpublish", ..]))
+}
+
+fn is_release_tool(tokens: &[&str], words: &[&str]) -> bool {
+    match (tokens[0], words) {
+        ("goreleaser", ["release", ..]) | ("goreleaser", []) => !has_flag(tokens, &["--snapshot", "--skip=publish"]),
+        ("semantic-release" | "release-it" | "np" | "release-please", _) => true,
+        _ => false,
+    }
+}
+
+fn is_deploy(tokens: &[&str], words: &[&str]) -> bool {
+    match (tokens[0], words) {
+        ("wrangler", ["deploy" | "publish", ..] | ["pages" | "versions", "deploy", ..]) => true,
+        // Bare `vercel` deploys the current directory
+        ("vercel", [] | ["deploy" | "promote" | "redeploy" | "rollback", ..]) => true,
+        ("netlify" | "firebase" | "fly" | "flyctl" | "sls" | "serverless" | "eb", ["deploy", ..]) => true,
+        ("railway", ["up", ..]) | ("heroku", ["releases:rollback", ..]) | ("eas", ["submit" | "update", ..]) => true,
+        ("gcloud", words) => words.contains(&"deploy"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unquoted(segment: &str) -> Vec<&str> {
+        segment.split_whitespace().map(paths::strip_quotes).collect()
+    }
+
+    fn check(command: &str) -> Option<&'static str> {
+        check_segment(&unquoted(command), "/work/app", &PublishPolicy::default()).map(|rule| rule.id)
+    }
+
+    #[test]
+    fn test_programs_sorted() {
+        assert!(PROGRAMS.is_sorted());
+    }
+
+    #[test]
+    fn test_catalog() {
+        let cases = [
+            ("npm publish --access public", "npm-publish"),
+            ("pnpm -r publish", "npm-publish"),
+            ("yarn npm publish", "npm-publish"),
+            ("npm dist-tag add pkg@1.2.0 latest", "npm-publish"),
+            ("cargo +stable publish -p core", "cargo-publish"),
+            ("cargo owner --add github:acme:core", "cargo-publish"),
+            ("twine upload dist/*", "pypi-upload"),
+            ("python3 -m twine upload dist/*", "pypi-upload"),
+            ("uv publish", "pypi-upload"),
+            ("gem push pkg/app-1.0.gem", "gem-push"),
+            ("bundle exec rake release", "gem-push"),
+            ("mix hex.publish --yes", "hex-publish"),
+            ("mix do test, hex.owner add pkg me@example.com", "hex-publish"),
+            ("./mvnw -B clean deploy", "maven-deploy"),
+            ("./gradlew :lib:publishToSonatype", "maven-deploy"),
+            ("dotnet nuget push app.nupkg", "nuget-push"),
+            ("swift package-registry publish acme.app 1.0.0", "swift-registry-publish"),
+            ("docker push ghcr.io/acme/app:1.0", "container-push"),
+            ("docker buildx build --push -t app .", "container-push"),
+            ("gh release create v1.0.0 --generate-notes", "github-release"),
+            ("git push --tags", "git-push-tags"),
+            ("git push origin refs/tags/v1.0.0", "git-push-tags"),
+            ("vsce publish", "extension-publish"),
+            ("npx semantic-release", "release-tool"),
+            ("goreleaser release --clean", "release-tool"),
+            ("wrangler deploy", "deploy"),
+            ("vercel --prod", "deploy"),
+            ("gcloud run deploy api --source .", "deploy"),
+            ("sudo FOO=1 firebase deploy --only hosting", "deploy"),
+        ];
+        for (command, id) in cases {
+            // `npx` and `bundle exec` payloads reach the catalog once unwrapped
+            let command = command.strip_prefix("npx ").or_else(|| command.strip_prefix("bundle exec ")).unwrap_or(command);
+            assert_eq!(check(command), Some(id), "{}", command);
+        }
+        for command in ["npm publish --dry-run", "npm pack", "cargo package", "gradle publishToMavenLocal", "docker build -t app .", "gh release view", "git push origin main", "goreleaser release --snapshot", "wrangler dev", "vercel env ls", "gem build app.gemspec", "cargo owner --list"] {
+            assert_eq!(check(command), None, "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy: PublishPolicy = serde_json::from_str(
+            r#"{"allow": ["container-push"], "projects": {"/work/site": {"allow": ["deploy"]}, "/work/sandbox": {"enabled": false}, "/work/sandbox/prod": {"enabled": true}}}"#,
+        )
+        .unwrap();
+        let id = |command: &str, dir: &str| check_segment(&unquoted(command), dir, &policy).map(|rule| rule.id);
+        assert_eq!(id("docker push app", "/work/app"), None);
+        assert_eq!(id("wrangler deploy", "/work/app"), Some("deploy"));
+        assert_eq!(id("wrangler deploy", "/work/site/worker"), None);
+        assert_eq!(id("npm publish", "/work/site"), Some("npm-publish"));
+        assert_eq!(id("npm publish", "/work/sandbox/lib"), None);
+        assert_eq!(id("npm publish", "/work/sandbox/prod"), Some("npm-publish"));
+        // Sibling directories sharing a prefix aren't the project
+        assert_eq!(id("wrangler deploy", "/work/site-old"), Some("deploy"));
+
+        let off = PublishPolicy { enabled: false, ..PublishPolicy::default() };
+        assert_eq!(check_segment(&["npm", "publish"], "/work/app", &off), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! Bash calls that set `dangerouslyDisableSandbox` run without the sandbox's file and
+//! network limits. They're never auto-allowed: the judgment of the command itself still
+//! applies, and anything short of a deny asks, with the agent's justification in the
+//! reason so the user can weigh it. Commands on the bypass list keep their judgment.
+
+use serde::Deserialize;
+
+use crate::paths;
+use crate::types::Decision;
+
+/// Commands that may run outside the sandbox without asking
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SandboxPolicy {
+    /// Patterns (`*`, `?`) matched against each segment as written, e.g. `gh *` for
+    /// commands that need the keychain
+    pub bypass_allowed: Vec<String>,
+}
+
+impl SandboxPolicy {
+    /// Whether every segment (`cd` aside) matches a bypass pattern
+    fn allows(&self, segments: &[String]) -> bool {
+        let mut segments = segments.iter().map(|s| s.trim()).filter(|s| !s.is_empty() && *s != "cd" && !s.starts_with("cd ")).peekable();
+        segments.peek().is_some() && segments.all(|segment| self.bypass_allowed.iter().any(|pattern| paths::glob_match(pattern, segment)))
+    }
+}
+
+/// Apply the sandbox-bypass policy to `decision`, the judgment of a command split into
+/// `segments`. `justification` is the reason the agent gave for turning the sandbox off.
+pub fn review(decision: Decision, segments: &[String], justification: Option<&str>, policy: &SandboxPolicy) -> Decision {
+    if matches!(decision, Decision::Deny(_)) || policy.allows(segments) {
+        return decision;
+    }
+    let why = match justification.map(str::trim).filter(|j| !j.is_empty()) {
+        Some(justification) => format!("Runs outside the sandbox: \"{}\"", justification),
+        None => "Runs outside the sandbox, with no reason given".to_string(),
+    };
+    match decision {
+        Decision::Ask(reason) => Decision::Ask(format!("{}; {}", why, reason)),
+        _ => Decision::Ask(why),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(command: &str) -> Vec<String> {
+        command.split("&&").map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_never_allows() {
+        let policy = SandboxPolicy::default();
+        assert_eq!(
+            review(Decision::Allow("Safe read-only/build command".to_string()), &segments("ls"), Some("needs /var/run"), &policy),
+            Decision::Ask("Runs outside the sandbox: \"needs /var/run\"".to_string())
+        );
+        assert_eq!(review(Decision::Abstain, &segments("make"), None, &policy), Decision::Ask("Runs outside the sandbox, with no reason given".to_string()));
+        assert_eq!(
+            review(Decision::Ask("rm needs confirmation".to_string()), &segments("rm x"), Some("  "), &policy),
+            Decision::Ask("Runs outside the sandbox, with no reason given; rm needs confirmation".to_string())
+        );
+        assert_eq!(review(Decision::Deny("no".to_string()), &segments("rm -rf /"), Some("why not"), &policy), Decision::Deny("no".to_string()));
+    }
+
+    #[test]
+    fn test_bypass_allowed() {
+        let policy = SandboxPolicy { bypass_allowed: vec!["gh *".to_string()] };
+        let allow = Decision::Allow("Safe read-only/build command".to_string());
+        assert_eq!(review(allow.clone(), &segments("gh pr view 12"), None, &policy), allow);
+        assert_eq!(review(allow.clone(), &segments("cd web && gh pr list"), None, &policy), allow);
+        assert_eq!(review(Decision::Abstain, &segments("gh api user"), None, &policy), Decision::Abstain);
+        assert!(matches!(review(allow.clone(), &segments("gh pr view 12 && curl example.com"), None, &policy), Decision::Ask(_)));
+        assert!(matches!(review(allow, &segments("cd web"), None, &policy), Decision::Ask(_)));
+    }
+}
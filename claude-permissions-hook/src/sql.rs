@@ -0,0 +1,327 @@
+//! A small SQL tokenizer for the scripts database clients run: splits a script into
+//! statements and classifies each as a read, a write or a destructive change. It knows
+//! enough of the Postgres, MySQL, SQLite and DuckDB dialects to skip comments, string
+//! literals, quoted identifiers and dollar-quoted bodies; it doesn't parse expressions.
+
+/// What a statement does to the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// Reads data or schema, or only changes session state
+    Read,
+    /// Changes data, schema or server state, e.g. `INSERT` or `CREATE INDEX`
+    Write(String),
+    /// Destroys data, e.g. `DROP TABLE` or `DELETE without WHERE`
+    Destroy(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A keyword, identifier or number, uppercased
+    Word(String),
+    /// A quoted identifier or dollar-quoted body
+    Quoted,
+    /// A single-quoted string literal's contents
+    Str(String),
+    Punct(char),
+}
+
+/// Statements that only read, or only change the session
+const READ_STATEMENTS: &[&str] = &[
+    "SHOW", "DESCRIBE", "DESC", "BEGIN", "START", "COMMIT", "END", "ROLLBACK", "SAVEPOINT",
+    "RELEASE", "USE", "RESET", "LISTEN", "UNLISTEN", "FETCH", "MOVE", "CLOSE", "DECLARE",
+    "DISCARD", "SUMMARIZE", "HELP",
+];
+
+/// Functions that change server state, touch the server's files or reach other servers
+const SIDE_EFFECT_FUNCTIONS: &[&str] = &[
+    "PG_TERMINATE_BACKEND", "PG_CANCEL_BACKEND", "PG_RELOAD_CONF", "PG_ROTATE_LOGFILE",
+    "PG_READ_FILE", "PG_READ_BINARY_FILE", "PG_LS_DIR", "PG_SWITCH_WAL",
+    "PG_CREATE_RESTORE_POINT", "PG_DROP_REPLICATION_SLOT", "LO_IMPORT", "LO_EXPORT", "LO_UNLINK",
+    "DBLINK", "DBLINK_EXEC", "SET_CONFIG", "NEXTVAL", "SETVAL", "LOAD_FILE", "LOAD_EXTENSION",
+    "WRITEFILE",
+];
+
+/// Functions that read local files: DuckDB's table functions and SQLite's `readfile`
+const FILE_READERS: &[&str] = &[
+    "READ_TEXT", "READ_BLOB", "READ_CSV", "READ_CSV_AUTO", "READ_JSON", "READ_JSON_AUTO",
+    "READ_JSON_OBJECTS", "READ_NDJSON", "READ_NDJSON_AUTO", "READ_PARQUET", "PARQUET_SCAN", "GLOB",
+    "READFILE",
+];
+
+/// Modifiers between `CREATE`/`DROP`/`ALTER` and the kind of object
+const OBJECT_MODIFIERS: &[&str] = &[
+    "OR", "REPLACE", "TEMP", "TEMPORARY", "UNIQUE", "MATERIALIZED", "GLOBAL", "LOCAL", "UNLOGGED",
+    "IF", "NOT", "EXISTS", "CONCURRENTLY", "RECURSIVE", "VIRTUAL", "EXTERNAL", "DEFINER",
+];
+
+/// Classify every statement in a script, in order
+pub fn classify_script(sql: &str) -> Vec<Effect> {
+    statements(sql).iter().map(|statement| classify(statement)).collect()
+}
+
+/// Local files a script reads: the paths passed to `FILE_READERS` and bare `'path'`
+/// table references (`select * from 'data.csv'`)
+pub fn files_read(sql: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    for tokens in statements(sql) {
+        for (i, token) in tokens.iter().enumerate() {
+            match (token, tokens.get(i + 1)) {
+                (Token::Word(name), Some(Token::Punct('('))) if FILE_READERS.contains(&name.as_str()) => files.extend(first_argument_strings(&tokens[i + 2..])),
+                (Token::Word(word), Some(Token::Str(path))) if word == "FROM" || word == "JOIN" => files.push(path.clone()),
+                _ => {}
+            }
+        }
+    }
+    files
+}
+
+/// The string literals in a call's first argument: `'a.csv'` or `['a.csv', 'b.csv']`
+fn first_argument_strings(args: &[Token]) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut depth = 0usize;
+    for token in args {
+        match token {
+            Token::Punct(')' | ']' | ',') if depth == 0 => break,
+            Token::Punct('(' | '[') => depth += 1,
+            Token::Punct(')' | ']') => depth -= 1,
+            Token::Str(s) => strings.push(s.clone()),
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// Split a script into its non-empty statements
+fn statements(sql: &str) -> Vec<Vec<Token>> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        let next = chars.get(i + 1).copied();
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '-' if next == Some('-') => {
+                while chars.get(i).is_some_and(|&c| c != '\n') {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            '\'' | '"' | '`' => {
+                // A doubled quote is an escaped quote inside the literal
+                i += 1;
+                let mut contents = String::new();
+                loop {
+                    match chars.get(i) {
+                        None => break,
+                        Some(&q) if q == c && chars.get(i + 1) == Some(&c) => {
+                            contents.push(q);
+                            i += 2;
+                        }
+                        Some(&q) if q == c => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&other) => {
+                            contents.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                current.push(if c == '\'' { Token::Str(contents) } else { Token::Quoted });
+            }
+            '$' if next.is_some_and(|n| n == '$' || n.is_alphabetic() || n == '_') => {
+                // `$tag$ ... $tag$`; a bare `$name` without the closing `$` is a parameter
+                let tag_end = (i + 1..chars.len()).find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_'));
+                match tag_end {
+                    Some(end) if chars[end] == '$' => {
+                        let tag: String = chars[i..=end].iter().collect();
+                        let body = chars[end + 1..].iter().collect::<String>();
+                        i = match body.find(&tag) {
+                            Some(pos) => end + 1 + body[..pos].chars().count() + tag.chars().count(),
+                            None => chars.len(),
+                        };
+                        current.push(Token::Quoted);
+                    }
+                    _ => {
+                        current.push(Token::Punct('$'));
+                        i += 1;
+                    }
+                }
+            }
+            ';' => {
+                if !current.is_empty() {
+                    statements.push(std::mem::take(&mut current));
+                }
+                i += 1;
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while chars.get(i).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+                    i += 1;
+                }
+                current.push(Token::Word(chars[start..i].iter().collect::<String>().to_uppercase()));
+            }
+            _ => {
+                current.push(Token::Punct(c));
+                i += 1;
+            }
+        }
+    }
+    if !current.is_empty() {
+        statements.push(current);
+    }
+    statements
+}
+
+/// Classify one statement
+fn classify(tokens: &[Token]) -> Effect {
+    let top = top_level_words(tokens);
+    let Some(&first) = top.first() else {
+        return Effect::Read;
+    };
+    let has_top = |word: &str| top.contains(&word);
+    match first {
+        "SELECT" | "VALUES" | "TABLE" => select_effect(tokens, &top),
+        "WITH" => match words(tokens).find(|w| matches!(*w, "INSERT" | "UPDATE" | "DELETE" | "MERGE")) {
+            Some(verb) => Effect::Write(format!("WITH ... {}", verb)),
+            None => select_effect(tokens, &top),
+        },
+        // EXPLAIN ANALYZE runs the statement it explains
+        "EXPLAIN" if words(tokens).any(|w| w == "ANALYZE") => {
+            let start = tokens.iter().skip(1).position(|t| {
+                matches!(t, Token::Word(w) if matches!(w.as_str(), "SELECT" | "WITH" | "VALUES" | "TABLE" | "INSERT" | "UPDATE" | "DELETE" | "MERGE" | "CREATE"))
+            });
+            match start {
+                Some(start) => classify(&tokens[start + 1..]),
+                None => Effect::Read,
+            }
+        }
+        "EXPLAIN" => Effect::Read,
+        _ if READ_STATEMENTS.contains(&first) => Effect::Read,
+        "SET" if top.get(1).is_some_and(|w| matches!(*w, "GLOBAL" | "PERSIST" | "PERSIST_ONLY")) => Effect::Write("SET GLOBAL".to_string()),
+        "SET" => Effect::Read,
+        "PRAGMA" if tokens.contains(&Token::Punct('=')) => Effect::Write("PRAGMA".to_string()),
+        "PRAGMA" => Effect::Read,
+        "COPY" if has_top("TO") && has_top("STDOUT") => Effect::Read,
+        "DROP" => Effect::Destroy(format!("DROP {}", object_kind(&top))),
+        "TRUNCATE" => Effect::Destroy("TRUNCATE".to_string()),
+        "DELETE" if !has_top("WHERE") => Effect::Destroy("DELETE without WHERE".to_string()),
+        "CREATE" | "ALTER" => Effect::Write(format!("{} {}", first, object_kind(&top))),
+        _ => Effect::Write(first.to_string()),
+    }
+}
+
+/// A `SELECT` reads unless it writes its result somewhere (`INTO`) or calls a function
+/// with side effects
+fn select_effect(tokens: &[Token], top: &[&str]) -> Effect {
+    if top.contains(&"INTO") {
+        return Effect::Write("SELECT INTO".to_string());
+    }
+    let calls = tokens.windows(2).filter_map(|pair| match pair {
+        [Token::Word(name), Token::Punct('(')] => Some(name.as_str()),
+        _ => None,
+    });
+    for name in calls {
+        if SIDE_EFFECT_FUNCTIONS.contains(&name) {
+            return Effect::Write(name.to_lowercase());
+        }
+    }
+    Effect::Read
+}
+
+/// The object kind after `CREATE`/`DROP`/`ALTER`, e.g. `TABLE`
+fn object_kind<'a>(top: &[&'a str]) -> &'a str {
+    top.iter().skip(1).find(|w| !OBJECT_MODIFIERS.contains(w)).copied().unwrap_or_default()
+}
+
+fn words(tokens: &[Token]) -> impl Iterator<Item = &str> {
+    tokens.iter().filter_map(|t| match t {
+        Token::Word(w) => Some(w.as_str()),
+        _ => None,
+    })
+}
+
+/// Words outside any parentheses
+fn top_level_words(tokens: &[Token]) -> Vec<&str> {
+    let mut depth = 0usize;
+    let mut top = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Punct('(') => depth += 1,
+            Token::Punct(')') => depth = depth.saturating_sub(1),
+            Token::Word(w) if depth == 0 => top.push(w.as_str()),
+            _ => {}
+        }
+    }
+    top
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(name: &str) -> Effect {
+        Effect::Write(name.to_string())
+    }
+
+    fn destroy(name: &str) -> Effect {
+        Effect::Destroy(name.to_string())
+    }
+
+    #[test]
+    fn test_classify_statements() {
+        let cases = [
+            ("select count(*) from users where name = 'a;b'", Effect::Read),
+            ("EXPLAIN SELECT * FROM t", Effect::Read),
+            ("show tables", Effect::Read),
+            ("with recent as (select * from orders) select * from recent", Effect::Read),
+            ("SET search_path TO app", Effect::Read),
+            ("pragma table_info(users)", Effect::Read),
+            ("insert into t values (1)", write("INSERT")),
+            ("update users set admin = true where id = 1", write("UPDATE")),
+            ("delete from users where id in (select id from banned)", write("DELETE")),
+            ("create unique index concurrently if not exists i on t (c)", write("CREATE INDEX")),
+            ("alter table users add column age int", write("ALTER TABLE")),
+            ("select * into backup from users", write("SELECT INTO")),
+            ("select pg_terminate_backend(pid) from pg_stat_activity", write("pg_terminate_backend")),
+            ("with d as (delete from t returning *) select * from d", write("WITH ... DELETE")),
+            ("explain analyze delete from t", destroy("DELETE without WHERE")),
+            ("set global max_connections = 10", write("SET GLOBAL")),
+            ("pragma journal_mode = wal", write("PRAGMA")),
+            ("drop table if exists users cascade", destroy("DROP TABLE")),
+            ("truncate table sessions", destroy("TRUNCATE")),
+            ("DELETE FROM users", destroy("DELETE without WHERE")),
+            ("delete from users using (select 1 where true) x", destroy("DELETE without WHERE")),
+        ];
+        for (sql, effect) in cases {
+            assert_eq!(classify_script(sql), vec![effect], "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_script_splitting() {
+        let script = "-- drop table users;\nselect 1; /* delete from t; */\n\
+                      create function f() returns void as $body$ begin; drop table t; end $body$ language plpgsql;\n\
+                      select \"drop;\" from `t;`;;";
+        assert_eq!(classify_script(script), vec![Effect::Read, write("CREATE FUNCTION"), Effect::Read]);
+        assert_eq!(classify_script("select $1, $$a;b$$"), vec![Effect::Read]);
+        assert_eq!(classify_script("  \n-- nothing\n"), vec![]);
+    }
+
+    #[test]
+    fn test_files_read() {
+        assert_eq!(files_read("select * from read_csv('data/a.csv', delim = ',')"), vec!["data/a.csv"]);
+        assert_eq!(files_read("select * from read_parquet(['a.parquet', 'b''s.parquet'])"), vec!["a.parquet", "b's.parquet"]);
+        assert_eq!(files_read("select content from read_text('.env'); select * from glob('/etc/*')"), vec![".env", "/etc/*"]);
+        assert_eq!(files_read("select * from 'events.json' e join '/tmp/users.csv' u on e.id = u.id"), vec!["events.json", "/tmp/users.csv"]);
+        assert_eq!(files_read("select readfile('/etc/passwd')"), vec!["/etc/passwd"]);
+        assert_eq!(files_read("select * from users where name = 'read_csv(x)'"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,481 @@
+//! just: recipes, parameters, dependencies, aliases and string variables.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{strip_line_prefixes, unresolved};
+use crate::package_scripts::{Resolution, ScriptCommand};
+use crate::paths;
+use crate::rules;
+
+/// Justfile names just looks for in each directory
+const JUSTFILE_NAMES: &[&str] = &["justfile", "Justfile", ".justfile"];
+
+/// just flags that take a separate value
+const VALUE_FLAGS: &[&str] = &[
+    "-f", "--justfile", "-d", "--working-directory", "--shell", "--shell-arg", "--color",
+    "--dotenv-filename", "--dotenv-path", "--command-color", "--chooser", "--unstable",
+];
+
+/// just flags that list or print recipes instead of running them; after the first
+/// recipe they're that recipe's arguments
+const READ_ONLY_FLAGS: &[&str] = &[
+    "-n", "--dry-run", "-l", "--list", "--summary", "-s", "--show", "--evaluate",
+    "--variables", "--dump", "--groups", "-V", "--version", "-h", "--help",
+];
+
+/// Shebang interpreters whose lines are shell commands
+const SHELL_INTERPRETERS: &[&str] = &["sh", "bash", "zsh", "dash"];
+
+/// Cap on dependency and variable nesting
+const MAX_DEPTH: usize = 16;
+
+#[derive(Default)]
+struct Justfile {
+    /// Variable name → unevaluated expression
+    vars: HashMap<String, String>,
+    aliases: HashMap<String, String>,
+    recipes: Vec<Recipe>,
+    /// Why the file can't be resolved statically, if it can't
+    unsupported: Option<String>,
+}
+
+struct Recipe {
+    name: String,
+    params: Vec<Param>,
+    /// Dependencies run before the body
+    deps: Vec<String>,
+    /// Dependencies after `&&`, run after the body
+    post_deps: Vec<String>,
+    body: Vec<String>,
+}
+
+struct Param {
+    name: String,
+    default: Option<String>,
+    /// `*args` or `+args`: takes every remaining argument
+    variadic: bool,
+}
+
+/// Resolve `just [flags] [NAME=value] [recipe [args]...]` to the lines it runs
+pub fn resolve(args: &[&str], current_dir: &Path) -> Resolution {
+    let mut file: Option<PathBuf> = None;
+    let mut working_dir: Option<PathBuf> = None;
+    let mut overrides: HashMap<String, String> = HashMap::new();
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        if positional.is_empty() && READ_ONLY_FLAGS.contains(&arg) {
+            return Resolution::Commands(Vec::new());
+        }
+        if arg == "--set" {
+            if let (Some(name), Some(value)) = (args.get(i), args.get(i + 1)) {
+                overrides.insert(name.to_string(), value.to_string());
+            }
+            i += 2;
+            continue;
+        }
+        if arg.starts_with('-') && positional.is_empty() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None if VALUE_FLAGS.contains(&arg) => {
+                    i += 1;
+                    (arg, args.get(i - 1).copied())
+                }
+                None => (arg, None),
+            };
+            let value = value.map(|v| paths::normalize_path(v, &current_dir.to_string_lossy()));
+            match flag {
+                "-f" | "--justfile" => file = value,
+                "-d" | "--working-directory" => working_dir = value,
+                _ => {}
+            }
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((name, value)) if positional.is_empty() && rules::is_env_assignment(arg) => {
+                overrides.insert(name.to_string(), value.to_string());
+            }
+            _ => positional.push(arg.to_string()),
+        }
+    }
+
+    let Some(path) = file.or_else(|| find_justfile(current_dir)) else {
+        return Resolution::Missing;
+    };
+    let justfile = match std::fs::read_to_string(&path) {
+        Ok(text) => Justfile::parse(&text),
+        Err(_) => return Resolution::Missing,
+    };
+    let label = paths::display_path(&path, current_dir);
+    if let Some(why) = &justfile.unsupported {
+        return unresolved("just", &positional, &format!("{} uses {}", label, why));
+    }
+    let dir = working_dir.unwrap_or_else(|| path.parent().map(Path::to_path_buf).unwrap_or_default());
+
+    // Split the positional arguments into recipe invocations by parameter count
+    let mut invocations: Vec<(&Recipe, Vec<String>)> = Vec::new();
+    let mut rest = positional.as_slice();
+    if rest.is_empty() {
+        match justfile.recipes.first() {
+            Some(recipe) => invocations.push((recipe, Vec::new())),
+            None => return Resolution::Missing,
+        }
+    }
+    while let Some((name, after)) = rest.split_first() {
+        let Some(recipe) = justfile.recipe(name) else {
+            return Resolution::Missing;
+        };
+        let count = if recipe.params.iter().any(|p| p.variadic) {
+            after.len()
+        } else {
+            recipe.params.len().min(after.len())
+        };
+        invocations.push((recipe, after[..count].to_vec()));
+        rest = &after[count..];
+    }
+
+    let mut ran = Vec::new();
+    let mut commands = Vec::new();
+    for (recipe, recipe_args) in invocations {
+        if let Err(why) = justfile.plan(recipe, &recipe_args, &overrides, &mut ran, &mut commands, 0) {
+            return unresolved("just", &positional, &why);
+        }
+    }
+
+    Resolution::Commands(
+        commands
+            .into_iter()
+            .map(|(recipe, command)| ScriptCommand {
+                label: format!("{} recipe `{}`", label, recipe),
+                command,
+                dir: dir.clone(),
+            })
+            .collect(),
+    )
+}
+
+/// The nearest justfile in `dir` or its ancestors
+fn find_justfile(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|d| JUSTFILE_NAMES.iter().map(move |n| d.join(n)))
+        .find(|p| p.is_file())
+}
+
+impl Justfile {
+    fn parse(text: &str) -> Justfile {
+        let mut justfile = Justfile::default();
+        let mut lines = text.lines().peekable();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('[') {
+                continue;
+            }
+            let statement = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+            let keyword = statement.split_whitespace().next().unwrap_or_default();
+            if keyword == "set" {
+                continue;
+            }
+            if matches!(keyword, "import" | "mod") {
+                justfile.unsupported.get_or_insert(format!("`{}`", keyword));
+                continue;
+            }
+            if let Some((name, value)) = statement.split_once(":=") {
+                let name = name.trim();
+                match name.strip_prefix("alias ") {
+                    Some(alias) => justfile.aliases.insert(alias.trim().to_string(), value.trim().to_string()),
+                    None => justfile.vars.insert(name.to_string(), value.trim().to_string()),
+                };
+                continue;
+            }
+
+            let Some((header, deps)) = trimmed.split_once(':') else {
+                justfile.unsupported.get_or_insert(format!("`{}`", trimmed));
+                continue;
+            };
+            let mut body = Vec::new();
+            while let Some(next) = lines.peek() {
+                if !next.starts_with([' ', '\t']) && !next.trim().is_empty() {
+                    break;
+                }
+                let next = lines.next().unwrap_or_default().trim();
+                if !next.is_empty() {
+                    body.push(next.to_string());
+                }
+            }
+            match Recipe::parse(header, deps, body) {
+                Ok(recipe) => justfile.recipes.push(recipe),
+                Err(why) => {
+                    justfile.unsupported.get_or_insert(why);
+                }
+            }
+        }
+        justfile
+    }
+
+    fn recipe(&self, name: &str) -> Option<&Recipe> {
+        let name = self.aliases.get(name).map(String::as_str).unwrap_or(name);
+        self.recipes.iter().find(|r| r.name == name)
+    }
+
+    /// Append the lines `recipe` runs, with its dependencies', to `commands`
+    fn plan(
+        &self,
+        recipe: &Recipe,
+        args: &[String],
+        overrides: &HashMap<String, String>,
+        ran: &mut Vec<String>,
+        commands: &mut Vec<(String, String)>,
+        depth: usize,
+    ) -> Result<(), String> {
+        // Like just, a recipe runs once per invocation with the same arguments
+        let key = format!("{} {}", recipe.name, args.join(" "));
+        if ran.contains(&key) {
+            return Ok(());
+        }
+        ran.push(key);
+        if depth > MAX_DEPTH {
+            return Err("dependencies nest too deeply".to_string());
+        }
+
+        for dep in &recipe.deps {
+            let dep = self.recipe(dep).ok_or_else(|| format!("unknown dependency `{}`", dep))?;
+            self.plan(dep, &[], overrides, ran, commands, depth + 1)?;
+        }
+
+        let mut scope = overrides.clone();
+        for (idx, param) in recipe.params.iter().enumerate() {
+            let value = if param.variadic {
+                args.get(idx..).unwrap_or_default().join(" ")
+            } else {
+                match (args.get(idx), &param.default) {
+                    (Some(arg), _) => arg.clone(),
+                    (None, Some(default)) => self.evaluate(default, &scope, 0)?,
+                    (None, None) => return Err(format!("recipe `{}` needs argument `{}`", recipe.name, param.name)),
+                }
+            };
+            scope.insert(param.name.clone(), value);
+        }
+
+        let mut body = recipe.body.as_slice();
+        if let Some(shebang) = body.first().and_then(|l| l.strip_prefix("#!")) {
+            let interpreter = shebang.split_whitespace().find(|w| !w.ends_with("/env")).unwrap_or_default();
+            let interpreter = interpreter.rsplit('/').next().unwrap_or_default();
+            if !SHELL_INTERPRETERS.contains(&interpreter) {
+                return Err(format!("recipe `{}` is a `{}` script", recipe.name, interpreter));
+            }
+            body = &body[1..];
+        }
+        for line in body {
+            if line.starts_with('#') {
+                continue;
+            }
+            let line = self.interpolate(line, &scope)?;
+            commands.push((recipe.name.clone(), strip_line_prefixes(&line).trim_end().to_string()));
+        }
+
+        for dep in &recipe.post_deps {
+            let dep = self.recipe(dep).ok_or_else(|| format!("unknown dependency `{}`", dep))?;
+            self.plan(dep, &[], overrides, ran, commands, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Replace `{{expr}}` in a recipe line; `{{{{` is a literal `{{`
+    fn interpolate(&self, line: &str, scope: &HashMap<String, String>) -> Result<String, String> {
+        let mut out = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            if let Some(escaped) = after.strip_prefix("{{") {
+                out.push_str("{{");
+                rest = escaped;
+                continue;
+            }
+            let end = after.find("}}").ok_or("an unclosed `{{`")?;
+            out.push_str(&self.evaluate(&after[..end], scope, 0)?);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Evaluate string literals and variables joined with `+` or `/`. Function calls,
+    /// conditionals and backticks can't be evaluated statically and are errors.
+    fn evaluate(&self, expr: &str, scope: &HashMap<String, String>, depth: usize) -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err("a recursive variable".to_string());
+        }
+        let mut out = String::new();
+        let mut join = None;
+        let mut rest = expr.trim();
+        while !rest.is_empty() {
+            let (value, after) = match rest.chars().next().unwrap_or_default() {
+                quote @ ('"' | '\'') => {
+                    let end = rest[1..].find(quote).ok_or("an unclosed string")?;
+                    (rest[1..end + 1].to_string(), &rest[end + 2..])
+                }
+                '`' => return Err("a backtick command".to_string()),
+                _ => {
+                    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+                    let name = &rest[..end];
+                    let after = &rest[end..];
+                    if name.is_empty() || name == "if" || after.trim_start().starts_with('(') {
+                        return Err(format!("the expression `{}`", expr.trim()));
+                    }
+                    (self.variable(name, scope, depth)?, after)
+                }
+            };
+            match join {
+                Some('/') => {
+                    out.push('/');
+                    out.push_str(&value);
+                }
+                _ => out.push_str(&value),
+            }
+            rest = after.trim_start();
+            match rest.chars().next() {
+                Some(op @ ('+' | '/')) => {
+                    join = Some(op);
+                    rest = rest[1..].trim_start();
+                }
+                Some(_) => return Err(format!("the expression `{}`", expr.trim())),
+                None => {}
+            }
+        }
+        Ok(out)
+    }
+
+    fn variable(&self, name: &str, scope: &HashMap<String, String>, depth: usize) -> Result<String, String> {
+        if let Some(value) = scope.get(name) {
+            return Ok(value.clone());
+        }
+        match self.vars.get(name) {
+            Some(expr) => self.evaluate(expr, scope, depth + 1),
+            None => Err(format!("undefined variable `{}`", name)),
+        }
+    }
+}
+
+impl Recipe {
+    /// Parse `[@]name params: deps && post-deps` and its body
+    fn parse(header: &str, deps: &str, body: Vec<String>) -> Result<Recipe, String> {
+        let mut words = header.trim().trim_start_matches('@').split_whitespace();
+        let name = words.next().unwrap_or_default().to_string();
+        let params = words
+            .map(|word| {
+                let word = word.trim_start_matches('$');
+                let variadic = word.starts_with(['*', '+']);
+                let word = word.trim_start_matches(['*', '+']);
+                let (name, default) = match word.split_once('=') {
+                    Some((name, default)) => (name, Some(default.to_string())),
+                    None => (word, None),
+                };
+                Param { name: name.to_string(), default, variadic }
+            })
+            .collect();
+
+        if deps.contains('(') {
+            return Err(format!("dependency arguments in recipe `{}`", name));
+        }
+        let (before, after) = deps.split_once("&&").unwrap_or((deps, ""));
+        let words = |s: &str| s.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+        Ok(Recipe { name, params, deps: words(before), post_deps: words(after), body })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn resolve_in(args: &str, dir: &Path) -> Resolution {
+        resolve(&args.split_whitespace().collect::<Vec<_>>(), dir)
+    }
+
+    fn commands(args: &str, dir: &Path) -> Vec<(String, String)> {
+        match resolve_in(args, dir) {
+            Resolution::Commands(commands) => commands.into_iter().map(|c| (c.label, c.command)).collect(),
+            other => panic!("expected commands, got {:?}", other),
+        }
+    }
+
+    fn pair(label: &str, command: &str) -> (String, String) {
+        (label.to_string(), command.to_string())
+    }
+
+    const JUSTFILE: &str = "set shell := [\"bash\", \"-c\"]\n\
+bin := \"app\"\n\
+out := \"target\" / bin\n\
+\n\
+alias b := build\n\
+\n\
+# Build it\n\
+build profile='debug':\n    cargo build --profile {{profile}}\n\n\
+test *args: build\n    @cargo test {{args}}\n\n\
+release: test && notify\n    -cp {{out}} dist/\n\n\
+notify:\n    #!/usr/bin/env bash\n    echo done\n";
+
+    #[test]
+    fn test_recipes() {
+        let dir = TempDir::new("just_recipes");
+        dir.write_file("justfile", JUSTFILE);
+        dir.create_dir("sub");
+
+        assert_eq!(commands("", dir.path()), vec![pair("justfile recipe `build`", "cargo build --profile debug")]);
+        // Recipes rerun when their arguments differ
+        let label = |recipe: &str| format!("{}/justfile recipe `{}`", dir.path_str(), recipe);
+        assert_eq!(
+            commands("b release test --lib", &dir.path().join("sub")),
+            vec![
+                pair(&label("build"), "cargo build --profile release"),
+                pair(&label("build"), "cargo build --profile debug"),
+                pair(&label("test"), "cargo test --lib"),
+            ]
+        );
+        assert_eq!(
+            commands("release", dir.path()),
+            vec![
+                pair("justfile recipe `build`", "cargo build --profile debug"),
+                pair("justfile recipe `test`", "cargo test"),
+                pair("justfile recipe `release`", "cp target/app dist/"),
+                pair("justfile recipe `notify`", "echo done"),
+            ]
+        );
+        assert_eq!(
+            commands("--set bin other release", dir.path())[2],
+            pair("justfile recipe `release`", "cp target/other dist/")
+        );
+        assert_eq!(commands("--list", dir.path()), Vec::new());
+        // After a recipe `-n` is an argument, not a dry run: `release` takes none
+        assert_eq!(resolve_in("release -n", dir.path()), Resolution::Missing);
+        assert_eq!(resolve_in("deploy", dir.path()), Resolution::Missing);
+    }
+
+    #[test]
+    fn test_unresolved() {
+        let dir = TempDir::new("just_unresolved");
+        assert_eq!(resolve_in("build", dir.path()), Resolution::Missing);
+
+        dir.write_file(
+            "justfile",
+            "version := `git describe`\ntag:\n    git tag {{version}}\nhome:\n    echo {{env_var('HOME')}}\nplot:\n    #!/usr/bin/env python3\n    print(1)\n",
+        );
+        assert_eq!(
+            resolve_in("tag", dir.path()),
+            Resolution::Unresolved("Can't resolve `just tag` statically (a backtick command)".to_string())
+        );
+        assert_eq!(
+            resolve_in("home", dir.path()),
+            Resolution::Unresolved("Can't resolve `just home` statically (the expression `env_var('HOME')`)".to_string())
+        );
+        assert_eq!(
+            resolve_in("plot", dir.path()),
+            Resolution::Unresolved("Can't resolve `just plot` statically (recipe `plot` is a `python3` script)".to_string())
+        );
+    }
+}
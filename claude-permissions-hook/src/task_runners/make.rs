@@ -0,0 +1,573 @@
+//! GNU make: explicit rules, prerequisites and simple variables.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{strip_line_prefixes, unresolved};
+use crate::package_scripts::{Resolution, ScriptCommand};
+use crate::paths;
+use crate::rules;
+
+/// Makefile names make looks for, in order
+const MAKEFILE_NAMES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+
+/// make flags that take a separate value
+const VALUE_FLAGS: &[&str] = &[
+    "-f", "--file", "--makefile", "-C", "--directory", "-o", "--old-file", "-W",
+    "--what-if", "-I", "--include-dir",
+];
+
+/// make flags that only print what would run or query state
+const DRY_RUN_FLAGS: &[&str] = &[
+    "-n", "--dry-run", "--just-print", "--recon", "-q", "--question", "-v", "--version", "-h",
+    "--help",
+];
+
+/// Variables make defines itself
+const BUILTIN_VARIABLES: &[(&str, &str)] = &[
+    ("MAKE", "make"), ("RM", "rm -f"), ("CC", "cc"), ("CXX", "g++"), ("AR", "ar"),
+    ("SHELL", "/bin/sh"),
+];
+
+/// Cap on nested variable references and includes, against self-references
+const MAX_DEPTH: usize = 16;
+
+#[derive(Default)]
+struct Makefile {
+    /// The makefile's directory, where recipes run
+    dir: PathBuf,
+    /// Variable name → unexpanded value
+    vars: HashMap<String, String>,
+    rules: Vec<Rule>,
+    /// Why the file can't be resolved statically, if it can't
+    unsupported: Option<String>,
+}
+
+struct Rule {
+    targets: Vec<String>,
+    prerequisites: Vec<String>,
+    recipe: Vec<String>,
+}
+
+/// Why a target couldn't be planned
+enum PlanError {
+    /// No rule and no file: make would fail
+    Missing,
+    Unresolved(String),
+}
+
+/// Resolve `make [flags] [VAR=value] [targets]` to the recipe lines it runs
+pub fn resolve(args: &[&str], current_dir: &Path) -> Resolution {
+    let mut dir = current_dir.to_path_buf();
+    let mut file: Option<PathBuf> = None;
+    let mut targets: Vec<String> = Vec::new();
+    let mut overrides: HashMap<String, String> = HashMap::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        if DRY_RUN_FLAGS.contains(&arg) {
+            return Resolution::Commands(Vec::new());
+        }
+        if arg.starts_with('-') {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+                // Attached short values: `-Csub`, `-fbuild.mk`
+                _ if arg.len() > 2 && (arg.starts_with("-C") || arg.starts_with("-f")) => (&arg[..2], Some(&arg[2..])),
+                _ => (arg, None),
+            };
+            let value = match value {
+                Some(value) => Some(value),
+                None if VALUE_FLAGS.contains(&flag) => {
+                    i += 1;
+                    args.get(i - 1).copied()
+                }
+                // `-j 4`: the job count is optional, so only skip a number
+                None if flag == "-j" || flag == "-l" => {
+                    if args.get(i).is_some_and(|a| a.parse::<f64>().is_ok()) {
+                        i += 1;
+                    }
+                    None
+                }
+                None => None,
+            };
+            match (flag, value) {
+                ("-C" | "--directory", Some(value)) => dir = paths::normalize_path(value, &dir.to_string_lossy()),
+                ("-f" | "--file" | "--makefile", Some(value)) => file = Some(PathBuf::from(value)),
+                _ => {}
+            }
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((name, value)) if rules::is_env_assignment(arg) => {
+                overrides.insert(name.to_string(), value.to_string());
+            }
+            _ => targets.push(arg.to_string()),
+        }
+    }
+
+    let path = match file {
+        Some(file) => paths::normalize_path(&file.to_string_lossy(), &dir.to_string_lossy()),
+        None => match MAKEFILE_NAMES.iter().map(|n| dir.join(n)).find(|p| p.is_file()) {
+            Some(path) => path,
+            None => return Resolution::Missing,
+        },
+    };
+
+    let mut makefile = Makefile { dir: dir.clone(), ..Makefile::default() };
+    makefile.parse(&path, 0);
+    if let Some(why) = &makefile.unsupported {
+        return unresolved("make", &targets, &format!("{} uses {}", paths::display_path(&path, current_dir), why));
+    }
+
+    let requested = if targets.is_empty() {
+        match makefile.default_target() {
+            Some(target) => vec![target],
+            None => return Resolution::Missing,
+        }
+    } else {
+        targets.clone()
+    };
+
+    let label = paths::display_path(&path, current_dir);
+    let mut planned = Vec::new();
+    let mut commands = Vec::new();
+    for target in &requested {
+        match makefile.plan(target, &overrides, &mut planned, &mut commands, 0) {
+            Ok(()) => {}
+            Err(PlanError::Missing) => return Resolution::Missing,
+            Err(PlanError::Unresolved(why)) => return unresolved("make", &targets, &why),
+        }
+    }
+
+    Resolution::Commands(
+        commands
+            .into_iter()
+            .map(|(target, command)| ScriptCommand {
+                label: format!("{} target `{}`", label, target),
+                command,
+                dir: dir.clone(),
+            })
+            .collect(),
+    )
+}
+
+impl Makefile {
+    fn parse(&mut self, path: &Path, depth: usize) {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            self.unsupported.get_or_insert(format!("an unreadable file `{}`", path.display()));
+            return;
+        };
+
+        // Index into `rules` of the rule that tab-indented lines belong to
+        let mut current: Option<usize> = None;
+        let mut in_define = false;
+        for line in logical_lines(&text) {
+            if in_define {
+                in_define = line.trim() != "endef";
+                continue;
+            }
+            if let Some(recipe) = line.strip_prefix('\t') {
+                let recipe = recipe.trim();
+                if let Some(rule) = current.and_then(|idx| self.rules.get_mut(idx)) {
+                    if !recipe.is_empty() && !recipe.starts_with('#') {
+                        rule.recipe.push(recipe.to_string());
+                    }
+                }
+                continue;
+            }
+
+            let statement = strip_comment(&line).trim();
+            if statement.is_empty() {
+                continue;
+            }
+            let keyword = statement.split_whitespace().next().unwrap_or_default();
+            match keyword {
+                "ifeq" | "ifneq" | "ifdef" | "ifndef" | "else" | "endif" => {
+                    self.unsupported.get_or_insert("conditionals".to_string());
+                    continue;
+                }
+                "define" => {
+                    in_define = true;
+                    self.unsupported.get_or_insert("`define` blocks".to_string());
+                    continue;
+                }
+                "include" | "-include" | "sinclude" => {
+                    self.include(statement, keyword, depth);
+                    continue;
+                }
+                "vpath" | "unexport" | "export" if !statement.contains('=') => continue,
+                _ => {}
+            }
+
+            let statement = statement
+                .strip_prefix("override ")
+                .or_else(|| statement.strip_prefix("export "))
+                .unwrap_or(statement)
+                .trim();
+            if let Some((name, op, value)) = split_assignment(statement) {
+                self.assign(name, op, value);
+                current = None;
+            } else if let Some((targets, rest)) = split_rule(statement) {
+                current = self.add_rule(targets, rest);
+            } else {
+                self.unsupported.get_or_insert(format!("`{}`", statement));
+            }
+        }
+    }
+
+    /// Follow `include` directives whose paths can be resolved; optional includes of
+    /// missing files are skipped like make does
+    fn include(&mut self, statement: &str, keyword: &str, depth: usize) {
+        let files = match self.expand(&statement[keyword.len()..], &HashMap::new(), 0) {
+            Ok(files) => files,
+            Err(why) => {
+                self.unsupported.get_or_insert(why);
+                return;
+            }
+        };
+        for file in files.split_whitespace() {
+            let path = paths::normalize_path(file, &self.dir.to_string_lossy());
+            if depth < MAX_DEPTH && path.is_file() {
+                self.parse(&path, depth + 1);
+            } else if keyword == "include" {
+                self.unsupported.get_or_insert(format!("an include of `{}`", file));
+            }
+        }
+    }
+
+    fn assign(&mut self, name: &str, op: &str, value: &str) {
+        let value = match op {
+            // Simply expanded: expand now, keeping the raw text if that fails so the
+            // error surfaces if the variable is used
+            ":" | "::" => self.expand(value, &HashMap::new(), 0).unwrap_or_else(|_| value.to_string()),
+            "+" => match self.vars.get(name) {
+                Some(old) => format!("{} {}", old, value),
+                None => value.to_string(),
+            },
+            "?" if self.vars.contains_key(name) => return,
+            "!" => format!("$(shell {})", value),
+            _ => value.to_string(),
+        };
+        self.vars.insert(name.to_string(), value);
+    }
+
+    /// Add a rule from `targets: prerequisites ; recipe`, returning its index
+    fn add_rule(&mut self, targets: &str, rest: &str) -> Option<usize> {
+        let (prerequisites, inline) = match rest.split_once(';') {
+            Some((prerequisites, inline)) => (prerequisites, Some(inline.trim())),
+            None => (rest, None),
+        };
+        // Target-specific variables (`target: VAR = value`) don't add a rule
+        if split_assignment(prerequisites.trim()).is_some() {
+            return None;
+        }
+        let expanded = self
+            .expand(targets, &HashMap::new(), 0)
+            .and_then(|t| Ok((t, self.expand(prerequisites, &HashMap::new(), 0)?)));
+        let (targets, prerequisites) = match expanded {
+            Ok(expanded) => expanded,
+            Err(why) => {
+                self.unsupported.get_or_insert(why);
+                return None;
+            }
+        };
+        self.rules.push(Rule {
+            targets: targets.split_whitespace().map(str::to_string).collect(),
+            // Order-only prerequisites (after `|`) are still built first
+            prerequisites: prerequisites.split_whitespace().filter(|p| *p != "|").map(str::to_string).collect(),
+            recipe: inline.filter(|r| !r.is_empty()).map(str::to_string).into_iter().collect(),
+        });
+        Some(self.rules.len() - 1)
+    }
+
+    /// The first target that isn't special (`.PHONY`) or a pattern
+    fn default_target(&self) -> Option<String> {
+        self.rules
+            .iter()
+            .flat_map(|r| &r.targets)
+            .find(|t| !t.starts_with('.') && !t.contains('%'))
+            .cloned()
+    }
+
+    /// Append the recipe lines for `target`, after its prerequisites', to `commands`
+    fn plan(
+        &self,
+        target: &str,
+        overrides: &HashMap<String, String>,
+        planned: &mut Vec<String>,
+        commands: &mut Vec<(String, String)>,
+        depth: usize,
+    ) -> Result<(), PlanError> {
+        if planned.iter().any(|t| t == target) {
+            return Ok(());
+        }
+        planned.push(target.to_string());
+        if depth > MAX_DEPTH {
+            return Err(PlanError::Unresolved("prerequisites nest too deeply".to_string()));
+        }
+
+        let rules: Vec<&Rule> = self.rules.iter().filter(|r| r.targets.iter().any(|t| t == target)).collect();
+        if rules.is_empty() {
+            if self.rules.iter().any(|r| r.targets.iter().any(|t| pattern_matches(t, target))) {
+                return Err(PlanError::Unresolved(format!("`{}` is built by a pattern rule", target)));
+            }
+            // A plain file with no rule is up to date
+            return if self.dir.join(target).exists() { Ok(()) } else { Err(PlanError::Missing) };
+        }
+
+        let prerequisites: Vec<&String> = rules.iter().flat_map(|r| &r.prerequisites).collect();
+        for prerequisite in &prerequisites {
+            self.plan(prerequisite, overrides, planned, commands, depth + 1)?;
+        }
+
+        let mut automatic = overrides.clone();
+        automatic.insert("@".to_string(), target.to_string());
+        automatic.insert("<".to_string(), prerequisites.first().map(|p| p.to_string()).unwrap_or_default());
+        automatic.insert("^".to_string(), prerequisites.iter().map(|p| p.as_str()).collect::<Vec<_>>().join(" "));
+        automatic.insert("*".to_string(), String::new());
+        for line in rules.iter().flat_map(|r| &r.recipe) {
+            let expanded = self.expand(line, &automatic, 0).map_err(PlanError::Unresolved)?;
+            commands.push((target.to_string(), strip_line_prefixes(&expanded).to_string()));
+        }
+        Ok(())
+    }
+
+    /// Expand `$(VAR)`, `${VAR}`, `$X` and `$$`. Function calls and substitution
+    /// references can't be evaluated statically and are errors.
+    fn expand(&self, text: &str, locals: &HashMap<String, String>, depth: usize) -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err("a recursive variable".to_string());
+        }
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(at) = rest.find('$') {
+            out.push_str(&rest[..at]);
+            let after = &rest[at + 1..];
+            let Some(next) = after.chars().next() else {
+                out.push('$');
+                rest = "";
+                break;
+            };
+            let (name, consumed) = match next {
+                '$' => {
+                    out.push('$');
+                    rest = &after[1..];
+                    continue;
+                }
+                '(' | '{' => {
+                    let close = if next == '(' { ')' } else { '}' };
+                    let end = matching_close(after, next, close).ok_or("an unbalanced variable reference")?;
+                    (self.expand(&after[1..end], locals, depth + 1)?, end + 1)
+                }
+                c => (c.to_string(), c.len_utf8()),
+            };
+            if name.contains(char::is_whitespace) || name.contains([':', ',', '=']) {
+                let function = name.split_whitespace().next().unwrap_or_default();
+                return Err(format!("`$({} ...)`", function));
+            }
+            out.push_str(&self.lookup(&name, locals, depth)?);
+            rest = &after[consumed..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn lookup(&self, name: &str, locals: &HashMap<String, String>, depth: usize) -> Result<String, String> {
+        if let Some(value) = locals.get(name) {
+            return Ok(value.clone());
+        }
+        if let Some(value) = self.vars.get(name) {
+            return self.expand(value, locals, depth + 1);
+        }
+        if let Some((_, value)) = BUILTIN_VARIABLES.iter().find(|(n, _)| *n == name) {
+            return Ok(value.to_string());
+        }
+        Ok(match name {
+            "CURDIR" => self.dir.to_string_lossy().to_string(),
+            "HOME" => paths::home_dir(),
+            // Like make, fall back to the environment, and undefined variables are empty
+            _ => std::env::var(name).unwrap_or_default(),
+        })
+    }
+}
+
+/// Join backslash-continued lines. Recipe continuations keep their leading tab.
+fn logical_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pending: Option<String> = None;
+    for line in text.lines() {
+        let joined = match pending.take() {
+            Some(mut previous) => {
+                previous.push(' ');
+                previous.push_str(line.trim_start());
+                previous
+            }
+            None => line.to_string(),
+        };
+        match joined.strip_suffix('\\') {
+            Some(head) => pending = Some(head.trim_end().to_string()),
+            None => lines.push(joined),
+        }
+    }
+    lines.extend(pending);
+    lines
+}
+
+/// Drop a `#` comment outside recipes
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(at) if !line[..at].ends_with('\\') => &line[..at],
+        _ => line,
+    }
+}
+
+/// Split `NAME op= value` for the `=`, `:=`, `::=`, `?=`, `+=` and `!=` operators
+fn split_assignment(statement: &str) -> Option<(&str, &str, &str)> {
+    let eq = statement.find('=')?;
+    let left = &statement[..eq];
+    let op_start = left.trim_end_matches([':', '?', '+', '!']).len();
+    let name = left[..op_start].trim();
+    let op = &left[op_start..];
+    if name.is_empty() || name.contains(char::is_whitespace) || name.contains(':') || !matches!(op, "" | ":" | "::" | "?" | "+" | "!") {
+        return None;
+    }
+    Some((name, op, statement[eq + 1..].trim()))
+}
+
+/// Split `targets: rest` (or `targets:: rest`)
+fn split_rule(statement: &str) -> Option<(&str, &str)> {
+    let colon = statement.find(':')?;
+    let rest = &statement[colon + 1..];
+    Some((&statement[..colon], rest.strip_prefix(':').unwrap_or(rest)))
+}
+
+/// Index of the bracket closing the one at the start of `text`
+fn matching_close(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(idx);
+            }
+        }
+    }
+    None
+}
+
+/// Check a `%` pattern target against a target name
+fn pattern_matches(pattern: &str, target: &str) -> bool {
+    match pattern.split_once('%') {
+        Some((prefix, suffix)) => {
+            target.len() >= prefix.len() + suffix.len() && target.starts_with(prefix) && target.ends_with(suffix)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn commands(args: &str, dir: &Path) -> Vec<(String, String)> {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        match resolve(&args, dir) {
+            Resolution::Commands(commands) => commands.into_iter().map(|c| (c.label, c.command)).collect(),
+            other => panic!("expected commands, got {:?}", other),
+        }
+    }
+
+    fn pair(label: &str, command: &str) -> (String, String) {
+        (label.to_string(), command.to_string())
+    }
+
+    const MAKEFILE: &str = "BIN := app\n\
+INSTALL_DIR = $(HOME)/bin\n\
+OUT ?= out\n\
+FLAGS = -v\n\
+FLAGS += --release\n\
+\n\
+.PHONY: all build install clean\n\
+\n\
+all: build # default\n\
+\n\
+build: src/main.rs\n\
+\t@cargo build $(FLAGS)\n\
+\n\
+install: build\n\
+\tmkdir -p $(INSTALL_DIR)\n\
+\tcp $(OUT)/$(BIN) \\\n\
+\t  $(INSTALL_DIR)/$@\n\
+\n\
+clean: ; -rm -f $(OUT)/$(BIN)\n";
+
+    #[test]
+    fn test_targets_and_variables() {
+        let dir = TempDir::new("make_targets");
+        dir.write_file("Makefile", MAKEFILE);
+        dir.create_file("src/main.rs");
+        let home = paths::home_dir();
+
+        assert_eq!(commands("", dir.path()), vec![pair("Makefile target `build`", "cargo build -v --release")]);
+        assert_eq!(
+            commands("install OUT=target", dir.path()),
+            vec![
+                pair("Makefile target `build`", "cargo build -v --release"),
+                pair("Makefile target `install`", &format!("mkdir -p {}/bin", home)),
+                pair("Makefile target `install`", &format!("cp target/app {}/bin/install", home)),
+            ]
+        );
+        assert_eq!(commands("clean", dir.path()), vec![pair("Makefile target `clean`", "rm -f out/app")]);
+        assert_eq!(commands("-n install", dir.path()), Vec::new());
+        // `-p` prints the database and then builds as usual
+        assert_eq!(commands("-p clean", dir.path()), vec![pair("Makefile target `clean`", "rm -f out/app")]);
+    }
+
+    #[test]
+    fn test_directory_and_file_flags() {
+        let dir = TempDir::new("make_flags");
+        dir.write_file("sub/build.mk", "test:\n\tcargo test\n");
+        assert_eq!(
+            commands("-C sub -f build.mk -j 4 test", dir.path()),
+            vec![pair("sub/build.mk target `test`", "cargo test")]
+        );
+    }
+
+    #[test]
+    fn test_missing_and_unresolved() {
+        let dir = TempDir::new("make_unresolved");
+        let resolve_in = |args: &str| resolve(&args.split_whitespace().collect::<Vec<_>>(), dir.path());
+        assert_eq!(resolve_in("build"), Resolution::Missing);
+
+        dir.write_file("Makefile", "FILES = $(shell git ls-files)\nlint:\n\techo ok\nfmt:\n\tprettier $(FILES)\n%.o: %.c\n\tcc -c $<\n");
+        assert_eq!(resolve_in("nope"), Resolution::Missing);
+        assert_eq!(
+            resolve_in("fmt"),
+            Resolution::Unresolved("Can't resolve `make fmt` statically (`$(shell ...)`)".to_string())
+        );
+        assert_eq!(
+            resolve_in("main.o"),
+            Resolution::Unresolved("Can't resolve `make main.o` statically (`main.o` is built by a pattern rule)".to_string())
+        );
+
+        dir.write_file("Makefile", "ifeq ($(OS),Windows_NT)\nX = 1\nendif\nall:\n\techo hi\n");
+        assert_eq!(
+            resolve_in(""),
+            Resolution::Unresolved("Can't resolve `make` statically (Makefile uses conditionals)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_split_assignment() {
+        assert_eq!(split_assignment("A := b c"), Some(("A", ":", "b c")));
+        assert_eq!(split_assignment("A::=b"), Some(("A", "::", "b")));
+        assert_eq!(split_assignment("A ?= b"), Some(("A", "?", "b")));
+        assert_eq!(split_assignment("target: A = b"), None);
+        assert_eq!(split_assignment("build: dep"), None);
+    }
+}
@@ -0,0 +1,39 @@
+//! Task runners (`make`, `just`, `task`) resolved to the recipe lines they would run.
+//!
+//! The runner's file is found from the tracked directory and parsed statically: target
+//! dependencies are expanded and simple variables substituted, so each recipe line can
+//! go through bash evaluation. Anything dynamic (`$(shell ...)`, conditionals,
+//! backticks, includes that can't be followed) leaves the invocation unresolved.
+
+pub mod just;
+pub mod make;
+pub mod taskfile;
+
+use std::path::Path;
+
+use crate::package_scripts::Resolution;
+
+/// Resolve a `make`, `just` or `task` invocation from its tokens (quotes stripped)
+pub fn resolve(tokens: &[&str], current_dir: &Path) -> Resolution {
+    match tokens.split_first() {
+        Some((&"make" | &"gmake", args)) => make::resolve(args, current_dir),
+        Some((&"just", args)) => just::resolve(args, current_dir),
+        Some((&"task", args)) => taskfile::resolve(args, current_dir),
+        _ => Resolution::NotScript,
+    }
+}
+
+/// An invocation that can't be resolved, with why
+fn unresolved(command: &str, targets: &[String], why: &str) -> Resolution {
+    let mut invocation = command.to_string();
+    for target in targets {
+        invocation.push(' ');
+        invocation.push_str(target);
+    }
+    Resolution::Unresolved(format!("Can't resolve `{}` statically ({})", invocation, why))
+}
+
+/// Strip the silent/ignore-errors markers (`@`, `-`, `+`) from the front of a recipe line
+fn strip_line_prefixes(line: &str) -> &str {
+    line.trim_start_matches(['@', '-', '+', ' ', '\t'])
+}
@@ -0,0 +1,370 @@
+//! Task (taskfile.dev): tasks, their `cmds` and `deps`, and `{{.VAR}}` templates.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{strip_line_prefixes, unresolved};
+use crate::package_scripts::{Resolution, ScriptCommand};
+use crate::paths;
+use crate::rules;
+use crate::yaml::{self, Yaml};
+
+/// Taskfile names task looks for in each directory
+const TASKFILE_NAMES: &[&str] = &[
+    "Taskfile.yml", "taskfile.yml", "Taskfile.yaml", "taskfile.yaml", "Taskfile.dist.yml",
+    "taskfile.dist.yml", "Taskfile.dist.yaml", "taskfile.dist.yaml",
+];
+
+/// task flags that take a separate value
+const VALUE_FLAGS: &[&str] = &["-t", "--taskfile", "-d", "--dir", "-o", "--output", "-c", "--color", "--concurrency", "-I", "--interval"];
+
+/// task flags that list or print tasks instead of running them
+const READ_ONLY_FLAGS: &[&str] = &[
+    "-n", "--dry", "-l", "--list", "-a", "--list-all", "--summary", "--status", "--version",
+    "-h", "--help", "--json",
+];
+
+/// Cap on task calls nested through `deps` and `task:`
+const MAX_DEPTH: usize = 16;
+
+struct Taskfile {
+    doc: Yaml,
+    /// The Taskfile's directory, where tasks run by default
+    dir: PathBuf,
+    /// Variables from the command line, which override the Taskfile's
+    overrides: HashMap<String, String>,
+}
+
+/// Resolve `task [flags] [tasks] [VAR=value] [-- args]` to the commands it runs
+pub fn resolve(args: &[&str], current_dir: &Path) -> Resolution {
+    let mut file: Option<PathBuf> = None;
+    let mut dir: Option<PathBuf> = None;
+    let mut tasks: Vec<String> = Vec::new();
+    let mut overrides: HashMap<String, String> = HashMap::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        if arg == "--" {
+            overrides.insert("CLI_ARGS".to_string(), args[i..].join(" "));
+            break;
+        }
+        if READ_ONLY_FLAGS.contains(&arg) {
+            return Resolution::Commands(Vec::new());
+        }
+        if arg.starts_with('-') {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None if VALUE_FLAGS.contains(&arg) => {
+                    i += 1;
+                    (arg, args.get(i - 1).copied())
+                }
+                None => (arg, None),
+            };
+            let value = value.map(|v| paths::normalize_path(v, &current_dir.to_string_lossy()));
+            match flag {
+                "-t" | "--taskfile" => file = value,
+                "-d" | "--dir" => dir = value,
+                _ => {}
+            }
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((name, value)) if rules::is_env_assignment(arg) => {
+                overrides.insert(name.to_string(), value.to_string());
+            }
+            _ => tasks.push(arg.to_string()),
+        }
+    }
+
+    let search_dir = dir.clone().unwrap_or_else(|| current_dir.to_path_buf());
+    let path = match file {
+        Some(file) if file.is_dir() => find_taskfile(&file),
+        Some(file) => Some(file),
+        None => find_taskfile(&search_dir),
+    };
+    let Some(path) = path.filter(|p| p.is_file()) else {
+        return Resolution::Missing;
+    };
+    let label = paths::display_path(&path, current_dir);
+    let Some(doc) = std::fs::read_to_string(&path).ok().and_then(|text| yaml::parse(&text)) else {
+        return unresolved("task", &tasks, &format!("{} isn't YAML this hook can read", label));
+    };
+    let dir = dir.unwrap_or_else(|| path.parent().map(Path::to_path_buf).unwrap_or_default());
+    let taskfile = Taskfile { doc, dir, overrides };
+
+    if tasks.is_empty() {
+        tasks.push("default".to_string());
+    }
+    let mut ran = Vec::new();
+    let mut commands = Vec::new();
+    for task in &tasks {
+        match taskfile.plan(task, &HashMap::new(), &mut ran, &mut commands, 0) {
+            Ok(true) => {}
+            Ok(false) => return Resolution::Missing,
+            Err(why) => return unresolved("task", &tasks, &why),
+        }
+    }
+
+    Resolution::Commands(
+        commands
+            .into_iter()
+            .map(|(task, command, dir)| ScriptCommand { label: format!("{} task `{}`", label, task), command, dir })
+            .collect(),
+    )
+}
+
+/// The nearest Taskfile in `dir` or its ancestors
+fn find_taskfile(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|d| TASKFILE_NAMES.iter().map(move |n| d.join(n)))
+        .find(|p| p.is_file())
+}
+
+impl Taskfile {
+    /// Append the commands `name` runs, after its dependencies', to `commands`.
+    /// `Ok(false)` if there's no such task.
+    fn plan(
+        &self,
+        name: &str,
+        call_vars: &HashMap<String, String>,
+        ran: &mut Vec<String>,
+        commands: &mut Vec<(String, String, PathBuf)>,
+        depth: usize,
+    ) -> Result<bool, String> {
+        let Some(task) = self.doc.get("tasks").and_then(|t| t.get(name)) else {
+            if name.contains(':') && self.doc.get("includes").is_some() {
+                return Err(format!("`{}` comes from an included Taskfile", name));
+            }
+            return Ok(false);
+        };
+        // Tasks run once per invocation unless called with different variables
+        let key = format!("{} {:?}", name, call_vars.iter().collect::<std::collections::BTreeMap<_, _>>());
+        if ran.contains(&key) {
+            return Ok(true);
+        }
+        ran.push(key);
+        if depth > MAX_DEPTH {
+            return Err("task calls nest too deeply".to_string());
+        }
+
+        // Precedence: the caller's vars, the command line, the task's, then the file's
+        let mut fixed = self.overrides.clone();
+        fixed.extend(call_vars.clone());
+        let mut vars = fixed.clone();
+        let file_vars = self.vars(self.doc.get("vars"), &vars, &fixed, name)?;
+        vars.extend(file_vars);
+        let task_vars = self.vars(task.get("vars"), &vars, &fixed, name)?;
+        vars.extend(task_vars);
+
+        let (cmds, deps) = match task {
+            Yaml::Scalar(_) | Yaml::List(_) => (Some(task), None),
+            Yaml::Map(_) => (task.get("cmds").or_else(|| task.get("cmd")), task.get("deps")),
+            Yaml::Null => (None, None),
+        };
+        if task.get("for").is_some() {
+            return Err(format!("task `{}` loops with `for`", name));
+        }
+        let dir = match task.get("dir").and_then(Yaml::as_str) {
+            Some(dir) => paths::normalize_path(&self.template(dir, &vars, name)?, &self.dir.to_string_lossy()),
+            None => self.dir.clone(),
+        };
+
+        for dep in items(deps) {
+            let (dep, dep_vars) = self.call(dep, &vars, name)?;
+            if !self.plan(&dep, &dep_vars, ran, commands, depth + 1)? {
+                return Err(format!("unknown dependency `{}`", dep));
+            }
+        }
+
+        for cmd in items(cmds) {
+            let line = match cmd {
+                Yaml::Scalar(line) => line,
+                _ if cmd.get("task").is_some() => {
+                    let (called, called_vars) = self.call(cmd, &vars, name)?;
+                    if !self.plan(&called, &called_vars, ran, commands, depth + 1)? {
+                        return Err(format!("unknown task `{}`", called));
+                    }
+                    continue;
+                }
+                _ => match cmd.get("cmd").or_else(|| cmd.get("defer")).and_then(Yaml::as_str) {
+                    Some(line) => line,
+                    None => return Err(format!("a command in task `{}` this hook can't read", name)),
+                },
+            };
+            for line in self.template(line, &vars, name)?.lines() {
+                let line = strip_line_prefixes(line.trim());
+                if !line.is_empty() && !line.starts_with('#') {
+                    commands.push((name.to_string(), line.to_string(), dir.clone()));
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Read a `vars:` map, templating each value against `scope` and the entries before
+    /// it. Keys in `fixed` are already set by a caller and skipped; dynamic (`sh:`)
+    /// variables can't be evaluated.
+    fn vars(
+        &self,
+        node: Option<&Yaml>,
+        scope: &HashMap<String, String>,
+        fixed: &HashMap<String, String>,
+        task: &str,
+    ) -> Result<HashMap<String, String>, String> {
+        let mut scope = scope.clone();
+        let mut vars = HashMap::new();
+        for (key, value) in node.and_then(Yaml::as_map).unwrap_or_default() {
+            if fixed.contains_key(key) {
+                continue;
+            }
+            let value = match value {
+                Yaml::Scalar(value) => self.template(value, &scope, task)?,
+                Yaml::Null => String::new(),
+                _ => return Err(format!("the dynamic variable `{}`", key)),
+            };
+            scope.insert(key.clone(), value.clone());
+            vars.insert(key.clone(), value);
+        }
+        Ok(vars)
+    }
+
+    /// A `deps` entry or `task:` command: the task name and the variables it passes
+    fn call(&self, node: &Yaml, vars: &HashMap<String, String>, task: &str) -> Result<(String, HashMap<String, String>), String> {
+        match node {
+            Yaml::Scalar(name) => Ok((self.template(name, vars, task)?, HashMap::new())),
+            _ => {
+                let name = node.get("task").and_then(Yaml::as_str).ok_or_else(|| format!("a dependency of `{}` this hook can't read", task))?;
+                Ok((self.template(name, vars, task)?, self.vars(node.get("vars"), vars, &HashMap::new(), task)?))
+            }
+        }
+    }
+
+    /// Fill `{{.VAR}}` placeholders; other template syntax can't be evaluated
+    fn template(&self, text: &str, vars: &HashMap<String, String>, task: &str) -> Result<String, String> {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after.find("}}").ok_or("an unclosed `{{`")?;
+            let expr = after[..end].trim();
+            let name = expr
+                .strip_prefix('.')
+                .filter(|n| n.chars().all(|c| c.is_alphanumeric() || c == '_'))
+                .ok_or_else(|| format!("the template `{{{{{}}}}}`", expr))?;
+            let value = match (vars.get(name), name) {
+                (Some(value), _) => value.clone(),
+                (None, "TASK") => task.to_string(),
+                (None, "ROOT_DIR" | "TASKFILE_DIR") => self.dir.to_string_lossy().to_string(),
+                (None, "USER_WORKING_DIR") => std::env::current_dir().unwrap_or_default().to_string_lossy().to_string(),
+                // Undefined variables render empty, like Go templates
+                (None, _) => String::new(),
+            };
+            out.push_str(&value);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// A node that's a single item or a list of them
+fn items(node: Option<&Yaml>) -> Vec<&Yaml> {
+    match node {
+        Some(Yaml::Null) | None => Vec::new(),
+        Some(node) => node.as_list().map(|items| items.iter().collect()).unwrap_or_else(|| vec![node]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn resolve_in(args: &str, dir: &Path) -> Resolution {
+        resolve(&args.split_whitespace().collect::<Vec<_>>(), dir)
+    }
+
+    fn commands(args: &str, dir: &Path) -> Vec<(String, String)> {
+        match resolve_in(args, dir) {
+            Resolution::Commands(commands) => commands.into_iter().map(|c| (c.label, c.command)).collect(),
+            other => panic!("expected commands, got {:?}", other),
+        }
+    }
+
+    fn pair(label: &str, command: &str) -> (String, String) {
+        (label.to_string(), command.to_string())
+    }
+
+    const TASKFILE: &str = "version: '3'
+vars:
+  BIN: app
+  OUT: 'dist/{{.BIN}}'
+tasks:
+  default:
+    deps: [build]
+  build:
+    cmds:
+      - go build -o {{.OUT}} .
+  test:
+    deps: [build]
+    cmds:
+      - go test ./... {{.CLI_ARGS}}
+      - task: lint
+        vars: {STRICT: 'yes'}
+  lint: golangci-lint run --strict={{.STRICT}}
+  docs:
+    dir: site
+    cmds:
+      - |
+        npm ci
+        npm run build
+";
+
+    #[test]
+    fn test_tasks() {
+        let dir = TempDir::new("taskfile_tasks");
+        dir.write_file("Taskfile.yml", TASKFILE);
+
+        assert_eq!(commands("", dir.path()), vec![pair("Taskfile.yml task `build`", "go build -o dist/app .")]);
+        assert_eq!(
+            commands("test BIN=cli -- -run Foo", dir.path()),
+            vec![
+                pair("Taskfile.yml task `build`", "go build -o dist/cli ."),
+                pair("Taskfile.yml task `test`", "go test ./... -run Foo"),
+                pair("Taskfile.yml task `lint`", "golangci-lint run --strict=yes"),
+            ]
+        );
+        match resolve_in("docs", dir.path()) {
+            Resolution::Commands(commands) => {
+                assert_eq!(commands.iter().map(|c| c.command.as_str()).collect::<Vec<_>>(), vec!["npm ci", "npm run build"]);
+                assert_eq!(commands[0].dir, dir.path().join("site"));
+            }
+            other => panic!("expected commands, got {:?}", other),
+        }
+        assert_eq!(commands("--list", dir.path()), Vec::new());
+        assert_eq!(resolve_in("deploy", dir.path()), Resolution::Missing);
+    }
+
+    #[test]
+    fn test_unresolved() {
+        let dir = TempDir::new("taskfile_unresolved");
+        assert_eq!(resolve_in("build", dir.path()), Resolution::Missing);
+
+        dir.write_file(
+            "Taskfile.yml",
+            "version: '3'\nvars:\n  SHA:\n    sh: git rev-parse HEAD\ntasks:\n  tag: git tag {{.SHA}}\n",
+        );
+        assert_eq!(
+            resolve_in("tag", dir.path()),
+            Resolution::Unresolved("Can't resolve `task tag` statically (the dynamic variable `SHA`)".to_string())
+        );
+        dir.write_file("Taskfile.yml", "version: '3'\ntasks:\n  up: docker compose up {{if .D}}-d{{end}}\n");
+        assert_eq!(
+            resolve_in("up", dir.path()),
+            Resolution::Unresolved("Can't resolve `task up` statically (the template `{{if .D}}`)".to_string())
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! A small TOML reader for config files the hook inspects (`.cargo/config.toml`,
+//! `pyproject.toml` and the like): tables, arrays of tables, dotted keys, strings,
+//! arrays and inline tables. Numbers, booleans and dates are kept as their raw text.
+
+/// A parsed TOML value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Toml {
+    Scalar(String),
+    Array(Vec<Toml>),
+    Table(Vec<(String, Toml)>),
+}
+
+impl Toml {
+    /// Look up a key in a table
+    pub fn get(&self, key: &str) -> Option<&Toml> {
+        match self {
+            Toml::Table(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Toml::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Toml]> {
+        match self {
+            Toml::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a TOML document; `None` if it's malformed or uses unsupported syntax
+pub fn parse(text: &str) -> Option<Toml> {
+    let mut parser = Parser { text, pos: 0 };
+    let mut root = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    loop {
+        parser.skip_trivia();
+        if parser.done() {
+            break;
+        }
+        if parser.eat("[[") {
+            let path = parser.key_path()?;
+            parser.expect("]]")?;
+            let (last, parent) = path.split_last()?;
+            let parent = table_at(&mut root, parent)?;
+            let idx = match parent.iter().position(|(k, _)| k == last) {
+                Some(idx) => idx,
+                None => {
+                    parent.push((last.clone(), Toml::Array(Vec::new())));
+                    parent.len() - 1
+                }
+            };
+            match &mut parent[idx].1 {
+                Toml::Array(items) => items.push(Toml::Table(Vec::new())),
+                _ => return None,
+            }
+            current = path;
+        } else if parser.eat("[") {
+            let path = parser.key_path()?;
+            parser.expect("]")?;
+            table_at(&mut root, &path)?;
+            current = path;
+        } else {
+            let key = parser.key_path()?;
+            parser.expect("=")?;
+            let value = parser.value()?;
+            insert(table_at(&mut root, &current)?, &key, value)?;
+        }
+        parser.end_of_line()?;
+    }
+    Some(Toml::Table(root))
+}
+
+/// The table at `path`, creating missing tables; arrays of tables resolve to their
+/// last element
+fn table_at<'a>(root: &'a mut Vec<(String, Toml)>, path: &[String]) -> Option<&'a mut Vec<(String, Toml)>> {
+    let mut table = root;
+    for key in path {
+        let idx = match table.iter().position(|(k, _)| k == key) {
+            Some(idx) => idx,
+            None => {
+                table.push((key.clone(), Toml::Table(Vec::new())));
+                table.len() - 1
+            }
+        };
+        table = match &mut table[idx].1 {
+            Toml::Table(entries) => entries,
+            Toml::Array(items) => match items.last_mut() {
+                Some(Toml::Table(entries)) => entries,
+                _ => return None,
+            },
+            Toml::Scalar(_) => return None,
+        };
+    }
+    Some(table)
+}
+
+/// Insert a value at a dotted key within a table
+fn insert(table: &mut Vec<(String, Toml)>, key: &[String], value: Toml) -> Option<()> {
+    let (last, parents) = key.split_last()?;
+    let table = table_at(table, parents)?;
+    if table.iter().any(|(k, _)| k == last) {
+        return None;
+    }
+    table.push((last.clone(), value));
+    Some(())
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.text.len()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> Option<()> {
+        self.skip_spaces();
+        self.eat(token).then_some(())
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    /// Skip whitespace, newlines and comments
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with('#') {
+                break;
+            }
+            self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    /// After a statement only a comment may follow on the line
+    fn end_of_line(&mut self) -> Option<()> {
+        self.skip_spaces();
+        let rest = self.rest();
+        if rest.starts_with('#') {
+            self.pos += rest.find('\n').unwrap_or(rest.len());
+        }
+        let rest = self.rest();
+        (rest.is_empty() || rest.starts_with('\n') || rest.starts_with("\r\n")).then_some(())
+    }
+
+    /// `a.b."c d"`
+    fn key_path(&mut self) -> Option<Vec<String>> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_spaces();
+            let key = match self.rest().chars().next()? {
+                '"' | '\'' => self.string()?,
+                _ => {
+                    let rest = self.rest();
+                    let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+                    if len == 0 {
+                        return None;
+                    }
+                    self.pos += len;
+                    rest[..len].to_string()
+                }
+            };
+            path.push(key);
+            self.skip_spaces();
+            if !self.eat(".") {
+                return Some(path);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<Toml> {
+        self.skip_spaces();
+        match self.rest().chars().next()? {
+            '"' | '\'' => self.string().map(Toml::Scalar),
+            '[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_trivia();
+                    if self.eat("]") {
+                        return Some(Toml::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_trivia();
+                    if !self.eat(",") {
+                        self.skip_trivia();
+                        self.eat("]").then_some(())?;
+                        return Some(Toml::Array(items));
+                    }
+                }
+            }
+            '{' => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                loop {
+                    self.skip_spaces();
+                    if self.eat("}") {
+                        return Some(Toml::Table(entries));
+                    }
+                    let key = self.key_path()?;
+                    self.expect("=")?;
+                    let value = self.value()?;
+                    insert(&mut entries, &key, value)?;
+                    self.skip_spaces();
+                    self.eat(",");
+                }
+            }
+            _ => {
+                let rest = self.rest();
+                let len = rest.find([',', ']', '}', '#', ' ', '\t', '\r', '\n']).unwrap_or(rest.len());
+                let raw = rest[..len].trim();
+                if raw.is_empty() {
+                    return None;
+                }
+                self.pos += len;
+                Some(Toml::Scalar(raw.to_string()))
+            }
+        }
+    }
+
+    /// A basic (`"..."`), literal (`'...'`) or multi-line (`"""`/`'''`) string
+    fn string(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let quote = rest.chars().next()?;
+        let literal = quote == '\'';
+        let delimiter = if rest.starts_with("\"\"\"") || rest.starts_with("'''") { &rest[..3] } else { &rest[..1] };
+        let body_start = delimiter.len();
+        // A newline right after the opening delimiter is trimmed
+        let skip_newline = usize::from(delimiter.len() == 3 && rest[body_start..].starts_with('\n'));
+
+        let mut out = String::new();
+        let mut chars = rest[body_start + skip_newline..].char_indices();
+        while let Some((idx, c)) = chars.next() {
+            let at = body_start + skip_newline + idx;
+            if rest[at..].starts_with(delimiter) {
+                self.pos += at + delimiter.len();
+                return Some(out);
+            }
+            if c == '\n' && delimiter.len() == 1 {
+                return None;
+            }
+            if c == '\\' && !literal {
+                match chars.next()?.1 {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    '"' => out.push('"'),
+                    '\\' => out.push('\\'),
+                    // Line-ending backslash in multi-line strings joins the lines
+                    '\n' => {
+                        let mut lookahead = chars.clone();
+                        while lookahead.next().is_some_and(|(_, c)| c.is_whitespace()) {
+                            chars.next();
+                        }
+                    }
+                    other => {
+                        out.push('\\');
+                        out.push(other);
+                    }
+                }
+                continue;
+            }
+            out.push(c);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(s: &str) -> Toml {
+        Toml::Scalar(s.to_string())
+    }
+
+    #[test]
+    fn test_tables_and_values() {
+        let doc = parse(
+            "# cargo config\n\
+             [alias]\n\
+             xtask = \"run --package xtask --\"  # comment\n\
+             ci = ['check', \"--all\"]\n\
+             \n\
+             [build]\n\
+             jobs = 4\n\
+             target.dir = { path = \"out\", 'quoted key' = true }\n\
+             \n\
+             [[bin]]\n\
+             name = \"a\"\n\
+             [[bin]]\n\
+             name = \"b\"\n",
+        )
+        .unwrap();
+        let alias = doc.get("alias").unwrap();
+        assert_eq!(alias.get("xtask"), Some(&scalar("run --package xtask --")));
+        assert_eq!(alias.get("ci"), Some(&Toml::Array(vec![scalar("check"), scalar("--all")])));
+        let build = doc.get("build").unwrap();
+        assert_eq!(build.get("jobs"), Some(&scalar("4")));
+        let dir = build.get("target").and_then(|t| t.get("dir")).unwrap();
+        assert_eq!(dir.get("path"), Some(&scalar("out")));
+        assert_eq!(dir.get("quoted key"), Some(&scalar("true")));
+        let bins = doc.get("bin").and_then(Toml::as_array).unwrap();
+        assert_eq!(bins.iter().map(|b| b.get("name").and_then(Toml::as_str)).collect::<Vec<_>>(), vec![Some("a"), Some("b")]);
+    }
+
+    #[test]
+    fn test_strings_and_multiline() {
+        let doc = parse(
+            "a = \"tab\\there \\\"q\\\"\"\nb = 'C:\\path'\nc = \"\"\"\nline one\nline two\"\"\"\nd = [\n  1,\n  2, # two\n]\n",
+        )
+        .unwrap();
+        assert_eq!(doc.get("a"), Some(&scalar("tab\there \"q\"")));
+        assert_eq!(doc.get("b"), Some(&scalar("C:\\path")));
+        assert_eq!(doc.get("c"), Some(&scalar("line one\nline two")));
+        assert_eq!(doc.get("d"), Some(&Toml::Array(vec![scalar("1"), scalar("2")])));
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert_eq!(parse("a = \"unclosed\n"), None);
+        assert_eq!(parse("a = 1 b = 2\n"), None);
+        assert_eq!(parse("a = 1\na = 2\n"), None);
+        assert_eq!(parse("[table\n"), None);
+    }
+}
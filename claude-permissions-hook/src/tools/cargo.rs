@@ -0,0 +1,251 @@
+//! cargo: toolchain overrides, global flags, aliases and a policy per subcommand.
+
+use std::path::{Path, PathBuf};
+
+use super::{check_path_flag, Judgment};
+use crate::paths;
+use crate::toml;
+
+/// Global flags that take a separate value
+const GLOBAL_VALUE_FLAGS: &[&str] = &["--color", "--config", "-Z", "-C", "--explain", "--manifest-path"];
+
+/// Global flags that only print information
+const INFO_FLAGS: &[&str] = &["-V", "--version", "--list", "-h", "--help", "--explain"];
+
+/// Subcommands that build, test, document or inspect the local workspace. `run`
+/// and `bench` execute the workspace's own code, which `test` and build scripts
+/// already do.
+const LOCAL_SUBCOMMANDS: &[&str] = &[
+    "build", "check", "test", "clippy", "fmt", "run", "bench", "doc", "rustdoc", "rustc",
+    "tree", "metadata", "nextest", "llvm-cov", "verify-project", "read-manifest",
+    "locate-project", "pkgid", "search", "info", "version", "help",
+];
+
+/// Every other built-in subcommand; aliases can't shadow these
+const OTHER_SUBCOMMANDS: &[&str] = &[
+    "add", "remove", "update", "clean", "new", "init", "fix", "fetch", "generate-lockfile",
+    "vendor", "package", "install", "uninstall", "publish", "yank", "owner", "login",
+    "logout", "report", "config",
+];
+
+/// Aliases cargo defines itself
+const BUILTIN_ALIASES: &[(&str, &str)] = &[("b", "build"), ("c", "check"), ("d", "doc"), ("t", "test"), ("r", "run"), ("rm", "remove")];
+
+/// Cap on aliases expanding to other aliases
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// Judge `cargo [+toolchain] [global flags] <subcommand> [args]`
+pub fn judge(args: &[&str], current_dir: &Path) -> Judgment {
+    // `+nightly` only counts as the first argument
+    let args = match args.split_first() {
+        Some((toolchain, rest)) if toolchain.starts_with('+') => rest,
+        _ => args,
+    };
+    let mut args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let mut dir = current_dir.to_path_buf();
+    let mut alias: Option<String> = None;
+
+    let mut expansions = 0;
+    let (sub, position) = loop {
+        let mut i = 0;
+        while i < args.len() && args[i].starts_with('-') {
+            let flag = args[i].as_str();
+            if INFO_FLAGS.contains(&flag) {
+                return Judgment::Safe;
+            }
+            if GLOBAL_VALUE_FLAGS.contains(&flag) {
+                if flag == "-C" {
+                    if let Some(value) = args.get(i + 1) {
+                        dir = paths::normalize_path(value, &dir.to_string_lossy());
+                    }
+                }
+                i += 1;
+            }
+            i += 1;
+        }
+        let Some(sub) = args.get(i).cloned() else {
+            // Bare `cargo` prints usage
+            return Judgment::Safe;
+        };
+        if LOCAL_SUBCOMMANDS.contains(&sub.as_str()) || OTHER_SUBCOMMANDS.contains(&sub.as_str()) {
+            break (sub, i);
+        }
+        let Some(expansion) = find_alias(&sub, &dir) else {
+            // External `cargo-<name>` subcommands run arbitrary binaries
+            return Judgment::Unknown;
+        };
+        expansions += 1;
+        if expansions > MAX_ALIAS_DEPTH {
+            return Judgment::Unknown;
+        }
+        if !BUILTIN_ALIASES.iter().any(|(name, _)| *name == sub) {
+            alias.get_or_insert(sub);
+        }
+        args.splice(i..=i, expansion);
+    };
+
+    // A user alias is judged as the command it expands to, like a script body
+    if let Some(alias) = alias {
+        return Judgment::Nested { label: format!("cargo alias `{}`", alias), command: format!("cargo {}", args.join(" ")) };
+    }
+    // `--manifest-path` also works as a global flag, before the subcommand
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    for flag in ["--manifest-path", "--target-dir"] {
+        if let Some(judgment) = check_path_flag(&args, flag, "cargo", &dir) {
+            return judgment;
+        }
+    }
+    judge_subcommand(&sub, &args[position + 1..])
+}
+
+fn judge_subcommand(sub: &str, args: &[&str]) -> Judgment {
+    let dry_run = args.iter().any(|a| matches!(*a, "--dry-run" | "-n"));
+    match sub {
+        _ if LOCAL_SUBCOMMANDS.contains(&sub) => match (sub, args.first().copied()) {
+            ("nextest", Some("self")) => Judgment::Unknown,
+            _ => Judgment::Safe,
+        },
+        "publish" if dry_run => Judgment::Safe,
+        "publish" => Judgment::Ask("cargo publish uploads a release to the registry, which can't be undone".to_string()),
+        "yank" => Judgment::Ask("cargo yank changes a published release on the registry".to_string()),
+        "owner" if args.iter().any(|a| matches!(*a, "-l" | "--list")) => Judgment::Safe,
+        "owner" => Judgment::Ask("cargo owner changes who can publish the crate".to_string()),
+        "login" | "logout" => Judgment::Ask(format!("cargo {} changes the stored registry token", sub)),
+        "install" if args.contains(&"--list") => Judgment::Safe,
+        "install" => Judgment::Ask(format!("cargo install builds and installs {} into ~/.cargo/bin", install_source(args))),
+        "uninstall" => Judgment::Ask("cargo uninstall removes binaries from ~/.cargo/bin".to_string()),
+        _ => Judgment::Unknown,
+    }
+}
+
+/// What `cargo install` installs, for the ask reason
+fn install_source(args: &[&str]) -> String {
+    for flag in ["--path", "--git"] {
+        if let Some(value) = super::flag_value(args, flag) {
+            return format!("`{}`", value);
+        }
+    }
+    let crates: Vec<String> = args.iter().filter(|a| !a.starts_with('-')).map(|a| format!("`{}`", a)).collect();
+    if crates.is_empty() {
+        "the current package".to_string()
+    } else {
+        crates.join(", ")
+    }
+}
+
+/// The expansion of an alias: built in, or from the nearest `.cargo/config.toml` up
+/// from `dir`, then `$CARGO_HOME/config.toml`
+fn find_alias(name: &str, dir: &Path) -> Option<Vec<String>> {
+    if let Some((_, expansion)) = BUILTIN_ALIASES.iter().find(|(alias, _)| *alias == name) {
+        return Some(vec![expansion.to_string()]);
+    }
+    let cargo_home = std::env::var("CARGO_HOME").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(paths::home_dir()).join(".cargo"));
+    let configs = dir
+        .ancestors()
+        .map(|d| d.join(".cargo"))
+        .chain(std::iter::once(cargo_home))
+        .flat_map(|d| [d.join("config.toml"), d.join("config")]);
+    for config in configs {
+        let Some(doc) = std::fs::read_to_string(&config).ok().and_then(|text| toml::parse(&text)) else {
+            continue;
+        };
+        // Aliases are a command line string or an array of arguments
+        if let Some(value) = doc.get("alias").and_then(|a| a.get(name)) {
+            return match value.as_array() {
+                Some(items) => items.iter().map(|i| i.as_str().map(str::to_string)).collect(),
+                None => value.as_str().map(|line| line.split_whitespace().map(str::to_string).collect()),
+            };
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    #[test]
+    fn test_toolchain_and_global_flags() {
+        let dir = TempDir::new("cargo_flags");
+        assert_eq!(judge_in("cargo +nightly test", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("cargo -q --locked --color always build --release", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("cargo -Z unstable-options t", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("cargo --version", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("cargo nextest run --workspace", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("cargo nextest self update", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("cargo add serde", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("cargo some-external-tool", dir.path()), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_registry_subcommands() {
+        let dir = TempDir::new("cargo_registry");
+        assert_eq!(
+            judge_in("cargo publish", dir.path()),
+            Judgment::Ask("cargo publish uploads a release to the registry, which can't be undone".to_string())
+        );
+        assert_eq!(judge_in("cargo publish --dry-run", dir.path()), Judgment::Safe);
+        // `--version` after the subcommand is yank's own flag
+        assert_eq!(
+            judge_in("cargo yank --version 1.0.0", dir.path()),
+            Judgment::Ask("cargo yank changes a published release on the registry".to_string())
+        );
+        assert_eq!(judge_in("cargo owner --list", dir.path()), Judgment::Safe);
+        assert_eq!(
+            judge_in("cargo install ripgrep fd-find", dir.path()),
+            Judgment::Ask("cargo install builds and installs `ripgrep`, `fd-find` into ~/.cargo/bin".to_string())
+        );
+        assert_eq!(
+            judge_in("cargo install --path .", dir.path()),
+            Judgment::Ask("cargo install builds and installs `.` into ~/.cargo/bin".to_string())
+        );
+        assert_eq!(judge_in("cargo login", dir.path()), Judgment::Ask("cargo login changes the stored registry token".to_string()));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let dir = TempDir::new("cargo_aliases");
+        dir.write_file(
+            ".cargo/config.toml",
+            "[alias]\nxtask = \"run --package xtask --\"\nrelease = [\"publish\", \"--no-verify\"]\nci = \"lint\"\nlint = \"clippy --all-targets\"\nbuild = \"publish\"\n",
+        );
+        dir.create_dir("crates/core");
+        let nested = dir.path().join("crates/core");
+        let alias = |name: &str, command: &str| Judgment::Nested { label: format!("cargo alias `{}`", name), command: command.to_string() };
+        assert_eq!(judge_in("cargo -q xtask dist", &nested), alias("xtask", "cargo -q run --package xtask -- dist"));
+        // Aliases may expand to other aliases; the first one names the expansion
+        assert_eq!(judge_in("cargo ci", dir.path()), alias("ci", "cargo clippy --all-targets"));
+        // Aliases can't shadow built-in subcommands
+        assert_eq!(judge_in("cargo build", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("cargo t --lib", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("cargo release", dir.path()), alias("release", "cargo publish --no-verify"));
+        dir.write_file(".cargo/config.toml", "[alias]\nloop = \"loop\"\n");
+        assert_eq!(judge_in("cargo loop", dir.path()), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_path_flags() {
+        let dir = TempDir::new("cargo_paths");
+        dir.create_dir(".git");
+        assert_eq!(judge_in("cargo build --target-dir target/alt", dir.path()), Judgment::Safe);
+        assert_eq!(
+            judge_in("cargo build --target-dir=/var/tmp/out", dir.path()),
+            Judgment::Ask("cargo --target-dir `/var/tmp/out` is outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_in("cargo test --manifest-path ../other/Cargo.toml", dir.path()),
+            Judgment::Ask("cargo --manifest-path `../other/Cargo.toml` is outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_in("cargo --manifest-path ../other/Cargo.toml build", dir.path()),
+            Judgment::Ask("cargo --manifest-path `../other/Cargo.toml` is outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_in("cargo -q --manifest-path=/srv/app/Cargo.toml test", dir.path()),
+            Judgment::Ask("cargo --manifest-path `/srv/app/Cargo.toml` is outside the repo".to_string())
+        );
+        assert_eq!(judge_in("cargo --manifest-path crates/core/Cargo.toml build", dir.path()), Judgment::Safe);
+    }
+}
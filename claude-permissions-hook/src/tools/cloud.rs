@@ -0,0 +1,605 @@
+//! Cloud CLIs: aws, gcloud, az, wrangler and flyctl. Each classifier parses the service
+//! and operation (`aws ec2 describe-instances`, `gcloud compute instances list`) and
+//! sorts it into reads, which are allowed, and changes, which ask. Commands that print
+//! secrets or credentials are denied. The profile, project, subscription, environment or
+//! app a command runs against is matched against `cloud.protected_environments`: there,
+//! reads ask and changes are denied.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::Judgment;
+use crate::config::Config;
+use crate::paths;
+use crate::rules;
+use crate::sql::{self, Effect};
+
+/// Environment name patterns that are protected unless configured otherwise
+const DEFAULT_PROTECTED_ENVIRONMENTS: &[&str] = &["prod*", "*-prod", "*-prod-*", "*_prod", "*production*"];
+
+/// aws flags that take a value: the global ones, then the s3 and s3api ones that
+/// commonly sit between operands
+const AWS_VALUE_FLAGS: &[&str] = &[
+    "--profile", "--region", "--output", "--endpoint-url", "--query", "--cli-read-timeout",
+    "--cli-connect-timeout", "--ca-bundle", "--color", "--cli-binary-format",
+    "--exclude", "--include", "--acl", "--storage-class", "--content-type", "--cache-control",
+    "--metadata", "--sse", "--sse-kms-key-id", "--bucket", "--key", "--version-id", "--range",
+];
+
+/// aws operations that print secrets or credentials
+const AWS_SECRET_GETTERS: &[(&str, &str)] = &[
+    ("secretsmanager", "get-secret-value"), ("secretsmanager", "batch-get-secret-value"),
+    ("kms", "decrypt"), ("sts", "get-session-token"), ("sts", "assume-role"),
+    ("sts", "assume-role-with-saml"), ("sts", "assume-role-with-web-identity"),
+    ("sts", "get-federation-token"), ("ecr", "get-login-password"), ("ecr", "get-authorization-token"),
+    ("ecr-public", "get-login-password"), ("ecr-public", "get-authorization-token"),
+    ("codeartifact", "get-authorization-token"), ("rds", "generate-db-auth-token"), ("eks", "get-token"),
+    ("configure", "export-credentials"), ("sso", "get-role-credentials"),
+    ("cognito-identity", "get-credentials-for-identity"), ("redshift", "get-cluster-credentials"),
+    ("ec2", "get-password-data"),
+];
+
+/// aws operations whose last operand is a local file they write
+const AWS_OUTFILE_WRITERS: &[(&str, &str)] = &[("s3api", "get-object"), ("s3api", "get-object-torrent")];
+
+/// aws operations (other than `describe-*`, `list-*` and `get-*`) that only read
+const AWS_READS: &[&str] = &["wait", "help", "scan", "query", "filter-log-events", "tail", "lookup-events", "validate-template", "head-object", "head-bucket"];
+
+/// gcloud and az flags that take a value
+const GCLOUD_VALUE_FLAGS: &[&str] = &[
+    "--project", "--account", "--configuration", "--format", "--filter", "--limit", "--region",
+    "--zone", "--verbosity", "--impersonate-service-account", "--billing-project", "--flatten",
+    "--sort-by", "--page-size", "--subscription", "-g", "--resource-group", "-n", "--name", "-o",
+    "--output", "--query", "-l", "--location",
+];
+
+/// gcloud and az verbs that only read
+const GCLOUD_READS: &[&str] = &["list", "describe", "show", "get-iam-policy", "get-value", "read", "tail", "info", "version", "help", "search", "find", "exists", "wait"];
+
+/// gcloud and az verbs that change resources
+const GCLOUD_WRITES: &[&str] = &[
+    "create", "update", "patch", "set", "unset", "start", "stop", "restart", "reset", "resize",
+    "enable", "disable", "import", "export", "submit", "rollback", "deploy", "up", "scale", "assign",
+    "upload", "swap", "renew", "regenerate", "rotate", "activate", "delete", "purge", "remove",
+    "add", "get-credentials",
+];
+
+/// gcloud and az commands (path and verb) that print secrets or credentials
+const GCLOUD_SECRET_GETTERS: &[(&[&str], &str)] = &[
+    (&["secrets", "versions"], "access"), (&["auth"], "print-access-token"), (&["auth"], "print-identity-token"),
+    (&["auth", "application-default"], "print-access-token"), (&["kms"], "decrypt"),
+    (&["keyvault", "secret"], "show"), (&["keyvault", "secret"], "download"), (&["keyvault", "key"], "download"),
+    (&["account"], "get-access-token"), (&["storage", "account", "keys"], "list"),
+    (&["storage", "account"], "show-connection-string"), (&["acr", "credential"], "show"),
+    (&["cosmosdb", "keys"], "list"), (&["webapp", "deployment"], "list-publishing-credentials"),
+    (&["webapp", "deployment"], "list-publishing-profiles"), (&["redis"], "list-keys"),
+];
+
+/// wrangler and flyctl verbs that only read
+const PLATFORM_READS: &[&str] = &[
+    "whoami", "tail", "list", "ls", "status", "view", "get", "info", "show", "logs", "releases",
+    "version", "doctor", "types", "check", "docs", "env", "regions", "history", "dig", "ping",
+];
+
+/// wrangler and flyctl verbs that change resources
+const PLATFORM_WRITES: &[&str] = &[
+    "put", "create", "set", "import", "bulk", "upload", "rollback", "stop", "start", "restart",
+    "update", "run", "clone", "extend", "add", "suspend", "resume", "move", "attach", "detach",
+    "count", "vm", "memory", "allocate-v4", "allocate-v6", "apply", "restore", "execute",
+];
+
+/// wrangler and flyctl verbs that delete resources
+const PLATFORM_DELETES: &[&str] = &["delete", "destroy", "remove", "rm", "unset", "release", "kill"];
+
+/// Which accounts, projects and apps get the stricter rules
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CloudPolicy {
+    /// Patterns (`*`, `?`) for aws profiles, gcloud projects, az subscriptions, wrangler
+    /// environments and fly apps where reads ask and changes are denied; replaces the
+    /// built-in patterns when set
+    pub protected_environments: Option<Vec<String>>,
+}
+
+impl CloudPolicy {
+    /// Names are compared lowercased: subscription names are often capitalized (`Production`)
+    fn is_protected(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        match &self.protected_environments {
+            Some(patterns) => patterns.iter().any(|pattern| paths::glob_match(pattern, &name)),
+            None => DEFAULT_PROTECTED_ENVIRONMENTS.iter().any(|pattern| paths::glob_match(pattern, &name)),
+        }
+    }
+}
+
+/// Check if a program is modelled here
+pub fn is_cloud_tool(program: &str) -> bool {
+    matches!(program, "aws" | "gcloud" | "az" | "wrangler" | "fly" | "flyctl")
+}
+
+/// What a command does to the account
+#[derive(Debug, PartialEq, Eq)]
+enum Access {
+    /// Doesn't touch the account
+    Local,
+    Read,
+    /// Changes the account; the description says how, e.g. "deletes resources"
+    Change(&'static str),
+}
+
+/// The profile, project or app a command runs against, e.g. ("profile", "prod")
+type Environment = (&'static str, String);
+
+/// Judge a cloud CLI command segment, which may start with environment assignments (`AWS_PROFILE`)
+pub fn judge(segment: &str, current_dir: &Path, config: &Config) -> Judgment {
+    let words = paths::shell_words(segment);
+    let start = words.iter().position(|w| !rules::is_env_assignment(w)).unwrap_or(words.len());
+    let env: Vec<(&str, &str)> = words[..start].iter().filter_map(|w| w.split_once('=')).collect();
+    let lookup = |name: &str| {
+        let value = env.iter().rev().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()).or_else(|| std::env::var(name).ok());
+        value.filter(|v| !v.is_empty())
+    };
+    let Some((program, args)) = words[start..].split_first() else {
+        return Judgment::Unknown;
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let value_flags = match program.as_str() {
+        "aws" => AWS_VALUE_FLAGS,
+        "gcloud" | "az" => GCLOUD_VALUE_FLAGS,
+        _ => &["-e", "--env", "-a", "--app", "-c", "--config", "--command", "--file", "-r", "--region", "--org"],
+    };
+    let parsed = parse(&args, value_flags);
+    if parsed.positionals.is_empty() {
+        return if parsed.has(&["--version", "-v", "--help", "-h"]) { Judgment::Safe } else { Judgment::Unknown };
+    }
+    let classified = match program.as_str() {
+        "aws" => aws(&parsed, current_dir),
+        "gcloud" | "az" => gcloud_or_az(program, &parsed),
+        "wrangler" => wrangler(&parsed, current_dir),
+        _ => fly(program, &parsed),
+    };
+    let (display, access) = match classified {
+        Ok(classified) => classified,
+        Err(judgment) => return judgment,
+    };
+    let environment = match access {
+        Access::Local => return Judgment::Safe,
+        _ => match program.as_str() {
+            "aws" => aws_profile(&parsed, &lookup),
+            "gcloud" => gcloud_project(&parsed, &lookup),
+            "az" => az_subscription(&parsed, &lookup),
+            "wrangler" => parsed.value(&["-e", "--env"]).map(|e| ("environment", e.to_string())),
+            _ => fly_app(&parsed, current_dir),
+        },
+    };
+    let protected = environment.as_ref().filter(|(_, name)| config.cloud.is_protected(name));
+    match (access, protected) {
+        (Access::Change(what), Some((kind, name))) => Judgment::Deny(format!("{} {} in {} `{}`, a protected environment", display, what, kind, name)),
+        (Access::Change(what), None) => match environment {
+            Some((kind, name)) => Judgment::Ask(format!("{} {} in {} `{}`", display, what, kind, name)),
+            None => Judgment::Ask(format!("{} {}", display, what)),
+        },
+        (_, Some((kind, name))) => Judgment::Ask(format!("{} runs against {} `{}`, a protected environment", display, kind, name)),
+        _ => Judgment::Safe,
+    }
+}
+
+/// A cloud CLI command line, with flags separated from positional arguments
+struct Parsed<'a> {
+    flags: Vec<(&'a str, Option<&'a str>)>,
+    positionals: Vec<&'a str>,
+}
+
+impl<'a> Parsed<'a> {
+    fn has(&self, names: &[&str]) -> bool {
+        self.flags.iter().any(|(flag, _)| names.contains(flag))
+    }
+
+    fn value(&self, names: &[&str]) -> Option<&'a str> {
+        self.flags.iter().rev().find(|(flag, _)| names.contains(flag)).and_then(|(_, value)| *value)
+    }
+}
+
+fn parse<'a>(args: &[&'a str], value_flags: &[&str]) -> Parsed<'a> {
+    let mut parsed = Parsed { flags: Vec::new(), positionals: Vec::new() };
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        if !arg.starts_with('-') || arg == "-" {
+            parsed.positionals.push(arg);
+        } else if let Some((flag, value)) = arg.split_once('=').filter(|(flag, _)| flag.starts_with("--")) {
+            parsed.flags.push((flag, Some(value)));
+        } else if value_flags.contains(&arg) {
+            parsed.flags.push((arg, args.get(i).copied()));
+            i += 1;
+        } else {
+            parsed.flags.push((arg, None));
+        }
+    }
+    parsed
+}
+
+type Classified = Result<(String, Access), Judgment>;
+
+fn aws(parsed: &Parsed, current_dir: &Path) -> Classified {
+    let (service, op) = match parsed.positionals.as_slice() {
+        [service, op, ..] => (*service, *op),
+        [service] => return if *service == "help" { Ok((format!("aws {}", service), Access::Local)) } else { Err(Judgment::Unknown) },
+        [] => return Err(Judgment::Unknown),
+    };
+    let display = format!("aws {} {}", service, op);
+    let secret = AWS_SECRET_GETTERS.contains(&(service, op))
+        || (service == "ssm" && op.starts_with("get-parameter") && parsed.has(&["--with-decryption"]))
+        || (service == "configure" && op == "get" && parsed.positionals.get(2).is_some_and(|key| key.contains("secret") || key.contains("token")));
+    if secret {
+        return Err(Judgment::Deny(format!("{} prints secrets or credentials", display)));
+    }
+    if AWS_OUTFILE_WRITERS.contains(&(service, op)) {
+        match parsed.positionals.last().filter(|_| parsed.positionals.len() > 2) {
+            Some(outfile) if super::is_outside_repo(outfile, current_dir) => {
+                return Err(Judgment::Ask(format!("{} writes `{}`, outside the repo", display, outfile)));
+            }
+            Some(_) => {}
+            None => return Err(Judgment::Unknown),
+        }
+    }
+    let access = match (service, op) {
+        ("configure", "list" | "get" | "list-profiles") => Access::Local,
+        ("configure" | "sso", _) => return Err(Judgment::Unknown),
+        ("s3", _) => return s3(parsed, display, op, current_dir),
+        (_, "deploy") | ("deploy", _) => Access::Change("deploys"),
+        _ if ["describe-", "list-", "get-", "batch-get-", "search-", "simulate-"].iter().any(|p| op.starts_with(p)) || AWS_READS.contains(&op) => Access::Read,
+        _ if ["delete-", "terminate-", "deregister-", "purge-", "remove-"].iter().any(|p| op.starts_with(p)) => Access::Change("deletes resources"),
+        _ => Access::Change("changes resources"),
+    };
+    Ok((display, access))
+}
+
+/// `aws s3`: `ls` reads, downloads only write local files (asking outside the repo or
+/// when `sync --delete` removes them), and uploads and deletes change the bucket
+fn s3(parsed: &Parsed, display: String, op: &str, current_dir: &Path) -> Classified {
+    let operands = &parsed.positionals[2..];
+    let to_bucket = operands.last().is_some_and(|dest| dest.starts_with("s3://"));
+    let local_dest = operands.last().filter(|dest| matches!(op, "cp" | "sync" | "mv") && operands.len() > 1 && !to_bucket && **dest != "-");
+    if let Some(dest) = local_dest {
+        if op == "sync" && parsed.has(&["--delete"]) {
+            return Err(Judgment::Ask(format!("{} --delete deletes files in `{}` that aren't in the bucket", display, dest)));
+        }
+        if super::is_outside_repo(dest, current_dir) {
+            return Err(Judgment::Ask(format!("{} writes `{}`, outside the repo", display, dest)));
+        }
+    }
+    let access = match op {
+        "ls" => Access::Read,
+        "rm" if parsed.has(&["--recursive"]) => return Err(Judgment::Deny(format!("{} --recursive deletes every object under the prefix", display))),
+        "rb" if parsed.has(&["--force"]) => return Err(Judgment::Deny(format!("{} --force deletes the bucket and every object in it", display))),
+        "rm" | "rb" => Access::Change("deletes objects"),
+        "presign" => return Err(Judgment::Ask(format!("{} creates a link that shares the object", display))),
+        "sync" if to_bucket && parsed.has(&["--delete"]) => Access::Change("deletes objects"),
+        "cp" | "sync" if !to_bucket => Access::Read,
+        _ => Access::Change("changes objects"),
+    };
+    Ok((display, access))
+}
+
+/// gcloud and az commands are a path of groups, then a verb: `gcloud compute instances list`
+fn gcloud_or_az(program: &str, parsed: &Parsed) -> Classified {
+    let is_verb = |word: &str| {
+        GCLOUD_READS.contains(&word) || GCLOUD_WRITES.contains(&word) || ["list-", "show-", "describe-", "get-", "print-", "access"].iter().any(|p| word.starts_with(p))
+    };
+    let Some(index) = parsed.positionals.iter().position(|w| is_verb(w)) else {
+        return Err(Judgment::Unknown);
+    };
+    let (path, verb) = (&parsed.positionals[..index], parsed.positionals[index]);
+    let display = std::iter::once(program).chain(path.iter().copied()).chain([verb]).collect::<Vec<_>>().join(" ");
+    if GCLOUD_SECRET_GETTERS.iter().any(|(secret_path, secret_verb)| *secret_verb == verb && path.ends_with(secret_path)) {
+        return Err(Judgment::Deny(format!("{} prints secrets or credentials", display)));
+    }
+    let access = match (path.first().copied(), verb) {
+        (Some("config"), "list" | "get-value" | "get" | "describe") => Access::Local,
+        (Some("config" | "account"), "set" | "unset" | "activate" | "create" | "delete" | "update") => {
+            return Err(Judgment::Ask(format!("{} changes the {} configuration", display, program)));
+        }
+        (Some("auth" | "login" | "logout"), _) => return Err(Judgment::Unknown),
+        (_, "get-credentials") => return Err(Judgment::Ask(format!("{} writes cluster credentials to the kubeconfig", display))),
+        (_, "delete" | "purge" | "remove") => Access::Change("deletes resources"),
+        (_, "deploy" | "up") => Access::Change("deploys"),
+        (_, verb) if GCLOUD_WRITES.contains(&verb) => Access::Change("changes resources"),
+        (_, verb) if verb.starts_with("print-") => return Err(Judgment::Unknown),
+        _ => Access::Read,
+    };
+    Ok((display, access))
+}
+
+/// The command path of a wrangler or flyctl command, up to its verb
+fn platform_command<'a>(parsed: &Parsed<'a>) -> (Vec<&'a str>, &'a str) {
+    let is_verb = |w: &str| {
+        PLATFORM_READS.contains(&w) || PLATFORM_WRITES.contains(&w) || PLATFORM_DELETES.contains(&w) || matches!(w, "deploy" | "publish" | "launch")
+    };
+    let words = &parsed.positionals;
+    // Old-style `kv:key put` is `kv key put`
+    let mut path: Vec<&str> = Vec::new();
+    for word in words.iter().take(4) {
+        path.extend(word.split(':'));
+        if is_verb(path.last().copied().unwrap_or_default()) {
+            break;
+        }
+    }
+    let verb = path.pop().unwrap_or_default();
+    (path, verb)
+}
+
+fn wrangler(parsed: &Parsed, current_dir: &Path) -> Classified {
+    let (path, verb) = platform_command(parsed);
+    let display = std::iter::once("wrangler").chain(path.iter().copied()).chain([verb]).collect::<Vec<_>>().join(" ");
+    let remote = parsed.has(&["--remote"]);
+    let access = match (path.as_slice(), verb) {
+        (["d1"], "execute") => return d1_execute(parsed, display, remote, current_dir),
+        (["d1", "migrations"], "apply") if !remote => Access::Local,
+        (["d1", "migrations"], "list" | "create") => Access::Local,
+        (["kv" | "r2", ..], _) if parsed.has(&["--local"]) => Access::Local,
+        ([], "types" | "check" | "docs" | "version" | "whoami") => Access::Local,
+        ([] | ["pages"] | ["versions"], "deploy" | "publish") => Access::Change("deploys"),
+        (_, verb) if PLATFORM_DELETES.contains(&verb) => Access::Change("deletes resources"),
+        (_, verb) if PLATFORM_WRITES.contains(&verb) => Access::Change("changes resources"),
+        (_, verb) if PLATFORM_READS.contains(&verb) => Access::Read,
+        // dev, login, init and the like
+        _ => return Err(Judgment::Unknown),
+    };
+    Ok((display, access))
+}
+
+/// `wrangler d1 execute`: judged by its SQL, which runs against the local database unless `--remote`
+fn d1_execute(parsed: &Parsed, display: String, remote: bool, current_dir: &Path) -> Classified {
+    let script = match (parsed.value(&["--command"]), parsed.value(&["--file"])) {
+        (Some(command), _) => command.to_string(),
+        (None, Some(file)) => std::fs::read_to_string(paths::normalize_path(file, &current_dir.to_string_lossy())).map_err(|_| Judgment::Unknown)?,
+        (None, None) => return Err(Judgment::Unknown),
+    };
+    let effects = sql::classify_script(&script);
+    let destroys = effects.iter().find_map(|e| match e {
+        Effect::Destroy(what) => Some(what.clone()),
+        _ => None,
+    });
+    match destroys {
+        Some(what) if remote => Err(Judgment::Deny(format!("{} runs {}, which destroys data", display, what))),
+        Some(what) => Err(Judgment::Ask(format!("{} runs {} against the local database", display, what))),
+        None if !remote => Ok((display, Access::Local)),
+        None if effects.iter().all(|e| *e == Effect::Read) => Ok((display, Access::Read)),
+        None => Ok((display, Access::Change("changes the database"))),
+    }
+}
+
+fn fly(program: &str, parsed: &Parsed) -> Classified {
+    let (path, verb) = platform_command(parsed);
+    let display = std::iter::once(program).chain(path.iter().copied()).chain([verb]).collect::<Vec<_>>().join(" ");
+    let access = match (path.as_slice(), verb) {
+        (["auth"], "token") => return Err(Judgment::Deny(format!("{} prints secrets or credentials", display))),
+        (["auth"], "whoami") | ([], "version" | "doctor" | "docs") => Access::Local,
+        ([], "deploy" | "launch") => Access::Change("deploys"),
+        (_, verb) if PLATFORM_DELETES.contains(&verb) => Access::Change("deletes resources"),
+        (_, verb) if PLATFORM_WRITES.contains(&verb) => Access::Change("changes resources"),
+        (_, verb) if PLATFORM_READS.contains(&verb) => Access::Read,
+        // ssh, proxy, console, auth login and the like
+        _ => return Err(Judgment::Unknown),
+    };
+    Ok((display, access))
+}
+
+/// `--profile`, else `$AWS_PROFILE`, else `$AWS_DEFAULT_PROFILE`
+fn aws_profile(parsed: &Parsed, lookup: &dyn Fn(&str) -> Option<String>) -> Option<Environment> {
+    let profile = parsed.value(&["--profile"]).map(str::to_string).or_else(|| lookup("AWS_PROFILE")).or_else(|| lookup("AWS_DEFAULT_PROFILE"))?;
+    Some(("profile", profile))
+}
+
+/// `--project`, else `$CLOUDSDK_CORE_PROJECT`, else the active configuration's `project`
+fn gcloud_project(parsed: &Parsed, lookup: &dyn Fn(&str) -> Option<String>) -> Option<Environment> {
+    if let Some(project) = parsed.value(&["--project"]).map(str::to_string).or_else(|| lookup("CLOUDSDK_CORE_PROJECT")) {
+        return Some(("project", project));
+    }
+    let config_dir = lookup("CLOUDSDK_CONFIG").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(paths::home_dir()).join(".config/gcloud"));
+    let name = parsed
+        .value(&["--configuration"])
+        .map(str::to_string)
+        .or_else(|| lookup("CLOUDSDK_ACTIVE_CONFIG_NAME"))
+        .or_else(|| std::fs::read_to_string(config_dir.join("active_config")).ok())
+        .unwrap_or_else(|| "default".to_string());
+    let text = std::fs::read_to_string(config_dir.join("configurations").join(format!("config_{}", name.trim()))).ok()?;
+    let project = text.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "project").then(|| value.trim().to_string())
+    })?;
+    Some(("project", project))
+}
+
+/// `--subscription`, else the default subscription in `azureProfile.json`
+fn az_subscription(parsed: &Parsed, lookup: &dyn Fn(&str) -> Option<String>) -> Option<Environment> {
+    if let Some(subscription) = parsed.value(&["--subscription"]) {
+        return Some(("subscription", subscription.to_string()));
+    }
+    let config_dir = lookup("AZURE_CONFIG_DIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(paths::home_dir()).join(".azure"));
+    let text = std::fs::read_to_string(config_dir.join("azureProfile.json")).ok()?;
+    // az writes the file with a byte order mark
+    let profile: serde_json::Value = serde_json::from_str(text.trim_start_matches('\u{feff}')).ok()?;
+    let default = profile.get("subscriptions")?.as_array()?.iter().find(|s| s.get("isDefault").and_then(|d| d.as_bool()) == Some(true))?;
+    Some(("subscription", default.get("name")?.as_str()?.to_string()))
+}
+
+/// `--app`, else the `app` in `fly.toml` (or `--config`), searching up from the current directory
+fn fly_app(parsed: &Parsed, current_dir: &Path) -> Option<Environment> {
+    if let Some(app) = parsed.value(&["-a", "--app"]) {
+        return Some(("app", app.to_string()));
+    }
+    let config = match parsed.value(&["-c", "--config"]) {
+        Some(file) => paths::normalize_path(file, &current_dir.to_string_lossy()),
+        None => current_dir.ancestors().map(|d| d.join("fly.toml")).find(|p| p.is_file())?,
+    };
+    let text = std::fs::read_to_string(config).ok()?;
+    let app = text.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "app").then(|| paths::strip_quotes(value.trim()).to_string())
+    })?;
+    Some(("app", app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    /// Judge without the profiles and configurations of the machine running the tests
+    fn judge_isolated(command: &str, dir: &Path) -> Judgment {
+        let isolated = format!("AWS_PROFILE= AWS_DEFAULT_PROFILE= CLOUDSDK_CORE_PROJECT= CLOUDSDK_CONFIG=/nonexistent AZURE_CONFIG_DIR=/nonexistent {}", command);
+        judge_in(&isolated, dir)
+    }
+
+    #[test]
+    fn test_aws() {
+        let dir = TempDir::new("cloud_aws");
+        let root = dir.path();
+        for command in [
+            "aws s3 ls s3://assets/", "aws --profile dev ec2 describe-instances --filters Name=tag:env,Values=dev",
+            "aws sts get-caller-identity", "aws logs tail /aws/lambda/api --follow", "aws s3 cp s3://assets/logo.png .",
+            "aws ssm get-parameter --name /app/region", "aws configure list", "aws --version",
+        ] {
+            assert_eq!(judge_isolated(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_isolated("aws secretsmanager get-secret-value --secret-id db", root),
+            Judgment::Deny("aws secretsmanager get-secret-value prints secrets or credentials".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws ssm get-parameters-by-path --path /app --with-decryption", root),
+            Judgment::Deny("aws ssm get-parameters-by-path prints secrets or credentials".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws s3 rm s3://assets/build --recursive", root),
+            Judgment::Deny("aws s3 rm --recursive deletes every object under the prefix".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws s3 rb s3://assets --force", root),
+            Judgment::Deny("aws s3 rb --force deletes the bucket and every object in it".to_string())
+        );
+        assert_eq!(
+            judge_isolated("AWS_PROFILE=staging aws ec2 terminate-instances --instance-ids i-1", root),
+            Judgment::Ask("aws ec2 terminate-instances deletes resources in profile `staging`".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws --profile prod-admin s3 sync dist s3://site --delete", root),
+            Judgment::Deny("aws s3 sync deletes objects in profile `prod-admin`, a protected environment".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws --profile=prod-admin s3 ls", root),
+            Judgment::Ask("aws s3 ls runs against profile `prod-admin`, a protected environment".to_string())
+        );
+        assert_eq!(judge_isolated("aws cloudformation deploy --stack-name api", root), Judgment::Ask("aws cloudformation deploy deploys".to_string()));
+        assert_eq!(
+            judge_isolated("aws ec2 get-password-data --instance-id i-1", root),
+            Judgment::Deny("aws ec2 get-password-data prints secrets or credentials".to_string())
+        );
+    }
+
+    #[test]
+    fn test_aws_local_writes() {
+        let dir = TempDir::new("cloud_aws_local");
+        let root = dir.path();
+        dir.write_file(".git/HEAD", "ref: refs/heads/main\n");
+        for command in [
+            "aws s3 cp s3://assets/logo.png public/logo.png", "aws s3 sync s3://assets/fixtures fixtures --exclude *.tmp",
+            "aws s3 cp s3://assets/report.csv -", "aws s3api get-object --bucket assets --key logo.png logo.png",
+        ] {
+            assert_eq!(judge_isolated(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_isolated("aws s3 cp s3://assets/hosts /etc/hosts", root),
+            Judgment::Ask("aws s3 cp writes `/etc/hosts`, outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws s3 sync s3://assets/dotfiles ~/", root),
+            Judgment::Ask("aws s3 sync writes `~/`, outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws s3 sync s3://assets/src src --delete", root),
+            Judgment::Ask("aws s3 sync --delete deletes files in `src` that aren't in the bucket".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws s3api get-object --bucket assets --key hosts /etc/hosts", root),
+            Judgment::Ask("aws s3api get-object writes `/etc/hosts`, outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_isolated("aws s3 mv s3://assets/logo.png logo.png", root),
+            Judgment::Ask("aws s3 mv changes objects".to_string())
+        );
+    }
+
+    #[test]
+    fn test_gcloud_and_az() {
+        let dir = TempDir::new("cloud_gcloud");
+        let root = dir.path();
+        dir.write_file("gcloud/active_config", "work\n");
+        dir.write_file("gcloud/configurations/config_work", "[core]\naccount = me@example.com\nproject = acme-production\n");
+        let config_dir = format!("CLOUDSDK_CONFIG={}", root.join("gcloud").display());
+        for command in ["gcloud projects list", "gcloud compute instances describe web --zone us-east1-b", "gcloud config list", "az group list -o table", "az vm show -g app -n web"] {
+            assert_eq!(judge_isolated(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_isolated(&format!("{} gcloud compute instances list", config_dir), root),
+            Judgment::Ask("gcloud compute instances list runs against project `acme-production`, a protected environment".to_string())
+        );
+        assert_eq!(
+            judge_isolated(&format!("{} gcloud --project acme-dev sql instances delete db", config_dir), root),
+            Judgment::Ask("gcloud sql instances delete deletes resources in project `acme-dev`".to_string())
+        );
+        assert_eq!(
+            judge_isolated("gcloud secrets versions access latest --secret api-key", root),
+            Judgment::Deny("gcloud secrets versions access prints secrets or credentials".to_string())
+        );
+        assert_eq!(judge_isolated("gcloud auth print-access-token", root), Judgment::Deny("gcloud auth print-access-token prints secrets or credentials".to_string()));
+        assert_eq!(
+            judge_isolated("gcloud config set project acme-dev", root),
+            Judgment::Ask("gcloud config set changes the gcloud configuration".to_string())
+        );
+        assert_eq!(
+            judge_isolated("az keyvault secret show --vault-name kv --name db", root),
+            Judgment::Deny("az keyvault secret show prints secrets or credentials".to_string())
+        );
+        assert_eq!(
+            judge_isolated("az group delete -n rg-prod --subscription Production", root),
+            Judgment::Deny("az group delete deletes resources in subscription `Production`, a protected environment".to_string())
+        );
+    }
+
+    #[test]
+    fn test_wrangler_and_fly() {
+        let dir = TempDir::new("cloud_platforms");
+        let root = dir.path();
+        dir.write_file("fly.toml", "app = \"api-staging\"\nprimary_region = \"iad\"\n");
+        for command in [
+            "wrangler tail", "wrangler kv key list --namespace-id abc", "wrangler d1 execute DB --command 'select 1'",
+            "wrangler d1 migrations apply DB", "fly status", "flyctl logs", "fly secrets list",
+        ] {
+            assert_eq!(judge_isolated(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_isolated("wrangler d1 execute DB --remote --command 'drop table users'", root),
+            Judgment::Deny("wrangler d1 execute runs DROP TABLE, which destroys data".to_string())
+        );
+        assert_eq!(
+            judge_isolated("wrangler kv:key delete --binding CACHE session --env production", root),
+            Judgment::Deny("wrangler kv key delete deletes resources in environment `production`, a protected environment".to_string())
+        );
+        assert_eq!(
+            judge_isolated("wrangler secret put API_KEY", root),
+            Judgment::Ask("wrangler secret put changes resources".to_string())
+        );
+        assert_eq!(
+            judge_isolated("fly machines destroy 1234 --force", root),
+            Judgment::Ask("fly machines destroy deletes resources in app `api-staging`".to_string())
+        );
+        assert_eq!(judge_isolated("fly -a api-prod status", root), Judgment::Ask("fly status runs against app `api-prod`, a protected environment".to_string()));
+        assert_eq!(judge_isolated("fly auth token", root), Judgment::Deny("fly auth token prints secrets or credentials".to_string()));
+        assert_eq!(judge_isolated("fly ssh console", root), Judgment::Unknown);
+    }
+}
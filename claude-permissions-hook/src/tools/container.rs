@@ -0,0 +1,475 @@
+//! Container tooling: docker, podman and nerdctl, and Compose (`docker compose`,
+//! `docker-compose`, `podman compose`). Read verbs are allowed and builds are treated
+//! like any build; `run` and `exec` are judged by the command they run in the container.
+//! Settings that let a container reach the host (`--privileged`, host namespaces,
+//! mounting `/`, `~/.ssh` or secrets) are denied, on the command line or in a compose
+//! service. Deleting containers, images and volumes asks.
+
+use std::path::{Path, PathBuf};
+
+use super::Judgment;
+use crate::paths;
+use crate::yaml::{self, Yaml};
+
+/// Global flags (before the subcommand) that take a value
+const GLOBAL_VALUE_FLAGS: &[&str] = &[
+    "--context", "-c", "-H", "--host", "--config", "-l", "--log-level", "--tlscacert", "--tlscert",
+    "--tlskey", "--connection", "--url", "--root", "--runroot", "--storage-driver", "--namespace",
+    "-n", "--address", "-a",
+];
+
+/// Subcommands (after normalizing `container ls` and the like) that only read
+const READ_VERBS: &[&str] = &[
+    "ps", "images", "logs", "inspect", "version", "info", "top", "port", "diff", "history",
+    "events", "search", "stats", "df",
+];
+
+/// Subcommands that build or fetch images, or start and stop existing containers
+const BUILD_VERBS: &[&str] = &["build", "pull", "tag", "start", "stop", "restart", "pause", "unpause", "wait"];
+
+/// Subcommands that delete containers, images, volumes or networks
+const DELETE_VERBS: &[&str] = &["rm", "rmi", "prune"];
+
+/// Compose subcommands that only read
+const COMPOSE_READ: &[&str] = &["config", "ps", "logs", "images", "ls", "top", "version", "port", "events", "convert", "stats"];
+
+/// Compose subcommands that build, fetch, or start and stop the project's services
+const COMPOSE_BUILD: &[&str] = &["build", "pull", "create", "start", "stop", "restart", "pause", "unpause", "wait"];
+
+/// `docker run`/`create` and `compose run` flags that take a value
+const RUN_VALUE_FLAGS: &[&str] = &[
+    "-e", "--env", "--env-file", "-v", "--volume", "--mount", "-p", "--publish", "--name", "-w",
+    "--workdir", "-u", "--user", "--entrypoint", "--network", "--net", "-l", "--label",
+    "--label-file", "-h", "--hostname", "--add-host", "--cpus", "-m", "--memory", "--restart",
+    "--platform", "--pull", "--log-driver", "--log-opt", "--cap-add", "--cap-drop", "--security-opt",
+    "--device", "--dns", "--ipc", "--pid", "--uts", "--userns", "--cgroupns", "--tmpfs", "--ulimit",
+    "--gpus", "--shm-size", "--stop-signal", "--stop-timeout", "--runtime", "--cidfile",
+    "--volumes-from", "--link", "--expose", "-a", "--attach", "--memory-swap", "--cpu-shares", "-c",
+    "--cpuset-cpus", "--group-add", "--ip", "--network-alias", "--pids-limit", "--sysctl",
+    "--health-cmd", "--detach-keys", "--index",
+];
+
+/// Host paths (under the home directory when relative) that a container must not mount
+const SENSITIVE_HOST_PATHS: &[&str] = &[
+    "/etc", "/root", "/boot", "/proc", "/sys", "/dev", "/var/run/docker.sock", "/run/docker.sock",
+    ".ssh", ".aws", ".gnupg", ".kube", ".docker", ".config/gcloud", ".azure", ".netrc",
+];
+
+/// Capabilities that amount to root on the host
+const HOST_CAPABILITIES: &[&str] = &["ALL", "SYS_ADMIN", "SYS_PTRACE", "SYS_MODULE", "DAC_READ_SEARCH", "NET_ADMIN"];
+
+/// Default Compose files, in the order Compose looks for them
+const COMPOSE_FILES: &[&str] = &["compose.yaml", "compose.yml", "docker-compose.yaml", "docker-compose.yml"];
+
+/// Override files Compose merges in when no `-f` is given
+const COMPOSE_OVERRIDES: &[&str] = &["compose.override.yaml", "compose.override.yml", "docker-compose.override.yaml", "docker-compose.override.yml"];
+
+/// Check if a program is a container CLI or standalone Compose
+pub fn is_container_tool(program: &str) -> bool {
+    matches!(program, "docker" | "podman" | "nerdctl" | "docker-compose" | "podman-compose")
+}
+
+/// Judge a container CLI command
+pub fn judge(program: &str, args: &[&str], current_dir: &Path) -> Judgment {
+    if program.ends_with("-compose") {
+        return judge_compose(program, args, current_dir);
+    }
+    let start = skip_global_flags(args);
+    let Some((&sub, rest)) = args[start..].split_first() else {
+        return Judgment::Unknown;
+    };
+    if matches!(sub, "-v" | "--version" | "-h" | "--help") {
+        return Judgment::Safe;
+    }
+    let mut display = format!("{} {}", program, sub);
+    // Management commands: `container ls` is `ps`, `image rm` is `rmi`, `system df` is `df`
+    let (verb, rest) = match (sub, rest.split_first()) {
+        ("compose", _) => return judge_compose(&format!("{} compose", program), rest, current_dir),
+        ("container" | "image" | "system" | "builder" | "buildx" | "volume" | "network" | "context" | "manifest", Some((&verb, rest))) => {
+            display = format!("{} {} {}", program, sub, verb);
+            match (sub, verb) {
+                ("container", "ls" | "list") => ("ps", rest),
+                ("image", "ls" | "list") => ("images", rest),
+                ("image", "rm" | "remove") => ("rmi", rest),
+                ("buildx", "build" | "b") => ("build", rest),
+                ("buildx", "bake") => ("build", rest),
+                ("buildx", "du") => ("df", rest),
+                ("volume" | "network" | "context" | "manifest" | "buildx", "ls" | "list" | "inspect" | "show") => ("inspect", rest),
+                ("volume" | "network" | "context" | "buildx", "rm" | "remove") => ("rm", rest),
+                ("container" | "image" | "system" | "builder", _) => (verb, rest),
+                _ => return Judgment::Unknown,
+            }
+        }
+        _ => (sub, rest),
+    };
+    match verb {
+        _ if READ_VERBS.contains(&verb) => Judgment::Safe,
+        _ if BUILD_VERBS.contains(&verb) => Judgment::Safe,
+        _ if DELETE_VERBS.contains(&verb) => Judgment::Ask(format!("{} deletes containers, images or volumes", display)),
+        "push" => Judgment::Ask(format!("{} uploads an image to a registry", display)),
+        "run" | "create" | "exec" => judge_payload(&display, verb, rest, current_dir),
+        _ => Judgment::Unknown,
+    }
+}
+
+/// The index of the subcommand, after global flags
+fn skip_global_flags(args: &[&str]) -> usize {
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        if GLOBAL_VALUE_FLAGS.contains(arg) {
+            i += 2;
+        } else if arg.starts_with('-') && !matches!(*arg, "-v" | "--version" | "-h" | "--help") {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i.min(args.len())
+}
+
+/// `run`/`create`/`exec`: deny host-escaping settings, then judge the payload command
+fn judge_payload(display: &str, verb: &str, args: &[&str], current_dir: &Path) -> Judgment {
+    let (settings, positionals) = run_settings(args);
+    if let Some(issue) = host_escape(&settings, current_dir, true) {
+        return Judgment::Deny(format!("{}: {}", display, issue));
+    }
+    // `exec CONTAINER CMD...`, `run IMAGE [CMD...]`, `compose run SERVICE [CMD...]`
+    let Some((target, command)) = positionals.split_first() else {
+        return Judgment::Unknown;
+    };
+    let entrypoint = settings.iter().find(|s| s.key == "entrypoint").map(|s| s.value.clone());
+    let command: Vec<String> = entrypoint.into_iter().chain(command.iter().map(|c| c.to_string())).collect();
+    if command.is_empty() {
+        // The image's own entrypoint, or `create`, which runs nothing yet
+        return if verb == "create" { Judgment::Safe } else { Judgment::Unknown };
+    }
+    Judgment::Remote { label: format!("{} `{}`", display, target), command: command.join(" "), ask: None }
+}
+
+/// A container setting that can reach the host, as written on the command line or in a
+/// compose file
+struct Setting {
+    /// How it was written, for reasons (`--pid=host`, `pid: host`)
+    display: String,
+    key: &'static str,
+    value: String,
+}
+
+/// Pull the host-relevant settings and the positional arguments out of `run`/`exec` flags
+fn run_settings<'a>(args: &[&'a str]) -> (Vec<Setting>, Vec<&'a str>) {
+    let mut settings = Vec::new();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        if !arg.starts_with('-') {
+            return (settings, args[i - 1..].to_vec());
+        }
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ if RUN_VALUE_FLAGS.contains(&arg) => {
+                i += 1;
+                (arg, args.get(i - 1).map(|v| v.to_string()))
+            }
+            _ => (arg, None),
+        };
+        let key = match flag {
+            "--privileged" => "privileged",
+            "-v" | "--volume" => "volume",
+            "--mount" => "mount",
+            "--env-file" => "env-file",
+            "--pid" | "--ipc" | "--uts" | "--userns" | "--cgroupns" => "namespace",
+            "--cap-add" => "cap-add",
+            "--security-opt" => "security-opt",
+            "--entrypoint" => "entrypoint",
+            _ => continue,
+        };
+        let value = value.unwrap_or_default();
+        let display = if value.is_empty() { flag.to_string() } else { format!("{} {}", flag, value) };
+        settings.push(Setting { display, key, value });
+    }
+    (settings, Vec::new())
+}
+
+/// Describe the first setting that lets the container reach the host. Relative mounts
+/// resolve against `base`; `secrets` also flags mounting or passing secrets files.
+fn host_escape(settings: &[Setting], base: &Path, secrets: bool) -> Option<String> {
+    settings.iter().find_map(|setting| {
+        let value = setting.value.as_str();
+        let issue = match setting.key {
+            "privileged" if !matches!(value, "false") => "gives the container full access to the host",
+            "namespace" if value == "host" => "shares the host's namespace with the container",
+            "cap-add" if HOST_CAPABILITIES.contains(&value.trim_start_matches("CAP_").to_uppercase().as_str()) => {
+                "gives the container root-level access to the host"
+            }
+            "security-opt" if value.ends_with("unconfined") || value == "no-new-privileges=false" => "turns off the container's sandbox",
+            "volume" | "mount" => {
+                let source = mount_source(setting.key, value)?;
+                let path = resolve_host_path(&source, base)?;
+                if is_sensitive_host_path(&path) || (secrets && paths::is_secrets_file(&path.to_string_lossy())) {
+                    return Some(format!("mounting `{}` exposes the host's files to the container", source));
+                }
+                return None;
+            }
+            "env-file" if secrets && paths::is_secrets_file(value) => "passes secrets into the container",
+            _ => return None,
+        };
+        Some(format!("`{}` {}", setting.display, issue))
+    })
+}
+
+/// The host side of `-v src:dst[:opts]` or `--mount type=bind,source=src,...`
+fn mount_source(key: &str, value: &str) -> Option<String> {
+    if key == "mount" {
+        return value.split(',').find_map(|kv| kv.strip_prefix("source=").or_else(|| kv.strip_prefix("src="))).map(str::to_string);
+    }
+    let (source, _) = value.split_once(':')?;
+    Some(source.to_string())
+}
+
+/// Resolve a bind-mount source on the host; `None` for named volumes
+fn resolve_host_path(source: &str, base: &Path) -> Option<PathBuf> {
+    let cwd = base.to_string_lossy();
+    let home = paths::home_dir();
+    let expanded = source
+        .replace("${HOME}", &home)
+        .replace("$HOME", &home)
+        .replace("${PWD}", &cwd)
+        .replace("$PWD", &cwd)
+        .replace("$(pwd)", &cwd);
+    let expanded = paths::expand_tilde(&expanded);
+    if !expanded.starts_with('/') && !expanded.starts_with('.') {
+        return None;
+    }
+    Some(paths::normalize_path(&expanded, &cwd))
+}
+
+fn is_sensitive_host_path(path: &Path) -> bool {
+    let home = PathBuf::from(paths::home_dir());
+    path == Path::new("/")
+        || path == home
+        || SENSITIVE_HOST_PATHS.iter().any(|p| match p.strip_prefix('/') {
+            Some(_) => path.starts_with(p),
+            None => path.starts_with(home.join(p)),
+        })
+}
+
+/// Compose global flags that take a value
+const COMPOSE_VALUE_FLAGS: &[&str] = &["-f", "--file", "-p", "--project-name", "--profile", "--env-file", "--project-directory", "--ansi", "--progress", "--parallel"];
+
+fn judge_compose(program: &str, args: &[&str], current_dir: &Path) -> Judgment {
+    let mut files = Vec::new();
+    let mut project_dir = current_dir.to_path_buf();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ if COMPOSE_VALUE_FLAGS.contains(&arg) => {
+                i += 1;
+                (arg, args.get(i).copied())
+            }
+            _ if arg.starts_with('-') => (arg, None),
+            _ => break,
+        };
+        i += 1;
+        match (flag, value) {
+            ("-f" | "--file", Some(file)) => files.push(paths::normalize_path(&paths::expand_tilde(file), &current_dir.to_string_lossy())),
+            ("--project-directory", Some(dir)) => project_dir = paths::normalize_path(&paths::expand_tilde(dir), &current_dir.to_string_lossy()),
+            ("--env-file", Some(file)) if paths::is_secrets_file(file) => {
+                // Interpolated into the config, which `compose config` prints
+                return Judgment::Deny(format!("{} --env-file `{}` reads secrets into the compose config", program, file));
+            }
+            ("-v" | "--version" | "-h" | "--help", _) => return Judgment::Safe,
+            _ => {}
+        }
+    }
+    let Some((&sub, rest)) = args.get(i..).and_then(|a| a.split_first()) else {
+        return Judgment::Unknown;
+    };
+    let display = format!("{} {}", program, sub);
+    match sub {
+        _ if COMPOSE_READ.contains(&sub) => Judgment::Safe,
+        "up" | "create" => check_compose_services(&display, &files, &project_dir),
+        _ if COMPOSE_BUILD.contains(&sub) => Judgment::Safe,
+        "down" if rest.iter().any(|a| matches!(*a, "-v" | "--volumes" | "--rmi")) || rest.iter().any(|a| a.starts_with("--rmi=")) => {
+            Judgment::Ask(format!("{} {} deletes the project's volumes or images", program, sub))
+        }
+        "down" => Judgment::Safe,
+        "rm" => Judgment::Ask(format!("{} deletes the project's stopped containers", display)),
+        "push" => Judgment::Ask(format!("{} uploads the project's images to a registry", display)),
+        "run" => match check_compose_services(&display, &files, &project_dir) {
+            Judgment::Safe => judge_payload(&display, sub, rest, current_dir),
+            judgment => judgment,
+        },
+        "exec" => judge_payload(&display, sub, rest, current_dir),
+        _ => Judgment::Unknown,
+    }
+}
+
+/// The compose files to check: `-f` files, or the default file (searching up from
+/// `project_dir`) and its override; `None` if there is none
+fn compose_files(files: &[PathBuf], project_dir: &Path) -> Option<Vec<PathBuf>> {
+    if !files.is_empty() {
+        return Some(files.to_vec());
+    }
+    project_dir.ancestors().find_map(|dir| {
+        let main = COMPOSE_FILES.iter().map(|f| dir.join(f)).find(|p| p.is_file())?;
+        Some(std::iter::once(main).chain(COMPOSE_OVERRIDES.iter().map(|f| dir.join(f)).filter(|p| p.is_file())).collect())
+    })
+}
+
+/// Check every service in the project's compose files for settings that reach the
+/// host. Unlike `docker run`, a service's own `.env` mounts and `env_file` are allowed:
+/// they're the project's config, not something a command line slipped in. `Unknown` if
+/// there's no compose file or it can't be parsed.
+fn check_compose_services(display: &str, files: &[PathBuf], project_dir: &Path) -> Judgment {
+    let Some(files) = compose_files(files, project_dir) else {
+        return Judgment::Unknown;
+    };
+    for file in &files {
+        let Some(doc) = std::fs::read_to_string(file).ok().and_then(|text| yaml::parse(&text)) else {
+            return Judgment::Unknown;
+        };
+        let base = file.parent().unwrap_or(Path::new("/"));
+        for (name, service) in doc.get("services").and_then(Yaml::as_map).unwrap_or_default() {
+            if let Some(issue) = host_escape(&service_settings(service), base, false) {
+                return Judgment::Deny(format!("{}: service `{}`: {}", display, name, issue));
+            }
+        }
+    }
+    Judgment::Safe
+}
+
+/// The host-relevant settings of a compose service
+fn service_settings(service: &Yaml) -> Vec<Setting> {
+    let mut settings = Vec::new();
+    let mut push = |key: &'static str, display: String, value: &str| settings.push(Setting { display, key, value: value.to_string() });
+    if let Some(value) = service.get("privileged").and_then(Yaml::as_str) {
+        push("privileged", format!("privileged: {}", value), value);
+    }
+    for field in ["pid", "ipc", "uts", "userns_mode", "cgroup"] {
+        if let Some(value) = service.get(field).and_then(Yaml::as_str) {
+            push("namespace", format!("{}: {}", field, value), value);
+        }
+    }
+    for cap in service.get("cap_add").and_then(Yaml::as_list).unwrap_or_default() {
+        if let Some(value) = cap.as_str() {
+            push("cap-add", format!("cap_add: {}", value), value);
+        }
+    }
+    for opt in service.get("security_opt").and_then(Yaml::as_list).unwrap_or_default() {
+        if let Some(value) = opt.as_str() {
+            push("security-opt", format!("security_opt: {}", value), &value.replace(':', "="));
+        }
+    }
+    for volume in service.get("volumes").and_then(Yaml::as_list).unwrap_or_default() {
+        match volume {
+            Yaml::Scalar(short) => push("volume", format!("volumes: {}", short), short),
+            // Long syntax: `{type: bind, source: ./data, target: /data}`
+            long => {
+                if let Some(source) = long.get("source").and_then(Yaml::as_str) {
+                    push("mount", format!("volumes: source {}", source), &format!("source={}", source));
+                }
+            }
+        }
+    }
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    #[test]
+    fn test_docker_verbs() {
+        let dir = TempDir::new("container_verbs");
+        for command in [
+            "docker ps -a", "docker --context dev images", "podman logs -f web", "docker container ls",
+            "docker image inspect alpine", "docker system df", "docker build -t app .", "docker buildx build .",
+            "nerdctl pull alpine:3", "docker volume ls", "docker --version",
+        ] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in("docker system prune -af", dir.path()),
+            Judgment::Ask("docker system prune deletes containers, images or volumes".to_string())
+        );
+        assert_eq!(judge_in("docker image rm app", dir.path()), Judgment::Ask("docker image rm deletes containers, images or volumes".to_string()));
+        assert_eq!(judge_in("podman push app", dir.path()), Judgment::Ask("podman push uploads an image to a registry".to_string()));
+        assert_eq!(judge_in("docker login", dir.path()), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_run_and_exec() {
+        let dir = TempDir::new("container_run");
+        let root = dir.path();
+        assert_eq!(
+            judge_in("docker exec -it -e DEBUG=1 web ls -la /app", root),
+            Judgment::Remote { label: "docker exec `web`".to_string(), command: "ls -la /app".to_string(), ask: None }
+        );
+        assert_eq!(
+            judge_in("docker run --rm -v ./src:/src --entrypoint cat alpine /src/main.rs", root),
+            Judgment::Remote { label: "docker run `alpine`".to_string(), command: "cat /src/main.rs".to_string(), ask: None }
+        );
+        assert_eq!(judge_in("docker run -d -p 5432:5432 postgres:16", root), Judgment::Unknown);
+        assert_eq!(
+            judge_in("docker run --rm --privileged alpine sh", root),
+            Judgment::Deny("docker run: `--privileged` gives the container full access to the host".to_string())
+        );
+        assert_eq!(
+            judge_in("docker run --pid=host alpine ps", root),
+            Judgment::Deny("docker run: `--pid host` shares the host's namespace with the container".to_string())
+        );
+        assert_eq!(
+            judge_in("docker run -v /:/host alpine ls /host", root),
+            Judgment::Deny("docker run: mounting `/` exposes the host's files to the container".to_string())
+        );
+        assert_eq!(
+            judge_in("podman run --mount type=bind,source=~/.ssh,target=/root/.ssh alpine", root),
+            Judgment::Deny("podman run: mounting `~/.ssh` exposes the host's files to the container".to_string())
+        );
+        assert_eq!(
+            judge_in("docker container run --env-file .env.production app", root),
+            Judgment::Deny("docker container run: `--env-file .env.production` passes secrets into the container".to_string())
+        );
+        assert_eq!(judge_in("docker run -v data:/var/lib/postgresql/data postgres", root), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_compose() {
+        let dir = TempDir::new("container_compose");
+        let root = dir.path();
+        assert_eq!(judge_in("docker compose up -d", root), Judgment::Unknown);
+        dir.write_file(
+            "compose.yaml",
+            "services:\n  db:\n    image: postgres\n    env_file: .env\n    volumes:\n      - pgdata:/var/lib/postgresql/data\n      - ./init.sql:/docker-entrypoint-initdb.d/init.sql\n  app:\n    build: .\n    volumes:\n      - type: bind\n        source: ./src\n        target: /app/src\nvolumes:\n  pgdata:\n",
+        );
+        for command in ["docker compose config", "docker compose up -d --build", "docker-compose build", "podman compose ps", "docker compose down"] {
+            assert_eq!(judge_in(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in("docker compose exec db psql -U postgres", root),
+            Judgment::Remote { label: "docker compose exec `db`".to_string(), command: "psql -U postgres".to_string(), ask: None }
+        );
+        assert_eq!(
+            judge_in("docker compose down -v", root),
+            Judgment::Ask("docker compose down deletes the project's volumes or images".to_string())
+        );
+        assert_eq!(
+            judge_in("docker compose --env-file .env config", root),
+            Judgment::Deny("docker compose --env-file `.env` reads secrets into the compose config".to_string())
+        );
+
+        dir.write_file("compose.override.yml", "services:\n  app:\n    volumes:\n      - /var/run/docker.sock:/var/run/docker.sock\n");
+        assert_eq!(
+            judge_in("docker compose up", root),
+            Judgment::Deny("docker compose up: service `app`: mounting `/var/run/docker.sock` exposes the host's files to the container".to_string())
+        );
+        dir.write_file("ci.yml", "services:\n  agent:\n    image: alpine\n    privileged: true\n");
+        assert_eq!(
+            judge_in("docker-compose -f ci.yml up", root),
+            Judgment::Deny("docker-compose up: service `agent`: `privileged: true` gives the container full access to the host".to_string())
+        );
+    }
+}
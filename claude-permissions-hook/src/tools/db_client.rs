@@ -0,0 +1,507 @@
+//! Database clients (psql, mysql/mariadb, sqlite3, duckdb): the SQL they run, from
+//! `-c`/`-e`, statement arguments, heredocs, `<` redirects and `-f` files, is classified
+//! statement by statement. Reads against a local target are allowed, writes ask, and
+//! `DROP`, `TRUNCATE` and `DELETE` without `WHERE` are denied. Local files sqlite3 and
+//! duckdb read from SQL (`read_csv('...')`, `from 'data.json'`, `readfile('...')`) ask
+//! outside the repo, and secrets files among them are denied.
+
+use std::path::Path;
+
+use super::{strictest, Judgment};
+use crate::config::Config;
+use crate::paths;
+use crate::rules::{self, database};
+use crate::sql::{self, Effect};
+
+/// psql flags that take a value
+const PSQL_VALUE_FLAGS: &[&str] = &[
+    "-c", "--command", "-f", "--file", "-d", "--dbname", "-h", "--host", "-p", "--port", "-U",
+    "--username", "-v", "--set", "--variable", "-o", "--output", "-L", "--log-file", "-P", "--pset",
+    "-F", "--field-separator", "-R", "--record-separator", "-T", "--table-attr",
+];
+
+/// mysql/mariadb flags that take a value (`-p` only takes an attached password)
+const MYSQL_VALUE_FLAGS: &[&str] = &[
+    "-e", "--execute", "-h", "--host", "-P", "--port", "-u", "--user", "-D", "--database", "-S",
+    "--socket", "--defaults-file", "--defaults-extra-file", "--login-path", "--default-character-set",
+    "--protocol", "--tee", "--init-command",
+];
+
+/// sqlite3 and duckdb flags that take a value
+const SQLITE_VALUE_FLAGS: &[&str] = &[
+    "-c", "-s", "-cmd", "-init", "-separator", "-newline", "-nullvalue", "-vfs", "-maxsize",
+    "-mmap", "-pagecache", "-lookaside", "-heap", "-zip",
+];
+
+/// psql meta-commands that only describe the database or set display options
+const SAFE_PSQL_META: &[&str] = &[
+    "l", "list", "x", "timing", "conninfo", "echo", "qecho", "pset", "a", "t", "q", "quit",
+    "encoding", "?", "h", "help", "set", "unset", "f", "H", "T", "C", "z", "sf", "sv",
+    "errverbose", "g", "gx", "gdesc", "p", "print", "r", "reset", "if", "elif", "else", "endif",
+];
+
+/// sqlite3/duckdb dot-commands that only describe the database or set display options
+const SAFE_DOT_COMMANDS: &[&str] = &[
+    "tables", "schema", "indexes", "indices", "headers", "header", "mode", "width", "dump",
+    "databases", "show", "help", "quit", "exit", "timer", "changes", "explain", "eqp",
+    "fullschema", "dbinfo", "stats", "print", "nullvalue", "separator", "bail", "echo", "lint",
+    "columns", "rows", "maxrows", "maxwidth", "highlight", "prompt",
+];
+
+/// Meta-commands that read or write files on this machine
+const FILE_META: &[&str] = &[
+    "copy", "o", "out", "w", "write", "lo_export", "lo_import", "output", "once", "import",
+    "backup", "save", "restore", "load", "excel", "clone", "archive", "ar", "log", "cd", "tee",
+];
+
+/// Check if a program is a database client this model understands
+pub fn is_db_client(program: &str) -> bool {
+    matches!(program, "psql" | "mysql" | "mariadb" | "sqlite3" | "duckdb")
+}
+
+/// Where a client connects
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Local,
+    Remote(String),
+}
+
+/// What one invocation runs: SQL texts in order, or `None` for an interactive session
+struct Invocation {
+    scripts: Option<Vec<String>>,
+    target: Target,
+    readonly: bool,
+}
+
+/// Judge a database client command segment
+pub fn judge(segment: &str, current_dir: &Path, config: &Config) -> Judgment {
+    let words = paths::shell_words(segment);
+    let start = words.iter().position(|w| !rules::is_env_assignment(w)).unwrap_or(words.len());
+    let env: Vec<(&str, &str)> = words[..start].iter().filter_map(|w| w.split_once('=')).collect();
+    let lookup = |name: &str| env.iter().rev().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()).or_else(|| std::env::var(name).ok());
+    let Some((program, args)) = words[start..].split_first() else {
+        return Judgment::Unknown;
+    };
+    if args.iter().any(|a| matches!(a.as_str(), "--version" | "-V" | "-version" | "--help" | "-help" | "-?")) {
+        return Judgment::Safe;
+    }
+
+    let (args, inputs) = redirects(args);
+    let policy = &config.database;
+    let invocation = match program.as_str() {
+        "psql" => psql(&args, inputs, current_dir, policy, &lookup),
+        "mysql" | "mariadb" => mysql(&args, inputs, current_dir, policy, &lookup),
+        _ => sqlite(&args, inputs, current_dir),
+    };
+    let Some(invocation) = invocation else {
+        return Judgment::Unknown;
+    };
+    let Some(scripts) = invocation.scripts else {
+        // An interactive session; the agent can't answer its prompts
+        return Judgment::Unknown;
+    };
+
+    let mut judgments = Vec::new();
+    let mut writes: Vec<String> = Vec::new();
+    for script in &scripts {
+        let (sql, metas) = split_meta(program, script);
+        judgments.extend(metas.iter().map(|meta| judge_meta(program, meta)));
+        if matches!(program.as_str(), "sqlite3" | "duckdb") {
+            judgments.extend(sql::files_read(&sql).iter().filter_map(|file| judge_file_read(program, file, current_dir)));
+        }
+        for effect in sql::classify_script(&sql) {
+            match effect {
+                _ if invocation.readonly => {}
+                Effect::Read => {}
+                Effect::Write(what) if !writes.contains(&what) => writes.push(what),
+                Effect::Write(_) => {}
+                Effect::Destroy(what) => judgments.push(Judgment::Deny(format!("{} runs {}, which destroys data", program, what))),
+            }
+        }
+    }
+    if !writes.is_empty() {
+        judgments.push(Judgment::Ask(format!("{} runs statements that change the database ({})", program, writes.join(", "))));
+    }
+    if let Target::Remote(target) = &invocation.target {
+        judgments.push(Judgment::Ask(format!("{} runs queries against `{}`, which isn't a local database", program, target)));
+    }
+    strictest(judgments)
+}
+
+/// Pull out `< file` and `<<< text` (heredocs arrive as here-strings) inputs, reading
+/// files; drop output redirects
+fn redirects(args: &[String]) -> (Vec<&str>, Vec<Input>) {
+    let mut rest = Vec::new();
+    let mut inputs = Vec::new();
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        i += 1;
+        let next = args.get(i).map(String::as_str);
+        match arg.as_str() {
+            "<<<" => {
+                inputs.extend(next.map(|text| Input::Text(text.to_string())));
+                i += 1;
+            }
+            "<" => {
+                inputs.extend(next.map(|file| Input::File(file.to_string())));
+                i += 1;
+            }
+            ">" | ">>" | "2>" | "2>>" | "&>" => i += 1,
+            _ if arg.starts_with("<<<") => inputs.push(Input::Text(arg[3..].to_string())),
+            _ if arg.starts_with('<') => inputs.push(Input::File(arg[1..].to_string())),
+            _ if arg.starts_with('>') || arg.starts_with("2>") || arg.starts_with("&>") => {}
+            _ => rest.push(arg.as_str()),
+        }
+    }
+    (rest, inputs)
+}
+
+/// Client input from stdin
+enum Input {
+    Text(String),
+    File(String),
+}
+
+/// Read a `-f` or `<` script file; `None` if it can't be read
+fn read_script(file: &str, current_dir: &Path) -> Option<String> {
+    let path = paths::normalize_path(&paths::expand_tilde(file), &current_dir.to_string_lossy());
+    std::fs::read_to_string(path).ok()
+}
+
+fn stdin_scripts(inputs: Vec<Input>, current_dir: &Path) -> Option<Vec<String>> {
+    inputs
+        .into_iter()
+        .map(|input| match input {
+            Input::Text(text) => Some(text),
+            Input::File(file) => read_script(&file, current_dir),
+        })
+        .collect()
+}
+
+/// Split the client's arguments into `(flag, value)` options and operands. `value_flags`
+/// take a value attached (`-hdb`, `--host=db`) or as the next argument.
+fn parse_args<'a>(args: &[&'a str], value_flags: &[&str]) -> (Vec<(&'a str, Option<&'a str>)>, Vec<&'a str>) {
+    let mut options = Vec::new();
+    let mut operands = Vec::new();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        if arg == "--" {
+            operands.extend(&args[i..]);
+            break;
+        }
+        if value_flags.contains(&arg) {
+            options.push((arg, args.get(i).copied()));
+            i += 1;
+        } else if let Some((flag, value)) = arg.split_once('=').filter(|(flag, _)| flag.starts_with("--")) {
+            options.push((flag, Some(value)));
+        } else if arg.len() > 2 && !arg.starts_with("--") && value_flags.contains(&&arg[..2]) {
+            options.push((&arg[..2], Some(&arg[2..])));
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            options.push((arg, None));
+        } else {
+            operands.push(arg);
+        }
+    }
+    (options, operands)
+}
+
+fn psql(args: &[&str], inputs: Vec<Input>, current_dir: &Path, policy: &database::DatabasePolicy, lookup: &dyn Fn(&str) -> Option<String>) -> Option<Invocation> {
+    let (options, operands) = parse_args(args, PSQL_VALUE_FLAGS);
+    let mut scripts = Vec::new();
+    let mut reads_stdin = false;
+    let mut host = None;
+    let mut dbname = operands.first().copied();
+    for (flag, value) in &options {
+        match (*flag, *value) {
+            ("-c" | "--command", Some(sql)) => scripts.push(sql.to_string()),
+            ("-f" | "--file", Some("-")) => reads_stdin = true,
+            ("-f" | "--file", Some(file)) => scripts.push(read_script(file, current_dir)?),
+            ("-h" | "--host", Some(value)) => host = Some(value.to_string()),
+            ("-d" | "--dbname", Some(value)) => dbname = Some(value),
+            ("-l" | "--list", _) => scripts.push("\\l".to_string()),
+            _ => {}
+        }
+    }
+    if scripts.is_empty() || reads_stdin {
+        scripts.extend(stdin_scripts(inputs, current_dir)?);
+    }
+
+    // The host comes from -h, a connection URI or `host=` conninfo, then $PGHOST
+    let target = match dbname {
+        Some(uri) if uri.starts_with("postgres://") || uri.starts_with("postgresql://") => match host {
+            Some(host) => host_target(&host, policy),
+            None if database::is_local_url(uri, policy) => Target::Local,
+            None => Target::Remote(uri.to_string()),
+        },
+        Some(conninfo) if conninfo.contains('=') => {
+            let conn_host = conninfo.split_whitespace().find_map(|kv| kv.strip_prefix("host="));
+            match host.as_deref().or(conn_host) {
+                Some(host) => host_target(host, policy),
+                None if conninfo.contains("service=") => Target::Remote(conninfo.to_string()),
+                None => env_host_target("PGHOST", policy, lookup),
+            }
+        }
+        _ => match host {
+            Some(host) => host_target(&host, policy),
+            None => env_host_target("PGHOST", policy, lookup),
+        },
+    };
+    Some(Invocation { scripts: (!scripts.is_empty()).then_some(scripts), target, readonly: false })
+}
+
+fn mysql(args: &[&str], inputs: Vec<Input>, current_dir: &Path, policy: &database::DatabasePolicy, lookup: &dyn Fn(&str) -> Option<String>) -> Option<Invocation> {
+    let (options, _) = parse_args(args, MYSQL_VALUE_FLAGS);
+    let mut scripts = Vec::new();
+    let mut target = None;
+    for (flag, value) in &options {
+        match (*flag, *value) {
+            ("-e" | "--execute", Some(sql)) => scripts.push(sql.to_string()),
+            ("-h" | "--host", Some(host)) => target = Some(host_target(host, policy)),
+            // Option files and login paths can name any server
+            ("--defaults-file" | "--defaults-extra-file" | "--login-path", Some(value)) => target = Some(Target::Remote(value.to_string())),
+            _ => {}
+        }
+    }
+    if scripts.is_empty() {
+        scripts = stdin_scripts(inputs, current_dir)?;
+    }
+    let target = target.unwrap_or_else(|| env_host_target("MYSQL_HOST", policy, lookup));
+    Some(Invocation { scripts: (!scripts.is_empty()).then_some(scripts), target, readonly: false })
+}
+
+/// sqlite3 and duckdb: `[options] [file] [sql...]`
+fn sqlite(args: &[&str], inputs: Vec<Input>, current_dir: &Path) -> Option<Invocation> {
+    let (options, operands) = parse_args(args, SQLITE_VALUE_FLAGS);
+    let mut scripts = Vec::new();
+    let mut readonly = false;
+    let mut exits = false;
+    for (flag, value) in &options {
+        match (*flag, *value) {
+            ("-cmd", Some(sql)) => scripts.push(sql.to_string()),
+            // duckdb -c and -s run the command and exit
+            ("-c" | "-s", Some(sql)) => {
+                scripts.push(sql.to_string());
+                exits = true;
+            }
+            ("-init", Some(file)) => scripts.push(read_script(file, current_dir)?),
+            ("-readonly", _) => readonly = true,
+            _ => {}
+        }
+    }
+    scripts.extend(operands.iter().skip(1).map(|sql| sql.to_string()));
+    if !exits && operands.len() < 2 {
+        let stdin = stdin_scripts(inputs, current_dir)?;
+        if stdin.is_empty() {
+            // Without SQL arguments or stdin the client opens an interactive shell
+            return Some(Invocation { scripts: None, target: Target::Local, readonly });
+        }
+        scripts.extend(stdin);
+    }
+    let target = match operands.first() {
+        None => Target::Local,
+        Some(file) => file_target(file, current_dir),
+    };
+    Some(Invocation { scripts: Some(scripts), target, readonly })
+}
+
+/// A SQLite or DuckDB database is local when it's in memory or a file in the repo
+fn file_target(file: &str, current_dir: &Path) -> Target {
+    let name = file.strip_prefix("file:").map_or(file, |f| f.split('?').next().unwrap_or(f));
+    if name.is_empty() || name == ":memory:" {
+        return Target::Local;
+    }
+    if name.starts_with("md:") || name.contains("://") {
+        return Target::Remote(file.to_string());
+    }
+    match super::is_outside_repo(name, current_dir) {
+        true => Target::Remote(file.to_string()),
+        false => Target::Local,
+    }
+}
+
+/// Deny a secrets file read from SQL; ask for a file outside the repo or off this machine
+fn judge_file_read(program: &str, file: &str, current_dir: &Path) -> Option<Judgment> {
+    if paths::is_secrets_file(file) {
+        Some(Judgment::Deny(format!("{} reads the secrets file `{}`", program, file)))
+    } else if file.contains("://") || super::is_outside_repo(file, current_dir) {
+        Some(Judgment::Ask(format!("{} reads `{}`, outside the repo", program, file)))
+    } else {
+        None
+    }
+}
+
+fn host_target(host: &str, policy: &database::DatabasePolicy) -> Target {
+    // A leading `/` is a unix socket directory
+    match host.starts_with('/') || database::is_local_host(host, policy) {
+        true => Target::Local,
+        false => Target::Remote(host.to_string()),
+    }
+}
+
+fn env_host_target(var: &str, policy: &database::DatabasePolicy, lookup: &dyn Fn(&str) -> Option<String>) -> Target {
+    match lookup(var).filter(|host| !host.is_empty()) {
+        Some(host) => host_target(&host, policy),
+        None => Target::Local,
+    }
+}
+
+/// Separate client meta-commands (psql `\dt`, sqlite `.tables`, mysql `system`) from the SQL
+fn split_meta(program: &str, script: &str) -> (String, Vec<String>) {
+    let mut sql = String::new();
+    let mut metas = Vec::new();
+    for line in script.lines() {
+        let trimmed = line.trim_start();
+        let is_meta = match program {
+            "psql" => trimmed.starts_with('\\'),
+            "mysql" | "mariadb" => {
+                trimmed.starts_with("\\!") || trimmed.starts_with("\\.") || ["system ", "source "].iter().any(|p| trimmed.to_lowercase().starts_with(p))
+            }
+            _ => trimmed.starts_with('.'),
+        };
+        if is_meta {
+            metas.push(trimmed.to_string());
+        } else {
+            sql.push_str(line);
+            sql.push('\n');
+        }
+    }
+    (sql, metas)
+}
+
+/// Judge one meta-command line
+fn judge_meta(program: &str, meta: &str) -> Judgment {
+    let name = meta.split_whitespace().next().unwrap_or_default();
+    let bare = name.trim_start_matches(['\\', '.']).to_lowercase();
+    match bare.as_str() {
+        "!" | "system" | "shell" | "setenv" => Judgment::Ask(format!("{} `{}` runs a shell command", program, name)),
+        _ if FILE_META.contains(&bare.as_str()) => Judgment::Ask(format!("{} `{}` reads or writes local files", program, name)),
+        // \g, \gx with a file argument write the result there
+        "g" | "gx" if meta.split_whitespace().nth(1).is_some() => Judgment::Ask(format!("{} `{}` reads or writes local files", program, name)),
+        _ if program == "psql" && (bare.starts_with('d') || SAFE_PSQL_META.contains(&bare.as_str())) => Judgment::Safe,
+        _ if program != "psql" && SAFE_DOT_COMMANDS.contains(&bare.as_str()) => Judgment::Safe,
+        // Includes (`\i`, `.read`, `source`), reconnects (`\c`, `.open`) and the rest
+        _ => Judgment::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    #[test]
+    fn test_psql() {
+        let dir = TempDir::new("db_client_psql");
+        dir.create_dir(".git");
+        dir.write_file("migrate.sql", "begin;\nalter table users add column age int;\ninsert into audit values (1);\ncommit;\n");
+        let local = "PGHOST=localhost psql";
+        for command in [
+            r#"PGHOST=localhost psql -c "select count(*) from users""#,
+            "PGHOST= psql -h localhost -d app -c 'explain select 1' -c '\\dt'",
+            "psql postgres://app@127.0.0.1:5432/app -X -A -t -c 'show search_path'",
+            "psql \"host=/tmp dbname=app\" -l",
+            "psql -h localhost <<< 'select 1;\n\\d users\nselect 2'",
+        ] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in(&format!("{} -f migrate.sql", local), dir.path()),
+            Judgment::Ask("psql runs statements that change the database (ALTER TABLE, INSERT)".to_string())
+        );
+        assert_eq!(
+            judge_in(&format!("{} -c 'drop table users' -c 'select 1'", local), dir.path()),
+            Judgment::Deny("psql runs DROP TABLE, which destroys data".to_string())
+        );
+        assert_eq!(
+            judge_in("psql -h db.prod.internal -c 'select 1'", dir.path()),
+            Judgment::Ask("psql runs queries against `db.prod.internal`, which isn't a local database".to_string())
+        );
+        assert_eq!(
+            judge_in("psql postgresql://app@db.example.com/app -c 'delete from users'", dir.path()),
+            Judgment::Deny("psql runs DELETE without WHERE, which destroys data".to_string())
+        );
+        assert_eq!(
+            judge_in(&format!("{} -c '\\copy users to /tmp/users.csv csv'", local), dir.path()),
+            Judgment::Ask("psql `\\copy` reads or writes local files".to_string())
+        );
+        assert_eq!(judge_in(&format!("{} -f missing.sql", local), dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in(&format!("{} -d app", local), dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("psql --version", dir.path()), Judgment::Safe);
+    }
+
+    #[test]
+    fn test_mysql() {
+        let dir = TempDir::new("db_client_mysql");
+        dir.write_file("q.sql", "select * from users;\n");
+        assert_eq!(judge_in("MYSQL_HOST= mysql -uroot -e 'show tables' app", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("mysql -h127.0.0.1 app < q.sql", dir.path()), Judgment::Safe);
+        assert_eq!(
+            judge_in("mysql -h 127.0.0.1 --execute='truncate sessions'", dir.path()),
+            Judgment::Deny("mysql runs TRUNCATE, which destroys data".to_string())
+        );
+        assert_eq!(
+            judge_in("mariadb --host=db.example.com -e 'select 1'", dir.path()),
+            Judgment::Ask("mariadb runs queries against `db.example.com`, which isn't a local database".to_string())
+        );
+        assert_eq!(
+            judge_in("mysql -h localhost <<< 'system rm -rf /tmp/x'", dir.path()),
+            Judgment::Ask("mysql `system` runs a shell command".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sqlite_and_duckdb() {
+        let dir = TempDir::new("db_client_sqlite");
+        dir.create_dir(".git");
+        let root = dir.path();
+        for command in ["sqlite3 dev.db '.tables' 'select * from users'", "sqlite3 -readonly dev.db 'delete from users'", "duckdb -c 'summarize select * from t'", "duckdb data.duckdb -s 'select 42'"] {
+            assert_eq!(judge_in(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in("sqlite3 dev.db 'update users set admin = 1 where id = 2'", root),
+            Judgment::Ask("sqlite3 runs statements that change the database (UPDATE)".to_string())
+        );
+        assert_eq!(
+            judge_in("sqlite3 ~/Library/Messages/chat.db 'select * from message'", root),
+            Judgment::Ask("sqlite3 runs queries against `~/Library/Messages/chat.db`, which isn't a local database".to_string())
+        );
+        assert_eq!(
+            judge_in("sqlite3 dev.db '.shell rm -rf build'", root),
+            Judgment::Ask("sqlite3 `.shell` runs a shell command".to_string())
+        );
+        assert_eq!(judge_in("duckdb md:my_db -c 'select 1'", root), Judgment::Ask("duckdb runs queries against `md:my_db`, which isn't a local database".to_string()));
+        assert_eq!(judge_in("sqlite3 dev.db", root), Judgment::Unknown);
+        assert_eq!(judge_in("sqlite3 dev.db '.read other.sql'", root), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_sql_file_reads() {
+        let dir = TempDir::new("db_client_files");
+        dir.create_dir(".git");
+        let root = dir.path();
+        for command in [
+            "duckdb -c \"select * from read_csv('data/events.csv')\"", "duckdb -c \"select count(*) from 'data/*.parquet'\"",
+            "sqlite3 dev.db \"select readfile('fixtures/logo.png')\"",
+        ] {
+            assert_eq!(judge_in(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in("duckdb -c \"select content from read_text('.env')\"", root),
+            Judgment::Deny("duckdb reads the secrets file `.env`".to_string())
+        );
+        assert_eq!(
+            judge_in("duckdb -c \"select * from '/etc/passwd'\"", root),
+            Judgment::Ask("duckdb reads `/etc/passwd`, outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_in("duckdb -c \"select * from read_json('https://example.com/x.json')\"", root),
+            Judgment::Ask("duckdb reads `https://example.com/x.json`, outside the repo".to_string())
+        );
+        assert_eq!(
+            judge_in("sqlite3 -readonly dev.db \"select readfile('/home/me/.ssh/id_rsa')\"", root),
+            Judgment::Ask("sqlite3 reads `/home/me/.ssh/id_rsa`, outside the repo".to_string())
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! dotnet: the .NET CLI's build verbs, NuGet and tool management.
+
+use std::path::Path;
+
+use super::{check_path_flag, operands, Judgment};
+
+/// Flags (of any verb) that take a separate value
+const VALUE_FLAGS: &[&str] = &[
+    "-c", "--configuration", "-f", "--framework", "-r", "--runtime", "-o", "--output", "-v",
+    "--verbosity", "-s", "--source", "-k", "--api-key", "--filter", "-p", "--project",
+    "--logger", "-l", "--arch", "--os", "--version", "--tool-path", "--settings",
+];
+
+/// Verbs that build, test, run, format or inspect the project; `publish` only writes a
+/// deployable build to a local folder
+const SAFE_VERBS: &[&str] = &["build", "test", "run", "restore", "clean", "format", "pack", "publish", "msbuild", "vstest", "help", "build-server"];
+
+/// Informational flags of the bare `dotnet` host
+const INFO_FLAGS: &[&str] = &["--info", "--version", "--list-sdks", "--list-runtimes", "-h", "--help", "-?"];
+
+/// Judge `dotnet <verb> [args]`
+pub fn judge(args: &[&str], current_dir: &Path) -> Judgment {
+    if args.first().is_some_and(|a| INFO_FLAGS.contains(a)) {
+        return Judgment::Safe;
+    }
+    if let Some(judgment) = check_path_flag(args, "--output", "dotnet", current_dir).or_else(|| check_path_flag(args, "-o", "dotnet", current_dir)) {
+        return judgment;
+    }
+    let words = operands(args, VALUE_FLAGS);
+    let global = args.iter().any(|a| matches!(*a, "-g" | "--global"));
+    match words.as_slice() {
+        [verb, ..] if SAFE_VERBS.contains(verb) => Judgment::Safe,
+        ["list", .., "package" | "reference"] | ["sln", "list"] | ["tool", "list" | "search"] | ["workload", "list" | "search"] => Judgment::Safe,
+        ["nuget", "push" | "delete", ..] => {
+            Judgment::Ask(format!("dotnet nuget {} changes what's published on the feed, which can't be undone", words[1]))
+        }
+        ["nuget", "locals", ..] if !args.iter().any(|a| matches!(*a, "--clear" | "-c")) => Judgment::Safe,
+        ["nuget", "verify" | "why", ..] => Judgment::Safe,
+        // Tools pinned in the local manifest
+        ["tool", "restore"] => Judgment::Safe,
+        ["tool", sub @ ("install" | "update" | "uninstall"), tools @ ..] if global => Judgment::Ask(format!(
+            "dotnet tool {} -g changes the user's global tools ({})",
+            sub,
+            tools.iter().map(|t| format!("`{}`", t)).collect::<Vec<_>>().join(", ")
+        )),
+        ["workload", "install" | "update" | "uninstall" | "repair", ..] => Judgment::Ask("dotnet workload changes the installed SDK workloads".to_string()),
+        ["dev-certs", "https", ..] if args.iter().any(|a| matches!(*a, "--trust" | "--clean")) => {
+            Judgment::Ask("dotnet dev-certs changes the trusted HTTPS development certificate".to_string())
+        }
+        // `add package`, `new`, local tools, `ef` and `watch` are judged elsewhere or not at all
+        _ => Judgment::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    #[test]
+    fn test_dotnet_verbs() {
+        let dir = TempDir::new("dotnet_verbs");
+        dir.create_dir(".git");
+        for command in ["dotnet test --filter Category=Unit", "dotnet build -c Release src/App.csproj", "dotnet publish -o out", "dotnet list package --outdated", "dotnet --info", "dotnet tool restore"] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in("dotnet nuget push bin/App.1.0.nupkg -k KEY -s https://api.nuget.org/v3/index.json", dir.path()),
+            Judgment::Ask("dotnet nuget push changes what's published on the feed, which can't be undone".to_string())
+        );
+        assert_eq!(
+            judge_in("dotnet tool install -g dotnet-ef", dir.path()),
+            Judgment::Ask("dotnet tool install -g changes the user's global tools (`dotnet-ef`)".to_string())
+        );
+        assert_eq!(
+            judge_in("dotnet publish -o /srv/app", dir.path()),
+            Judgment::Ask("dotnet -o `/srv/app` is outside the repo".to_string())
+        );
+        assert_eq!(judge_in("dotnet add package Newtonsoft.Json", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("dotnet ef database update", dir.path()), Judgment::Unknown);
+    }
+}
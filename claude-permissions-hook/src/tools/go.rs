@@ -0,0 +1,293 @@
+//! go: a policy per subcommand, with `go generate` judged by the `//go:generate`
+//! directives in the packages it targets.
+
+use std::path::{Path, PathBuf};
+
+use super::{check_path_flag, Judgment};
+use crate::package_scripts::ScriptCommand;
+use crate::paths;
+
+/// Subcommands that build, test, run, format or inspect the local module
+const LOCAL_SUBCOMMANDS: &[&str] = &["build", "test", "vet", "run", "fmt", "fix", "list", "doc", "version", "help"];
+
+/// `go mod` and `go work` operations: they only edit go.mod/go.work and fetch what
+/// go.sum pins
+const MODULE_SUBCOMMANDS: &[&str] = &["tidy", "download", "verify", "graph", "why", "init", "vendor", "edit", "use", "sync"];
+
+/// `go tool` tools that only inspect binaries and profiles
+const SAFE_GO_TOOLS: &[&str] = &["cover", "nm", "objdump", "addr2line", "buildid"];
+
+/// Build flags that run another program for every compile or test binary
+const EXEC_FLAGS: &[&str] = &["-toolexec", "-exec"];
+
+/// `go generate` flags that take a separate value
+const GENERATE_VALUE_FLAGS: &[&str] = &["-run", "-skip", "-tags", "-mod", "-modfile", "-overlay", "-p", "-C"];
+
+/// Cap on `.go` files scanned for `//go:generate` directives
+const MAX_GO_FILES: usize = 5000;
+
+/// Judge `go [-C dir] <subcommand> [args]`
+pub fn judge(args: &[&str], current_dir: &Path) -> Judgment {
+    let (dir, args) = match args {
+        ["-C", dir, rest @ ..] => (paths::normalize_path(dir, &current_dir.to_string_lossy()), rest),
+        _ => (current_dir.to_path_buf(), args),
+    };
+    let Some((&sub, args)) = args.split_first() else {
+        // Bare `go` prints usage
+        return Judgment::Safe;
+    };
+    // Go accepts `--flag` as well as `-flag`
+    let args: Vec<&str> = args.iter().map(|a| if a.starts_with("--") { &a[1..] } else { a }).collect();
+
+    if matches!(sub, "build" | "test" | "run" | "install" | "vet") && args.iter().any(|a| EXEC_FLAGS.contains(&a.split('=').next().unwrap_or(a))) {
+        return Judgment::Unknown;
+    }
+    if let Some(judgment) = check_path_flag(&args, "-o", "go", &dir) {
+        return judgment;
+    }
+
+    let operands: Vec<&str> = args.iter().filter(|a| !a.starts_with('-')).copied().collect();
+    let has = |flag: &str| args.iter().any(|a| *a == flag || a.strip_prefix(flag).is_some_and(|v| v.starts_with('=')));
+    match sub {
+        "run" => match operands.iter().find(|o| o.contains('@')) {
+            Some(remote) => Judgment::Ask(format!("go run downloads and runs `{}`", remote)),
+            None => Judgment::Safe,
+        },
+        _ if LOCAL_SUBCOMMANDS.contains(&sub) => Judgment::Safe,
+        "mod" | "work" if operands.first().is_some_and(|op| MODULE_SUBCOMMANDS.contains(op)) => Judgment::Safe,
+        "env" if has("-w") || has("-u") => Judgment::Ask(format!(
+            "go env {} changes the Go environment for every project ({})",
+            if has("-w") { "-w" } else { "-u" },
+            quoted(&operands)
+        )),
+        "env" => Judgment::Safe,
+        "clean" if has("-modcache") => Judgment::Ask("go clean -modcache deletes the whole module cache".to_string()),
+        "clean" if has("-i") => Judgment::Ask("go clean -i removes installed binaries".to_string()),
+        "clean" => Judgment::Safe,
+        "get" => Judgment::Ask(format!("go get changes the module's dependencies ({})", quoted(&operands))),
+        "install" => Judgment::Ask(format!(
+            "go install builds and installs {} into $GOBIN",
+            if operands.is_empty() { "the current package".to_string() } else { quoted(&operands) }
+        )),
+        "tool" if operands.first().is_some_and(|tool| SAFE_GO_TOOLS.contains(tool)) => Judgment::Safe,
+        "telemetry" if operands.is_empty() => Judgment::Safe,
+        "generate" if has("-n") => Judgment::Safe,
+        "generate" => generate(&args, &dir, current_dir),
+        _ => Judgment::Unknown,
+    }
+}
+
+fn quoted(items: &[&str]) -> String {
+    items.iter().map(|i| format!("`{}`", i)).collect::<Vec<_>>().join(", ")
+}
+
+/// The `//go:generate` directives in the targeted packages, in the order go runs them.
+/// `-run` and `-skip` are ignored, so every directive is judged.
+fn generate(args: &[&str], dir: &Path, current_dir: &Path) -> Judgment {
+    let mut patterns = Vec::new();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        if GENERATE_VALUE_FLAGS.contains(&arg) {
+            i += 1;
+        } else if !arg.starts_with('-') {
+            patterns.push(arg);
+        }
+    }
+    if patterns.is_empty() {
+        patterns.push(".");
+    }
+
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let Some(package_dirs) = package_dirs(pattern, dir) else {
+            return Judgment::Unknown;
+        };
+        for package_dir in package_dirs {
+            let Ok(entries) = std::fs::read_dir(&package_dir) else {
+                return Judgment::Unknown;
+            };
+            let mut go_files: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "go") && p.is_file())
+                .collect();
+            go_files.sort();
+            files.extend(go_files);
+        }
+        if files.len() > MAX_GO_FILES {
+            return Judgment::Unknown;
+        }
+    }
+
+    let mut commands = Vec::new();
+    for file in files {
+        let Ok(source) = std::fs::read_to_string(&file) else {
+            return Judgment::Unknown;
+        };
+        commands.extend(directives(&file, &source, current_dir));
+    }
+    Judgment::Commands(commands)
+}
+
+/// Directories of the packages a pattern names: `.`, `./pkg`, `./...`, or an import path
+/// inside the current module
+fn package_dirs(pattern: &str, dir: &Path) -> Option<Vec<PathBuf>> {
+    let (base, recursive) = match pattern.strip_suffix("...") {
+        Some(base) => (base.trim_end_matches('/'), true),
+        None => (pattern, false),
+    };
+    let root = if base.is_empty() || base == "." || base.starts_with("./") || base.starts_with("../") || base.starts_with('/') {
+        paths::normalize_path(if base.is_empty() { "." } else { base }, &dir.to_string_lossy())
+    } else {
+        // An import path: only packages of the current module can be found on disk
+        let module_root = dir.ancestors().find(|d| d.join("go.mod").is_file())?;
+        let go_mod = std::fs::read_to_string(module_root.join("go.mod")).ok()?;
+        let module = go_mod.lines().find_map(|l| l.trim().strip_prefix("module "))?.trim().trim_matches('"');
+        let rest = base.strip_prefix(module)?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        module_root.join(rest.trim_start_matches('/'))
+    };
+    if !root.is_dir() {
+        return None;
+    }
+    let mut dirs = vec![root.clone()];
+    if recursive {
+        let mut i = 0;
+        while let Some(current) = dirs.get(i).cloned() {
+            i += 1;
+            let Ok(entries) = std::fs::read_dir(&current) else { continue };
+            let mut children: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_dir())
+                .filter(|p| {
+                    let name = p.file_name().unwrap_or_default().to_string_lossy();
+                    // go skips these, and nested modules are separate
+                    !(name.starts_with('.') || name.starts_with('_') || name == "testdata" || name == "vendor" || p.join("go.mod").is_file())
+                })
+                .collect();
+            children.sort();
+            dirs.extend(children);
+            if dirs.len() > MAX_GO_FILES {
+                return None;
+            }
+        }
+    }
+    Some(dirs)
+}
+
+/// The commands of a file's `//go:generate` lines, with `-command` aliases and the
+/// `$GOFILE`, `$GOLINE`, `$GOPACKAGE` and `$DOLLAR` variables expanded
+fn directives(file: &Path, source: &str, current_dir: &Path) -> Vec<ScriptCommand> {
+    let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+    let package = source.lines().find_map(|l| l.strip_prefix("package ")).and_then(|p| p.split_whitespace().next()).unwrap_or_default();
+    let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut aliases: Vec<(String, String)> = Vec::new();
+    let mut commands = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let Some(command) = line.strip_prefix("//go:generate").filter(|c| c.starts_with([' ', '\t'])) else {
+            continue;
+        };
+        let mut command = command.trim().to_string();
+        for (name, value) in [("GOFILE", &*file_name), ("GOLINE", &(idx + 1).to_string()), ("GOPACKAGE", package), ("DOLLAR", "$")] {
+            command = command.replace(&format!("${{{}}}", name), value).replace(&format!("${}", name), value);
+        }
+        if let Some(alias) = command.strip_prefix("-command ") {
+            if let Some((name, value)) = alias.trim().split_once(char::is_whitespace) {
+                aliases.push((name.to_string(), value.trim().to_string()));
+            }
+            continue;
+        }
+        let first = command.split_whitespace().next().unwrap_or_default().to_string();
+        if let Some((_, value)) = aliases.iter().rev().find(|(name, _)| *name == first) {
+            command = format!("{}{}", value, &command[first.len()..]);
+        }
+        commands.push(ScriptCommand {
+            label: format!("{}:{} go:generate", paths::display_path(file, current_dir), idx + 1),
+            command,
+            dir: dir.clone(),
+        });
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    fn generated(judgment: Judgment) -> Vec<(String, String)> {
+        match judgment {
+            Judgment::Commands(commands) => commands.into_iter().map(|c| (c.label, c.command)).collect(),
+            other => panic!("expected commands, got {:?}", other),
+        }
+    }
+
+    fn pair(label: &str, command: &str) -> (String, String) {
+        (label.to_string(), command.to_string())
+    }
+
+    #[test]
+    fn test_subcommand_policy() {
+        let dir = TempDir::new("go_policy");
+        dir.create_dir(".git");
+        for command in ["go build ./...", "go test -race -count=1 ./...", "go run ./cmd/server", "go mod tidy", "go env GOPATH", "go clean -testcache", "go tool cover -html=c.out", "go version"] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in("go run golang.org/x/tools/cmd/stringer@latest -type=Kind", dir.path()),
+            Judgment::Ask("go run downloads and runs `golang.org/x/tools/cmd/stringer@latest`".to_string())
+        );
+        assert_eq!(
+            judge_in("go env -w GOFLAGS=-mod=mod", dir.path()),
+            Judgment::Ask("go env -w changes the Go environment for every project (`GOFLAGS=-mod=mod`)".to_string())
+        );
+        assert_eq!(judge_in("go clean -modcache", dir.path()), Judgment::Ask("go clean -modcache deletes the whole module cache".to_string()));
+        assert_eq!(
+            judge_in("go get github.com/pkg/errors@v0.9.1", dir.path()),
+            Judgment::Ask("go get changes the module's dependencies (`github.com/pkg/errors@v0.9.1`)".to_string())
+        );
+        assert_eq!(
+            judge_in("go install ./cmd/tool", dir.path()),
+            Judgment::Ask("go install builds and installs `./cmd/tool` into $GOBIN".to_string())
+        );
+        assert_eq!(
+            judge_in("go build -o /usr/local/bin/app .", dir.path()),
+            Judgment::Ask("go -o `/usr/local/bin/app` is outside the repo".to_string())
+        );
+        assert_eq!(judge_in("go test -exec sudo ./...", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("go tool yacc", dir.path()), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_generate_directives() {
+        let dir = TempDir::new("go_generate");
+        dir.write_file("go.mod", "module example.com/app\n\ngo 1.22\n");
+        dir.write_file("main.go", "package main\n\n//go:generate stringer -type=Kind $GOFILE\nfunc main() {}\n");
+        dir.write_file(
+            "api/api.go",
+            "package api\n//go:generate -command gen go run ./internal/gen\n//go:generate gen -pkg $GOPACKAGE -line ${GOLINE}\n// go:generate not a directive\n",
+        );
+        dir.write_file("testdata/x.go", "package x\n//go:generate rm -rf /\n");
+        dir.write_file("tools/go.mod", "module example.com/tools\n");
+        dir.write_file("tools/t.go", "package tools\n//go:generate rm -rf /\n");
+
+        assert_eq!(generated(judge_in("go generate", dir.path())), vec![pair("main.go:3 go:generate", "stringer -type=Kind main.go")]);
+        assert_eq!(
+            generated(judge_in("go generate -x ./...", dir.path())),
+            vec![
+                pair("main.go:3 go:generate", "stringer -type=Kind main.go"),
+                pair("api/api.go:3 go:generate", "go run ./internal/gen -pkg api -line 3"),
+            ]
+        );
+        assert_eq!(
+            generated(judge_in("go generate example.com/app/api", dir.path())),
+            vec![pair("api/api.go:3 go:generate", "go run ./internal/gen -pkg api -line 3")]
+        );
+        assert_eq!(judge_in("go generate -n ./...", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("go generate ./missing", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("go generate github.com/other/pkg", dir.path()), Judgment::Unknown);
+    }
+}
@@ -0,0 +1,302 @@
+//! Infrastructure as code: terraform, OpenTofu and terragrunt, pulumi, and the AWS and
+//! Terraform CDKs. Formatting, validating and planning are allowed when the working
+//! directory is inside the repo; applying changes asks, naming the workspace or stack
+//! (resolved from `.terraform/environment` and pulumi's workspace files). Destroying,
+//! unattended applies to workspaces that aren't known to be for development, and
+//! force-unlocking state are denied.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::Judgment;
+use crate::config::Config;
+use crate::paths;
+use crate::rules;
+use crate::yaml;
+
+/// Workspace and stack name patterns that count as development unless configured otherwise
+const DEFAULT_DEV_WORKSPACES: &[&str] = &["dev", "dev-*", "*-dev", "development", "test", "test-*", "*-test", "local", "sandbox*"];
+
+/// terraform flags that take a separate value
+const TERRAFORM_VALUE_FLAGS: &[&str] = &[
+    "-var", "-var-file", "-target", "-replace", "-out", "-state", "-state-out", "-backup",
+    "-lock-timeout", "-parallelism", "-backend-config", "-generate-config-out", "-chdir",
+];
+
+/// terraform commands that only read configuration or state
+const TERRAFORM_READS: &[&str] = &["fmt", "validate", "plan", "show", "output", "version", "providers", "graph", "init", "get", "modules", "metadata"];
+
+/// terraform commands that change infrastructure or state
+const TERRAFORM_WRITES: &[&str] = &["apply", "import", "refresh", "taint", "untaint"];
+
+/// pulumi flags that take a separate value
+const PULUMI_VALUE_FLAGS: &[&str] = &[
+    "-s", "--stack", "-C", "--cwd", "-t", "--target", "--target-replace", "--replace", "-m",
+    "--message", "-p", "--parallel", "--config-file", "-c", "--config", "--policy-pack", "--exclude",
+];
+
+/// pulumi commands that only read
+const PULUMI_READS: &[&str] = &["preview", "pre", "whoami", "version", "about", "logs", "config", "stack", "plugin", "schema", "convert", "gen-completion", "help"];
+
+/// pulumi commands that change infrastructure or state
+const PULUMI_WRITES: &[&str] = &["up", "update", "refresh", "import", "state", "cancel", "watch"];
+
+/// Which workspaces and stacks are for development
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct InfrastructurePolicy {
+    /// Workspace and stack name patterns (`*`, `?`) where unattended applies only ask;
+    /// replaces the built-in patterns when set
+    pub dev_workspaces: Option<Vec<String>>,
+}
+
+impl InfrastructurePolicy {
+    /// Pulumi's `org/project/stack` names match on the stack
+    fn is_dev(&self, workspace: &str) -> bool {
+        let name = workspace.rsplit('/').next().unwrap_or(workspace);
+        match &self.dev_workspaces {
+            Some(patterns) => patterns.iter().any(|pattern| paths::glob_match(pattern, name)),
+            None => DEFAULT_DEV_WORKSPACES.iter().any(|pattern| paths::glob_match(pattern, name)),
+        }
+    }
+}
+
+/// Check if a program is modelled here
+pub fn is_iac_tool(program: &str) -> bool {
+    matches!(program, "terraform" | "tofu" | "terragrunt" | "pulumi" | "cdk" | "cdktf")
+}
+
+/// Judge an IaC command segment, which may start with environment assignments (`TF_WORKSPACE`)
+pub fn judge(segment: &str, current_dir: &Path, config: &Config) -> Judgment {
+    let words = paths::shell_words(segment);
+    let start = words.iter().position(|w| !rules::is_env_assignment(w)).unwrap_or(words.len());
+    let env: Vec<(&str, &str)> = words[..start].iter().filter_map(|w| w.split_once('=')).collect();
+    let lookup = |name: &str| env.iter().rev().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()).or_else(|| std::env::var(name).ok());
+    let Some((program, args)) = words[start..].split_first() else {
+        return Judgment::Unknown;
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let policy = &config.infrastructure;
+    match program.as_str() {
+        "pulumi" => pulumi(&args, current_dir, policy),
+        "cdk" | "cdktf" => cdk(program, &args),
+        _ => terraform(program, &args, current_dir, policy, &lookup),
+    }
+}
+
+/// Flags (with their values, `-flag=value` or `-flag value`) and positionals
+fn split_args<'a>(args: &[&'a str], value_flags: &[&str]) -> (Vec<(&'a str, Option<&'a str>)>, Vec<&'a str>) {
+    let mut flags = Vec::new();
+    let mut positionals = Vec::new();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        if !arg.starts_with('-') {
+            positionals.push(arg);
+        } else if let Some((flag, value)) = arg.split_once('=') {
+            flags.push((flag, Some(value)));
+        } else if value_flags.contains(&arg) {
+            flags.push((arg, args.get(i).copied()));
+            i += 1;
+        } else {
+            flags.push((arg, None));
+        }
+    }
+    (flags, positionals)
+}
+
+fn has(flags: &[(&str, Option<&str>)], names: &[&str]) -> bool {
+    flags.iter().any(|(flag, value)| names.contains(flag) && value.is_none_or(|v| v != "false"))
+}
+
+fn value<'a>(flags: &[(&'a str, Option<&'a str>)], names: &[&str]) -> Option<&'a str> {
+    flags.iter().rev().find(|(flag, _)| names.contains(flag)).and_then(|(_, value)| *value)
+}
+
+fn terraform(program: &str, args: &[&str], current_dir: &Path, policy: &InfrastructurePolicy, lookup: &dyn Fn(&str) -> Option<String>) -> Judgment {
+    let (flags, positionals) = split_args(args, TERRAFORM_VALUE_FLAGS);
+    // terragrunt runs terraform across modules: `run-all apply`, `run --all apply`
+    let positionals: Vec<&str> = positionals.into_iter().filter(|p| program != "terragrunt" || !matches!(*p, "run-all" | "run")).collect();
+    let Some((&verb, rest)) = positionals.split_first() else {
+        return if has(&flags, &["-version", "--version", "-help", "--help", "-h"]) { Judgment::Safe } else { Judgment::Unknown };
+    };
+    let dir = paths::normalize_path(value(&flags, &["-chdir", "--working-dir"]).unwrap_or("."), &current_dir.to_string_lossy());
+    let display = format!("{} {}", program, verb);
+    let workspace = || terraform_workspace(&dir, lookup);
+    match (verb, rest.first().copied()) {
+        ("destroy", _) => Judgment::Deny(format!("{} destroys the infrastructure it manages", display)),
+        ("apply", _) if has(&flags, &["-destroy"]) => Judgment::Deny(format!("{} -destroy destroys the infrastructure it manages", display)),
+        ("force-unlock", _) => Judgment::Deny(format!("{} can corrupt state that another run is writing", display)),
+        ("output", _) | ("show", _) if has(&flags, &["-json", "-raw"]) => {
+            Judgment::Ask(format!("{} {} prints sensitive values unredacted", display, if has(&flags, &["-raw"]) { "-raw" } else { "-json" }))
+        }
+        ("state", Some("pull")) => Judgment::Ask(format!("{} pull prints sensitive values unredacted", display)),
+        ("apply", _) if has(&flags, &["-auto-approve", "--auto-approve", "--non-interactive"]) => {
+            let workspace = workspace();
+            if policy.is_dev(&workspace) {
+                Judgment::Ask(format!("{} -auto-approve changes infrastructure in workspace `{}`", display, workspace))
+            } else {
+                Judgment::Deny(format!("{} -auto-approve changes workspace `{}` without review", display, workspace))
+            }
+        }
+        _ if TERRAFORM_WRITES.contains(&verb) => Judgment::Ask(format!("{} changes infrastructure in workspace `{}`", display, workspace())),
+        ("state", Some("list" | "show")) | ("workspace", Some("list" | "show" | "select" | "new")) => in_repo(&dir),
+        ("state", Some(sub)) => Judgment::Ask(format!("{} {} changes the state of workspace `{}`", display, sub, workspace())),
+        ("workspace", Some("delete")) => Judgment::Ask(format!("{} delete deletes a workspace's state", display)),
+        _ if TERRAFORM_READS.contains(&verb) => in_repo(&dir),
+        _ => Judgment::Unknown,
+    }
+}
+
+/// Reads are only vouched for in the repo's own configuration
+fn in_repo(dir: &Path) -> Judgment {
+    if paths::find_repo_root(dir).is_some() {
+        Judgment::Safe
+    } else {
+        Judgment::Unknown
+    }
+}
+
+/// The selected workspace: `$TF_WORKSPACE`, else `.terraform/environment`, else `default`
+fn terraform_workspace(dir: &Path, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    lookup("TF_WORKSPACE")
+        .filter(|w| !w.is_empty())
+        .or_else(|| {
+            let data_dir = lookup("TF_DATA_DIR").map(|d| dir.join(d)).unwrap_or_else(|| dir.join(".terraform"));
+            std::fs::read_to_string(data_dir.join("environment")).ok().map(|w| w.trim().to_string())
+        })
+        .filter(|w| !w.is_empty())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+fn pulumi(args: &[&str], current_dir: &Path, policy: &InfrastructurePolicy) -> Judgment {
+    let (flags, positionals) = split_args(args, PULUMI_VALUE_FLAGS);
+    let Some((&verb, rest)) = positionals.split_first() else {
+        return if has(&flags, &["--version", "--help", "-h"]) { Judgment::Safe } else { Judgment::Unknown };
+    };
+    let dir = paths::normalize_path(value(&flags, &["-C", "--cwd"]).unwrap_or("."), &current_dir.to_string_lossy());
+    let display = format!("pulumi {}", verb);
+    let stack = || value(&flags, &["-s", "--stack"]).map(str::to_string).or_else(|| pulumi_stack(&dir)).unwrap_or_else(|| "(unknown)".to_string());
+    match (verb, rest.first().copied()) {
+        ("destroy" | "down", _) => Judgment::Deny(format!("{} destroys the stack's infrastructure", display)),
+        ("stack", Some("rm")) => Judgment::Deny("pulumi stack rm deletes the stack and its state".to_string()),
+        ("cancel", _) => Judgment::Deny(format!("{} can corrupt state that another update is writing", display)),
+        (_, _) if has(&flags, &["--show-secrets"]) => Judgment::Ask(format!("{} --show-secrets prints secrets unredacted", display)),
+        ("up" | "update", _) if has(&flags, &["-y", "--yes", "-f", "--skip-preview"]) => {
+            let stack = stack();
+            if policy.is_dev(&stack) {
+                Judgment::Ask(format!("{} --yes changes infrastructure in stack `{}`", display, stack))
+            } else {
+                Judgment::Deny(format!("{} --yes changes stack `{}` without review", display, stack))
+            }
+        }
+        _ if PULUMI_WRITES.contains(&verb) => Judgment::Ask(format!("{} changes infrastructure in stack `{}`", display, stack())),
+        ("config" | "stack", Some("set" | "rm" | "init" | "rename" | "import" | "change-secrets-provider" | "tag" | "unselect")) => {
+            Judgment::Ask(format!("{} {} changes the stack's configuration", display, rest[0]))
+        }
+        _ if PULUMI_READS.contains(&verb) => in_repo(&dir),
+        _ => Judgment::Unknown,
+    }
+}
+
+/// The selected stack, from pulumi's workspace file for the project
+/// (`~/.pulumi/workspaces/<project>-<hash>-workspace.json`); `None` if there isn't exactly one
+fn pulumi_stack(dir: &Path) -> Option<String> {
+    let project_file = dir.ancestors().map(|d| d.join("Pulumi.yaml")).find(|p| p.is_file())?;
+    let project = yaml::parse(&std::fs::read_to_string(project_file).ok()?)?.get("name")?.as_str()?.to_string();
+    let workspaces = PathBuf::from(paths::home_dir()).join(".pulumi/workspaces");
+    let prefix = format!("{}-", project);
+    let mut matches = std::fs::read_dir(workspaces).ok()?.filter_map(Result::ok).filter(|entry| {
+        let name = entry.file_name().to_string_lossy().to_string();
+        name.starts_with(&prefix) && name.ends_with("-workspace.json") && name[prefix.len()..].matches('-').count() == 1
+    });
+    let (Some(entry), None) = (matches.next(), matches.next()) else {
+        return None;
+    };
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(entry.path()).ok()?).ok()?;
+    json.get("stack")?.as_str().map(str::to_string)
+}
+
+fn cdk(program: &str, args: &[&str]) -> Judgment {
+    let (flags, positionals) = split_args(args, &["-a", "--app", "-c", "--context", "-o", "--output", "--profile", "--language", "-l"]);
+    let Some(&verb) = positionals.first() else {
+        return if has(&flags, &["--version", "--help", "-h"]) { Judgment::Safe } else { Judgment::Unknown };
+    };
+    let display = format!("{} {}", program, verb);
+    match verb {
+        // These run the app to synthesize it, like a build
+        "synth" | "synthesize" | "diff" | "ls" | "list" | "doctor" | "docs" | "notices" | "acknowledge" | "metadata" | "context" | "get" | "plan" | "output" | "outputs" | "version" => Judgment::Safe,
+        "destroy" => Judgment::Deny(format!("{} destroys the stacks' infrastructure", display)),
+        "deploy" | "apply" | "bootstrap" | "import" | "migrate" | "rollback" => Judgment::Ask(format!("{} changes infrastructure", display)),
+        _ => Judgment::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    fn repo(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        dir.create_dir(".git");
+        dir
+    }
+
+    #[test]
+    fn test_terraform() {
+        let dir = repo("iac_terraform");
+        let root = dir.path();
+        for command in ["terraform fmt -check", "terraform plan -out=tfplan", "tofu validate", "terraform output", "terraform state list", "terragrunt run-all plan"] {
+            assert_eq!(judge_in(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_in("terraform plan", Path::new("/")), Judgment::Unknown);
+        assert_eq!(judge_in("terraform output -json", root), Judgment::Ask("terraform output -json prints sensitive values unredacted".to_string()));
+        assert_eq!(
+            judge_in("terraform apply tfplan", root),
+            Judgment::Ask("terraform apply changes infrastructure in workspace `default`".to_string())
+        );
+        assert_eq!(
+            judge_in("terraform state rm aws_instance.web", root),
+            Judgment::Ask("terraform state rm changes the state of workspace `default`".to_string())
+        );
+        assert_eq!(judge_in("terraform destroy -target=aws_instance.web", root), Judgment::Deny("terraform destroy destroys the infrastructure it manages".to_string()));
+        assert_eq!(judge_in("tofu force-unlock 1234", root), Judgment::Deny("tofu force-unlock can corrupt state that another run is writing".to_string()));
+        assert_eq!(
+            judge_in("terraform apply -auto-approve", root),
+            Judgment::Deny("terraform apply -auto-approve changes workspace `default` without review".to_string())
+        );
+
+        dir.write_file("infra/.terraform/environment", "dev\n");
+        assert_eq!(
+            judge_in("terraform -chdir=infra apply -auto-approve", root),
+            Judgment::Ask("terraform apply -auto-approve changes infrastructure in workspace `dev`".to_string())
+        );
+        assert_eq!(
+            judge_in("TF_WORKSPACE=prod terraform -chdir=infra apply -auto-approve", root),
+            Judgment::Deny("terraform apply -auto-approve changes workspace `prod` without review".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pulumi_and_cdk() {
+        let dir = repo("iac_pulumi");
+        let root = dir.path();
+        for command in ["pulumi preview", "pulumi stack ls", "pulumi config get region", "cdk synth", "cdktf diff"] {
+            assert_eq!(judge_in(command, root), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_in("pulumi up -s acme/web/dev", root), Judgment::Ask("pulumi up changes infrastructure in stack `acme/web/dev`".to_string()));
+        assert_eq!(
+            judge_in("pulumi up --yes --stack production", root),
+            Judgment::Deny("pulumi up --yes changes stack `production` without review".to_string())
+        );
+        assert_eq!(
+            judge_in("pulumi stack output --show-secrets", root),
+            Judgment::Ask("pulumi stack --show-secrets prints secrets unredacted".to_string())
+        );
+        assert_eq!(judge_in("pulumi destroy -s dev", root), Judgment::Deny("pulumi destroy destroys the stack's infrastructure".to_string()));
+        assert_eq!(judge_in("cdk deploy --all", root), Judgment::Ask("cdk deploy changes infrastructure".to_string()));
+    }
+}
@@ -0,0 +1,155 @@
+//! Gradle and Maven, including the `./gradlew` and `./mvnw` wrappers: every task or
+//! goal on the command line is judged, and the strictest wins.
+
+use super::{operands, strictest, Judgment};
+
+/// Gradle flags that take a separate value
+const GRADLE_VALUE_FLAGS: &[&str] = &[
+    "-p", "--project-dir", "-b", "--build-file", "-c", "--settings-file", "-g", "--gradle-user-home",
+    "-I", "--init-script", "-x", "--exclude-task", "--console", "--warning-mode", "--max-workers",
+    "--priority", "--include-build", "--update-locks", "--write-verification-metadata", "-D", "-P",
+];
+
+/// Gradle tasks that build, test, run (`run`, `bootRun`), check or report on the local project
+const SAFE_GRADLE_TASKS: &[&str] = &[
+    "build", "assemble", "test", "check", "clean", "classes", "testClasses", "jar", "javadoc",
+    "dependencies", "dependencyInsight", "tasks", "projects", "properties", "help",
+    "buildEnvironment", "outgoingVariants", "buildNeeded", "buildDependents", "installDist",
+    "distZip", "distTar", "run", "bootRun", "bootJar", "detekt", "spotlessCheck", "spotlessApply",
+    "ktlintCheck", "ktlintFormat", "jacocoTestReport", "lint",
+];
+
+/// Prefixes of generated Gradle task names (`compileKotlin`, `assembleDebug`, `testDebugUnitTest`)
+const SAFE_GRADLE_TASK_PREFIXES: &[&str] = &["compile", "assemble", "test", "lint", "check", "bundle", "process", "generate", "merge"];
+
+/// Maven flags that take a separate value
+const MAVEN_VALUE_FLAGS: &[&str] = &[
+    "-f", "--file", "-s", "--settings", "-gs", "--global-settings", "-P", "--activate-profiles",
+    "-pl", "--projects", "-rf", "--resume-from", "-T", "--threads", "-l", "--log-file", "-t",
+    "--toolchains", "-D", "--define", "-b", "--builder",
+];
+
+/// Maven lifecycle phases up to `verify`, plus `clean` and `site`
+const SAFE_MAVEN_PHASES: &[&str] = &[
+    "validate", "initialize", "generate-sources", "process-sources", "generate-resources",
+    "process-resources", "compile", "process-classes", "generate-test-sources",
+    "process-test-sources", "generate-test-resources", "process-test-resources", "test-compile",
+    "process-test-classes", "test", "prepare-package", "package", "pre-integration-test",
+    "integration-test", "post-integration-test", "verify", "clean", "pre-clean", "post-clean",
+    "site", "pre-site", "post-site",
+];
+
+/// Maven plugins whose goals only build, check or report (`dependency:tree`)
+const SAFE_MAVEN_PLUGINS: &[&str] = &[
+    "dependency", "help", "compiler", "surefire", "failsafe", "jacoco", "checkstyle", "spotless",
+    "enforcer", "spotbugs", "pmd", "javadoc", "spring-boot", "quarkus", "jar", "resources",
+];
+
+/// Check if a program is Gradle, Maven or one of their wrapper scripts
+pub fn is_jvm_tool(program: &str) -> bool {
+    matches!(program, "gradle" | "mvn") || matches!(program.rsplit('/').next(), Some("gradlew" | "mvnw"))
+}
+
+/// Judge a Gradle or Maven command
+pub fn judge(program: &str, args: &[&str]) -> Judgment {
+    if program.ends_with("gradle") || program.ends_with("gradlew") {
+        judge_gradle(args)
+    } else {
+        judge_maven(args)
+    }
+}
+
+fn judge_gradle(args: &[&str]) -> Judgment {
+    if args.iter().any(|a| matches!(*a, "-v" | "--version" | "-h" | "--help" | "-?" | "-m" | "--dry-run")) {
+        return Judgment::Safe;
+    }
+    let tasks = operands(args, GRADLE_VALUE_FLAGS);
+    if tasks.is_empty() {
+        // Bare `gradle` runs the default tasks, which the build script chooses
+        return Judgment::Unknown;
+    }
+    strictest(tasks.iter().map(|task| {
+        // `:app:test` runs `test` in the `app` project
+        let name = task.rsplit(':').next().unwrap_or(task);
+        match name {
+            _ if SAFE_GRADLE_TASKS.contains(&name) => Judgment::Safe,
+            _ if SAFE_GRADLE_TASK_PREFIXES.iter().any(|p| name.starts_with(p)) => Judgment::Safe,
+            "publishToMavenLocal" => Judgment::Ask("gradle publishToMavenLocal installs artifacts into ~/.m2".to_string()),
+            _ if name.starts_with("publish") || name == "uploadArchives" => {
+                Judgment::Ask(format!("gradle {} uploads artifacts to a remote repository", name))
+            }
+            _ => Judgment::Unknown,
+        }
+    }))
+}
+
+fn judge_maven(args: &[&str]) -> Judgment {
+    if args.iter().any(|a| matches!(*a, "-v" | "--version" | "-h" | "--help")) {
+        return Judgment::Safe;
+    }
+    let goals = operands(args, MAVEN_VALUE_FLAGS);
+    if goals.is_empty() {
+        return Judgment::Unknown;
+    }
+    strictest(goals.iter().map(|goal| match *goal {
+        _ if SAFE_MAVEN_PHASES.contains(goal) => Judgment::Safe,
+        "install" | "install:install" | "install:install-file" => Judgment::Ask("mvn install copies artifacts into ~/.m2".to_string()),
+        "deploy" | "deploy:deploy" | "deploy:deploy-file" | "site-deploy" | "site:deploy" | "release:perform" => {
+            Judgment::Ask(format!("mvn {} uploads artifacts to a remote repository", goal))
+        }
+        "release:prepare" => Judgment::Ask("mvn release:prepare commits and tags a release".to_string()),
+        // Runs a main class from the project, like `spring-boot:run`; `exec:exec` runs any program
+        "exec:java" => Judgment::Safe,
+        // `plugin:goal`, or `group:artifact:version:goal`
+        _ => match goal.split(':').collect::<Vec<_>>().as_slice() {
+            [plugin, _] if SAFE_MAVEN_PLUGINS.contains(&plugin.trim_end_matches("-maven-plugin")) => Judgment::Safe,
+            _ => Judgment::Unknown,
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn judge_command(command: &str) -> Judgment {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        judge(tokens[0], &tokens[1..])
+    }
+
+    #[test]
+    fn test_gradle_tasks() {
+        for command in ["gradle test", "./gradlew clean build -x lint --stacktrace", "../gradlew :app:assembleDebug", "gradlew -p core check", "gradle --version", "./gradlew publish --dry-run"] {
+            assert!(is_jvm_tool(command.split(' ').next().unwrap()), "{}", command);
+            assert_eq!(judge_command(command), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_command("./gradlew build publish"),
+            Judgment::Ask("gradle publish uploads artifacts to a remote repository".to_string())
+        );
+        assert_eq!(
+            judge_command("gradle :lib:publishAllPublicationsToSonatypeRepository"),
+            Judgment::Ask("gradle publishAllPublicationsToSonatypeRepository uploads artifacts to a remote repository".to_string())
+        );
+        assert_eq!(judge_command("gradle publishToMavenLocal"), Judgment::Ask("gradle publishToMavenLocal installs artifacts into ~/.m2".to_string()));
+        assert_eq!(judge_command("./gradlew"), Judgment::Unknown);
+        assert_eq!(judge_command("gradle wrapper --gradle-version 8.5"), Judgment::Unknown);
+        assert!(!is_jvm_tool("bin/gradle"));
+    }
+
+    #[test]
+    fn test_maven_goals() {
+        for command in ["mvn verify", "./mvnw -B -pl core -am clean test", "mvn dependency:tree -Dverbose", "mvn -f sub/pom.xml package", "mvn spring-boot:run"] {
+            assert_eq!(judge_command(command), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_command("mvn clean deploy -DskipTests"),
+            Judgment::Ask("mvn deploy uploads artifacts to a remote repository".to_string())
+        );
+        assert_eq!(judge_command("./mvnw install"), Judgment::Ask("mvn install copies artifacts into ~/.m2".to_string()));
+        assert_eq!(judge_command("mvn versions:set -DnewVersion=2.0"), Judgment::Unknown);
+        assert_eq!(judge_command("mvn compile exec:java -Dexec.mainClass=app.Main"), Judgment::Safe);
+        assert_eq!(judge_command("mvn exec:exec -Dexec.executable=sh"), Judgment::Unknown);
+        assert_eq!(judge_command("mvn org.codehaus.mojo:exec-maven-plugin:3.1.0:exec"), Judgment::Unknown);
+    }
+}
@@ -0,0 +1,378 @@
+//! Kubernetes tooling: `kubectl` and `helm`. Reads are allowed and changes ask, judged
+//! against the context the command runs in: `--context`/`--kube-context`, or the current
+//! context of `--kubeconfig`, `$KUBECONFIG` or `~/.kube/config`. In a protected context
+//! (`prod*` by default) reads ask and changes are denied. Reading secrets asks, and printing
+//! their values is denied. `kubectl exec` is judged by the command it runs in the pod.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::Judgment;
+use crate::config::Config;
+use crate::paths;
+use crate::rules;
+
+/// Context name patterns that are protected unless configured otherwise
+const DEFAULT_PROTECTED_CONTEXTS: &[&str] = &["prod*", "*-prod", "*-prod-*", "*/prod*", "*production*"];
+
+/// kubectl flags that take a value (`-p` only for `patch`, where it isn't `--previous`)
+const KUBECTL_VALUE_FLAGS: &[&str] = &[
+    "-n", "--namespace", "--context", "--kubeconfig", "--cluster", "--user", "-o", "--output", "-l",
+    "--selector", "-f", "--filename", "-c", "--container", "--as", "--as-group", "-s", "--server",
+    "--token", "--field-selector", "--sort-by", "--template", "--since", "--since-time", "--tail",
+    "--type", "--timeout", "--for", "-k", "--kustomize", "--replicas", "--image", "--port",
+    "--field-manager", "--subresource", "--grace-period", "--dry-run", "--request-timeout",
+    "--cache-dir", "--certificate-authority", "--client-certificate", "--client-key", "--to-revision",
+    "-L", "--label-columns", "--chunk-size", "--revision", "--pod-running-timeout",
+];
+
+/// kubectl verbs that only read from the cluster
+const KUBECTL_READS: &[&str] = &[
+    "get", "describe", "logs", "top", "explain", "api-resources", "api-versions", "cluster-info",
+    "version", "events", "wait", "diff", "auth",
+];
+
+/// kubectl verbs that don't talk to the cluster
+const KUBECTL_LOCAL: &[&str] = &["kustomize", "completion", "plugin", "help", "options"];
+
+/// kubectl verbs that change resources in the cluster
+const KUBECTL_WRITES: &[&str] = &[
+    "apply", "create", "delete", "replace", "patch", "edit", "scale", "autoscale", "set", "label",
+    "annotate", "expose", "run", "drain", "cordon", "uncordon", "taint", "cp", "certificate",
+];
+
+/// Output formats that print a resource's full contents, secret values included
+const FULL_OUTPUTS: &[&str] = &["yaml", "json", "jsonpath", "go-template", "template", "custom-columns"];
+
+/// helm flags that take a value
+const HELM_VALUE_FLAGS: &[&str] = &[
+    "-n", "--namespace", "--kube-context", "--kubeconfig", "-f", "--values", "--set", "--set-string",
+    "--set-file", "--set-json", "--version", "-o", "--output", "--repo", "--timeout", "--description",
+    "--post-renderer", "--registry-config", "--repository-config", "--repository-cache", "--revision",
+    "--max", "-l", "--selector", "--kube-apiserver", "--kube-token", "--kube-as-user", "--burst-limit",
+];
+
+/// helm commands that work on local charts and repositories only
+const HELM_LOCAL: &[&str] = &[
+    "template", "lint", "show", "inspect", "search", "repo", "dependency", "dep", "package", "create",
+    "version", "env", "plugin", "completion", "pull", "fetch", "verify", "help",
+];
+
+/// helm commands that only read releases from the cluster
+const HELM_READS: &[&str] = &["list", "ls", "status", "get", "history", "hist", "diff"];
+
+/// helm commands that install, change or remove releases
+const HELM_WRITES: &[&str] = &["install", "upgrade", "uninstall", "delete", "del", "un", "rollback", "test"];
+
+/// Which kube contexts get the stricter rules
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct KubernetesPolicy {
+    /// Context name patterns (`*`, `?`) where reads ask and changes are denied; replaces
+    /// the built-in patterns when set
+    pub protected_contexts: Option<Vec<String>>,
+}
+
+impl KubernetesPolicy {
+    fn is_protected(&self, context: &str) -> bool {
+        match &self.protected_contexts {
+            Some(patterns) => patterns.iter().any(|pattern| paths::glob_match(pattern, context)),
+            None => DEFAULT_PROTECTED_CONTEXTS.iter().any(|pattern| paths::glob_match(pattern, context)),
+        }
+    }
+}
+
+/// Check if a program is modelled here
+pub fn is_kube_tool(program: &str) -> bool {
+    matches!(program, "kubectl" | "helm")
+}
+
+/// What a command does to the cluster
+#[derive(Debug, PartialEq, Eq)]
+enum Access {
+    /// Doesn't touch the cluster
+    Local,
+    Read,
+    /// Changes the cluster; the description names what
+    Write(String),
+}
+
+/// Judge a kubectl or helm command segment, which may start with environment assignments
+pub fn judge(segment: &str, current_dir: &Path, config: &Config) -> Judgment {
+    let words = paths::shell_words(segment);
+    let start = words.iter().position(|w| !rules::is_env_assignment(w)).unwrap_or(words.len());
+    let env: Vec<(&str, &str)> = words[..start].iter().filter_map(|w| w.split_once('=')).collect();
+    let lookup = |name: &str| env.iter().rev().find(|(n, _)| *n == name).map(|(_, v)| v.to_string()).or_else(|| std::env::var(name).ok());
+    let Some((program, args)) = words[start..].split_first() else {
+        return Judgment::Unknown;
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let parsed = if program == "helm" { parse(&args, HELM_VALUE_FLAGS, None) } else { parse(&args, KUBECTL_VALUE_FLAGS, Some("patch")) };
+    let Some(&verb) = parsed.positionals.first() else {
+        return if parsed.has(&["--help", "-h", "--version"]) { Judgment::Safe } else { Judgment::Unknown };
+    };
+    let access = if program == "helm" { helm_access(verb, &parsed) } else { kubectl_access(verb, &parsed) };
+    let access = match access {
+        Ok(access) => access,
+        Err(judgment) => return judgment,
+    };
+    if access == Access::Local {
+        return Judgment::Safe;
+    }
+
+    let context_flag = if program == "helm" { "--kube-context" } else { "--context" };
+    let context = parsed.value(context_flag).map(str::to_string).or_else(|| current_context(parsed.value("--kubeconfig"), current_dir, &lookup));
+    let display = format!("{} {}", program, verb);
+    let protected = context.as_deref().filter(|c| config.kubernetes.is_protected(c));
+    match (access, protected) {
+        (Access::Write(what), Some(context)) => Judgment::Deny(format!("{} {} in `{}`, a protected context", display, what, context)),
+        (Access::Write(what), None) => match &context {
+            Some(context) => Judgment::Ask(format!("{} {} in context `{}`", display, what, context)),
+            None => Judgment::Ask(format!("{} {}", display, what)),
+        },
+        (_, Some(context)) => Judgment::Ask(format!("{} runs against `{}`, a protected context", display, context)),
+        (_, None) if verb == "exec" => exec_payload(&parsed),
+        (_, None) => Judgment::Safe,
+    }
+}
+
+/// A kubectl or helm command line, with flags separated from positional arguments
+struct Parsed<'a> {
+    flags: Vec<(&'a str, Option<&'a str>)>,
+    positionals: Vec<&'a str>,
+    /// Everything after `--`
+    trailing: &'a [&'a str],
+}
+
+impl<'a> Parsed<'a> {
+    fn has(&self, names: &[&str]) -> bool {
+        self.flags.iter().any(|(flag, _)| names.contains(flag))
+    }
+
+    fn value(&self, name: &str) -> Option<&'a str> {
+        self.flags.iter().rev().find(|(flag, _)| *flag == name).and_then(|(_, value)| *value)
+    }
+}
+
+/// Split flags (which may come anywhere) from positionals; `value_verb` is the one verb
+/// for which `-p` takes a value
+fn parse<'a>(args: &'a [&'a str], value_flags: &[&str], value_verb: Option<&str>) -> Parsed<'a> {
+    let mut parsed = Parsed { flags: Vec::new(), positionals: Vec::new(), trailing: &[] };
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        if arg == "--" {
+            parsed.trailing = &args[i..];
+            break;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            parsed.positionals.push(arg);
+            continue;
+        }
+        let takes_value = value_flags.contains(&arg) || (arg == "-p" && parsed.positionals.first() == value_verb.as_ref());
+        match arg.split_once('=') {
+            Some((flag, value)) => parsed.flags.push((flag, Some(value))),
+            None if takes_value => {
+                parsed.flags.push((arg, args.get(i).copied()));
+                i += 1;
+            }
+            // `-oyaml`, `-nkube-system`
+            None if arg.len() > 2 && !arg.starts_with("--") && value_flags.contains(&&arg[..2]) => parsed.flags.push((&arg[..2], Some(&arg[2..]))),
+            None => parsed.flags.push((arg, None)),
+        }
+    }
+    parsed
+}
+
+/// Whether a change is only a dry run
+fn is_dry_run(parsed: &Parsed) -> bool {
+    parsed.flags.iter().any(|(flag, value)| *flag == "--dry-run" && value.is_none_or(|v| v != "none"))
+}
+
+fn kubectl_access(verb: &str, parsed: &Parsed) -> Result<Access, Judgment> {
+    let object = parsed.positionals.get(1).copied().unwrap_or_default();
+    let output = parsed.value("-o").or(parsed.value("--output")).unwrap_or_default();
+    Ok(match verb {
+        // `--template` implies `-o go-template`
+        "get" if is_secret_resource(object) && parsed.has(&["--template"]) => {
+            return Err(Judgment::Deny(format!("kubectl get {} --template prints secret values", object)));
+        }
+        "get" if is_secret_resource(object) && FULL_OUTPUTS.iter().any(|f| output.starts_with(f)) => {
+            return Err(Judgment::Deny(format!("kubectl get {} -o {} prints secret values", object, output)));
+        }
+        "get" | "describe" if is_secret_resource(object) => return Err(Judgment::Ask(format!("kubectl {} {} reads secrets", verb, object))),
+        "config" => match object {
+            "view" if parsed.has(&["--raw"]) => return Err(Judgment::Deny("kubectl config view --raw prints cluster credentials".to_string())),
+            "view" | "current-context" | "get-contexts" | "get-clusters" | "get-users" => Access::Local,
+            "" => return Err(Judgment::Unknown),
+            _ => return Err(Judgment::Ask(format!("kubectl config {} changes the kubeconfig", object))),
+        },
+        "version" if parsed.has(&["--client"]) => Access::Local,
+        "rollout" => match object {
+            "status" | "history" => Access::Read,
+            _ => Access::Write(format!("{} changes a rollout", object)),
+        },
+        "exec" => Access::Read,
+        _ if KUBECTL_LOCAL.contains(&verb) => Access::Local,
+        _ if KUBECTL_READS.contains(&verb) => Access::Read,
+        _ if KUBECTL_WRITES.contains(&verb) && is_dry_run(parsed) => Access::Read,
+        "delete" | "drain" => Access::Write("deletes resources".to_string()),
+        _ if KUBECTL_WRITES.contains(&verb) => Access::Write("changes resources".to_string()),
+        // attach, port-forward, proxy, debug: interactive or long-running
+        _ => return Err(Judgment::Unknown),
+    })
+}
+
+/// `secret`, `secrets`, `secret/name`, `secrets.v1`, or a comma list including one
+fn is_secret_resource(object: &str) -> bool {
+    object.split(',').any(|kind| {
+        let kind = kind.split('/').next().unwrap_or_default();
+        matches!(kind.split('.').next(), Some("secret" | "secrets"))
+    })
+}
+
+fn helm_access(verb: &str, parsed: &Parsed) -> Result<Access, Judgment> {
+    Ok(match verb {
+        _ if HELM_LOCAL.contains(&verb) => Access::Local,
+        _ if HELM_READS.contains(&verb) => Access::Read,
+        _ if HELM_WRITES.contains(&verb) && is_dry_run(parsed) => Access::Read,
+        "uninstall" | "delete" | "del" | "un" => Access::Write("removes a release".to_string()),
+        _ if HELM_WRITES.contains(&verb) => Access::Write("changes a release".to_string()),
+        _ => return Err(Judgment::Unknown),
+    })
+}
+
+/// `kubectl exec POD -- CMD...`: judge the command run in the pod
+fn exec_payload(parsed: &Parsed) -> Judgment {
+    let Some(pod) = parsed.positionals.get(1) else {
+        return Judgment::Unknown;
+    };
+    // The command follows `--`; the old `exec POD CMD` form has it among the positionals
+    let command = if parsed.trailing.is_empty() { &parsed.positionals[2..] } else { parsed.trailing };
+    if command.is_empty() {
+        return Judgment::Unknown;
+    }
+    let command = command.iter().map(|w| paths::shell_quote(w)).collect::<Vec<_>>().join(" ");
+    Judgment::Remote { label: format!("kubectl exec `{}`", pod), command, ask: None }
+}
+
+/// The current context of the kubeconfig: `--kubeconfig`, else the first file in
+/// `$KUBECONFIG` that sets one, else `~/.kube/config`
+fn current_context(flag: Option<&str>, current_dir: &Path, lookup: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    let cwd = current_dir.to_string_lossy();
+    let files: Vec<String> = match (flag, lookup("KUBECONFIG").filter(|v| !v.is_empty())) {
+        (Some(file), _) => vec![file.to_string()],
+        (None, Some(list)) => list.split(':').map(str::to_string).collect(),
+        (None, None) => vec![format!("{}/.kube/config", paths::home_dir())],
+    };
+    files.iter().find_map(|file| {
+        let text = std::fs::read_to_string(paths::normalize_path(&paths::expand_tilde(file), &cwd)).ok()?;
+        // A top-level key; reading the line avoids parsing the certificates around it
+        let line = text.lines().find_map(|line| line.strip_prefix("current-context:"))?;
+        Some(paths::strip_quotes(line.trim()).to_string()).filter(|c| !c.is_empty())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn kubeconfig(dir: &TempDir, name: &str, context: &str) -> String {
+        let contents = format!("apiVersion: v1\nkind: Config\ncontexts:\n- name: {0}\n  context:\n    cluster: {0}\ncurrent-context: {0}\n", context);
+        dir.write_file(name, &contents).to_string_lossy().to_string()
+    }
+
+    fn judge_with(command: &str, dir: &TempDir) -> Judgment {
+        judge(command, dir.path(), &Config::default())
+    }
+
+    #[test]
+    fn test_kubectl_verbs() {
+        let dir = TempDir::new("kube_verbs");
+        let config = kubeconfig(&dir, "dev", "kind-dev");
+        for command in [
+            "kubectl get pods -A -o wide", "kubectl describe deploy/web -n app", "kubectl logs -p web-1",
+            "kubectl top nodes", "kubectl rollout status deploy/web",
+            "kubectl apply -f k8s/ --dry-run=server", "kubectl config get-contexts", "kubectl kustomize overlays/dev",
+        ] {
+            let command = format!("KUBECONFIG={} {}", config, command);
+            assert_eq!(judge_with(&command, &dir), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_with(&format!("KUBECONFIG={} kubectl apply -f k8s/", config), &dir),
+            Judgment::Ask("kubectl apply changes resources in context `kind-dev`".to_string())
+        );
+        assert_eq!(
+            judge_with("kubectl --context staging rollout restart deploy/web", &dir),
+            Judgment::Ask("kubectl rollout restart changes a rollout in context `staging`".to_string())
+        );
+        assert_eq!(
+            judge_with("kubectl --context staging get secret db -oyaml", &dir),
+            Judgment::Deny("kubectl get secret -o yaml prints secret values".to_string())
+        );
+        assert_eq!(judge_with("kubectl get secrets", &dir), Judgment::Ask("kubectl get secrets reads secrets".to_string()));
+        assert_eq!(judge_with("kubectl describe secret/db -n app", &dir), Judgment::Ask("kubectl describe secret/db reads secrets".to_string()));
+        assert_eq!(
+            judge_with("kubectl get secret db --template={{.data.password}}", &dir),
+            Judgment::Deny("kubectl get secret --template prints secret values".to_string())
+        );
+        assert_eq!(
+            judge_with("kubectl get secret db -o jsonpath={.data.password}", &dir),
+            Judgment::Deny("kubectl get secret -o jsonpath={.data.password} prints secret values".to_string())
+        );
+        assert_eq!(
+            judge_with("kubectl config use-context prod", &dir),
+            Judgment::Ask("kubectl config use-context changes the kubeconfig".to_string())
+        );
+        assert_eq!(judge_with("kubectl --context staging port-forward svc/web 8080:80", &dir), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_protected_contexts() {
+        let dir = TempDir::new("kube_protected");
+        let config = kubeconfig(&dir, "config", "prod-eu");
+        assert_eq!(
+            judge_with(&format!("KUBECONFIG=/missing:{} kubectl get pods", config), &dir),
+            Judgment::Ask("kubectl get runs against `prod-eu`, a protected context".to_string())
+        );
+        assert_eq!(
+            judge_with(&format!("kubectl --kubeconfig {} delete pod web-1", config), &dir),
+            Judgment::Deny("kubectl delete deletes resources in `prod-eu`, a protected context".to_string())
+        );
+        assert_eq!(
+            judge_with("helm upgrade web ./chart --kube-context gke_acme_us-east1_app-prod", &dir),
+            Judgment::Deny("helm upgrade changes a release in `gke_acme_us-east1_app-prod`, a protected context".to_string())
+        );
+        assert_eq!(
+            judge_with("kubectl --context prod-eu exec web-1 -- ls", &dir),
+            Judgment::Ask("kubectl exec runs against `prod-eu`, a protected context".to_string())
+        );
+        // Local commands don't depend on the context
+        assert_eq!(judge_with(&format!("KUBECONFIG={} helm template web ./chart", config), &dir), Judgment::Safe);
+
+        let custom: Config = serde_json::from_str(r#"{"kubernetes": {"protected_contexts": ["live-*"]}}"#).unwrap();
+        assert_eq!(judge("kubectl --context prod-eu get pods", dir.path(), &custom), Judgment::Safe);
+        assert_eq!(
+            judge("kubectl --context live-1 get pods", dir.path(), &custom),
+            Judgment::Ask("kubectl get runs against `live-1`, a protected context".to_string())
+        );
+    }
+
+    #[test]
+    fn test_helm_and_exec() {
+        let dir = TempDir::new("kube_helm");
+        let config = kubeconfig(&dir, "config", "minikube");
+        let with_config = |command: &str| judge_with(&format!("KUBECONFIG={} {}", config, command), &dir);
+        for command in ["helm list -A", "helm status web", "helm lint ./chart", "helm install web ./chart --dry-run", "helm repo update"] {
+            assert_eq!(with_config(command), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(with_config("helm uninstall web"), Judgment::Ask("helm uninstall removes a release in context `minikube`".to_string()));
+        assert_eq!(
+            with_config("kubectl exec -it -n app web-1 -c app -- sh -c 'ls /data'"),
+            Judgment::Remote { label: "kubectl exec `web-1`".to_string(), command: "sh -c 'ls /data'".to_string(), ask: None }
+        );
+        assert_eq!(with_config("kubectl exec -it web-1"), Judgment::Unknown);
+    }
+}
@@ -0,0 +1,83 @@
+//! mix, Elixir's build tool: tasks (and each task of `mix do`) are judged by name.
+
+use std::path::Path;
+
+use super::{strictest, Judgment};
+
+/// Tasks that compile, test, run, lint, document or report on the project, and build
+/// escripts and releases
+const SAFE_MIX_TASKS: &[&str] = &[
+    "test", "compile", "format", "credo", "dialyzer", "docs", "xref", "help", "deps", "deps.tree",
+    "deps.compile", "deps.loadpaths", "deps.unlock", "clean", "escript.build", "release", "run",
+    "app.tree", "phx.routes", "hex.info", "hex.outdated", "hex.audit", "hex.build", "sobelow",
+    "coveralls", "coveralls.html",
+];
+
+/// Judge `mix <task> [args]` or `mix do task1 args, task2 args`
+pub fn judge(args: &[&str], current_dir: &Path) -> Judgment {
+    match args.split_first() {
+        None => Judgment::Unknown,
+        Some((&"do", rest)) => {
+            // Tasks are separated by `,` (or `+` since Elixir 1.14)
+            let joined = rest.join(" ");
+            strictest(joined.split([',', '+']).map(|task| {
+                let words: Vec<&str> = task.split_whitespace().collect();
+                judge_task(&words, current_dir)
+            }))
+        }
+        Some(_) => judge_task(args, current_dir),
+    }
+}
+
+fn judge_task(words: &[&str], current_dir: &Path) -> Judgment {
+    let Some((&task, args)) = words.split_first() else {
+        return Judgment::Safe;
+    };
+    let dry_run = args.contains(&"--dry-run");
+    match task {
+        "-v" | "--version" | "-h" | "--help" => Judgment::Safe,
+        // With a lockfile, deps.get only fetches what mix.lock pins
+        "deps.get" if current_dir.ancestors().find(|d| d.join("mix.exs").is_file()).is_some_and(|root| root.join("mix.lock").is_file()) => Judgment::Safe,
+        _ if SAFE_MIX_TASKS.contains(&task) => Judgment::Safe,
+        "hex.publish" if dry_run => Judgment::Safe,
+        "hex.publish" | "hex.retire" => Judgment::Ask(format!("mix {} changes what's published on hex.pm, which can't be undone", task)),
+        "hex.owner" if args.first().is_some_and(|a| matches!(*a, "add" | "remove" | "transfer")) => {
+            Judgment::Ask("mix hex.owner changes who can publish the package".to_string())
+        }
+        "hex.owner" => Judgment::Safe,
+        "hex.user" | "hex.organization" => Judgment::Ask(format!("mix {} changes the stored hex.pm credentials", task)),
+        "archive.install" | "escript.install" | "local.hex" | "local.rebar" | "local.phx" => {
+            Judgment::Ask(format!("mix {} installs into the user's mix home", task))
+        }
+        _ => Judgment::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    #[test]
+    fn test_mix_tasks() {
+        let dir = TempDir::new("mix_tasks");
+        dir.write_file("mix.exs", "defmodule App.MixProject do\nend\n");
+        for command in ["mix test --cover", "mix do compile --warnings-as-errors, test", "mix format --check-formatted", "mix hex.publish --dry-run"] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_in("mix deps.get", dir.path()), Judgment::Unknown);
+        dir.create_file("mix.lock");
+        assert_eq!(judge_in("mix deps.get", dir.path()), Judgment::Safe);
+        assert_eq!(
+            judge_in("mix do test + hex.publish --yes", dir.path()),
+            Judgment::Ask("mix hex.publish changes what's published on hex.pm, which can't be undone".to_string())
+        );
+        assert_eq!(
+            judge_in("mix archive.install hex phx_new", dir.path()),
+            Judgment::Ask("mix archive.install installs into the user's mix home".to_string())
+        );
+        assert_eq!(judge_in("mix ecto.reset", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("mix ", dir.path()), Judgment::Unknown);
+    }
+}
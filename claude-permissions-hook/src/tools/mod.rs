@@ -0,0 +1,199 @@
+//! Argv models for tools whose safety depends on the subcommand and flags rather than
+//! the program name: each model parses the tool's command line the way the tool does
+//! and judges the result.
+//!
+//! Build tools' `run` commands are allowed wherever `test` is: both execute the
+//! project's own code, so neither trusts it more than the other.
+
+pub mod cargo;
+pub mod cloud;
+pub mod container;
+pub mod db_client;
+pub mod dotnet;
+pub mod go;
+pub mod iac;
+pub mod jvm;
+pub mod kube;
+pub mod mix;
+pub mod processes;
+pub mod python;
+pub mod remote;
+pub mod ruby;
+pub mod swift;
+pub mod system_packages;
+
+use std::path::Path;
+
+use crate::config::Config;
+use crate::package_policy::{self, PackagePolicy, Verdict};
+use crate::package_scripts::ScriptCommand;
+use crate::paths;
+use crate::rules;
+
+/// A tool model's judgment of one command
+#[derive(Debug, PartialEq, Eq)]
+pub enum Judgment {
+    /// Safe to run; still risk-scored like any other safe command
+    Safe,
+    Ask(String),
+    Deny(String),
+    /// Nothing the model can vouch for either way
+    Unknown,
+    /// Judge `command` instead: the payload of a wrapper like `uv run`, labelled for reasons
+    Nested { label: String, command: String },
+    /// Like `Nested`, but `command` runs on another machine or in a container, where local
+    /// paths mean nothing; `ask` is the reason to ask even if the command itself is fine
+    Remote { label: String, command: String, ask: Option<String> },
+    /// Judge each of these commands, like a script's body (`go generate` directives)
+    Commands(Vec<ScriptCommand>),
+}
+
+/// Judge a command with the model for its program; `None` if there's no model for it.
+/// `segment` is the unsplit command, for models that need its quoting (SQL), and
+/// `tokens` its words with quotes stripped.
+pub fn judge(segment: &str, tokens: &[&str], current_dir: &Path, config: &Config) -> Option<Judgment> {
+    let unwrapped = rules::strip_wrappers(tokens);
+    if !may_have_model(unwrapped.first()?) {
+        return None;
+    }
+    // These commonly run with `PGPASSWORD=...`, `KUBECONFIG=...`, `AWS_PROFILE=...` and the like in front
+    match tokens.iter().find(|t| !rules::is_env_assignment(t)) {
+        Some(p) if db_client::is_db_client(p) => return Some(db_client::judge(segment, current_dir, config)),
+        Some(p) if kube::is_kube_tool(p) => return Some(kube::judge(segment, current_dir, config)),
+        Some(p) if iac::is_iac_tool(p) => return Some(iac::judge(segment, current_dir, config)),
+        Some(p) if cloud::is_cloud_tool(p) => return Some(cloud::judge(segment, current_dir, config)),
+        Some(p) if remote::is_remote_tool(p) => return Some(remote::judge(segment, current_dir, config)),
+        _ => {}
+    }
+    // System package managers and process and service control usually run under `sudo`
+    if let Some((&program, args)) = unwrapped.split_first() {
+        if system_packages::is_system_package_manager(program) {
+            return Some(system_packages::judge(program, args, &config.system_packages));
+        }
+        if processes::is_process_tool(program) {
+            return Some(processes::judge(program, args));
+        }
+    }
+    let (&program, args) = tokens.split_first()?;
+    match program {
+        "cargo" => Some(cargo::judge(args, current_dir)),
+        "go" => Some(go::judge(args, current_dir)),
+        "mix" => Some(mix::judge(args, current_dir)),
+        "swift" => Some(swift::judge(args, current_dir)),
+        "dotnet" => Some(dotnet::judge(args, current_dir)),
+        _ if jvm::is_jvm_tool(program) => Some(jvm::judge(program, args)),
+        _ if ruby::is_ruby_tool(program) => Some(ruby::judge(program, args, current_dir)),
+        _ if python::is_python_tool(program) => Some(python::judge(program, args, current_dir, config)),
+        _ if container::is_container_tool(program) => Some(container::judge(program, args, current_dir)),
+        _ => None,
+    }
+}
+
+/// Programs with a model, sorted for `binary_search`; anything else is skipped without
+/// asking each model. Paths (`./gradlew`, `bin/rails`, `.venv/bin/pytest`) and versioned
+/// interpreters (`python3.12`) are left to the models to recognize.
+const PROGRAMS: &[&str] = &[
+    "apt", "apt-cache", "apt-get", "aws", "az", "brew", "bundle", "cargo", "cdk", "cdktf",
+    "crontab", "dnf", "docker", "docker-compose", "dotnet", "duckdb", "fly", "flyctl", "gcloud",
+    "gem", "go", "gradle", "gradlew", "hatch", "helm", "kill", "killall", "kubectl", "launchctl", "mariadb",
+    "mix", "mvn", "mvnw", "mysql", "nerdctl", "pacman", "paru", "pdm", "pip", "pip3", "pipx", "pkill",
+    "podman", "podman-compose", "poetry", "port", "psql", "pulumi", "rails", "rake", "rspec",
+    "rsync", "scp", "sqlite3", "ssh", "swift", "systemctl", "terraform", "terragrunt", "tofu",
+    "uv", "wrangler", "yay", "yum",
+];
+
+/// Check whether some model might judge `program`
+fn may_have_model(program: &str) -> bool {
+    PROGRAMS.binary_search(&program).is_ok() || program.contains('/') || program.starts_with("python")
+}
+
+/// Judge `command` in `dir` with the default config, for the tool modules' tests
+#[cfg(test)]
+pub(crate) fn judge_in(command: &str, dir: &Path) -> Judgment {
+    let tokens: Vec<&str> = command.split_whitespace().map(paths::strip_quotes).collect();
+    judge(command, &tokens, dir, &Config::default()).unwrap_or_else(|| panic!("no tool model for `{}`", command))
+}
+
+/// Judge an install of the named packages against the package policy
+fn judge_packages(names: &[&str], popular: &[&str], policy: &PackagePolicy) -> Judgment {
+    match package_policy::check(names, popular, policy) {
+        Verdict::Allowed => Judgment::Safe,
+        Verdict::Denied(reason) => Judgment::Deny(reason),
+        Verdict::Suspicious(reason) => Judgment::Ask(reason),
+        Verdict::Unlisted => Judgment::Unknown,
+    }
+}
+
+/// The most restrictive of several judgments: deny, then ask, then unknown
+fn strictest(judgments: impl IntoIterator<Item = Judgment>) -> Judgment {
+    let rank = |j: &Judgment| match j {
+        Judgment::Deny(_) => 3,
+        Judgment::Ask(_) => 2,
+        Judgment::Safe => 0,
+        _ => 1,
+    };
+    judgments.into_iter().fold(Judgment::Safe, |worst, j| if rank(&j) > rank(&worst) { j } else { worst })
+}
+
+/// The arguments that aren't flags or the values of `value_flags`
+fn operands<'a>(args: &[&'a str], value_flags: &[&str]) -> Vec<&'a str> {
+    let mut operands = Vec::new();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        if value_flags.contains(&arg) {
+            i += 1;
+        } else if !arg.starts_with('-') {
+            operands.push(arg);
+        }
+    }
+    operands
+}
+
+/// The value of `--flag value` or `--flag=value` among `args`
+fn flag_value<'a>(args: &[&'a str], flag: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(idx, arg)| match arg.strip_prefix(flag) {
+        Some("") => args.get(idx + 1).copied(),
+        Some(value) => value.strip_prefix('='),
+        None => None,
+    })
+}
+
+/// Ask when a path flag points outside the project
+fn check_path_flag(args: &[&str], flag: &str, program: &str, current_dir: &Path) -> Option<Judgment> {
+    let value = flag_value(args, flag)?;
+    is_outside_repo(value, current_dir).then(|| Judgment::Ask(format!("{} {} `{}` is outside the repo", program, flag, value)))
+}
+
+/// Check if a path argument points outside the project
+fn is_outside_repo(value: &str, current_dir: &Path) -> bool {
+    let cwd = current_dir.to_string_lossy();
+    let path = paths::normalize_path(&paths::expand_tilde(value), &cwd);
+    let repo_root = paths::find_repo_root(current_dir);
+    paths::is_outside_project(&path, repo_root.as_deref(), &cwd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_programs_sorted_and_modelled() {
+        assert!(PROGRAMS.windows(2).all(|w| w[0] < w[1]));
+        let config = Config::default();
+        for program in PROGRAMS {
+            assert!(judge(program, &[program], Path::new("/tmp"), &config).is_some(), "{}", program);
+        }
+    }
+
+    #[test]
+    fn test_no_model() {
+        let config = Config::default();
+        for command in ["ls -la", "git status", "sudo echo hi", "FOO=1 grep bar"] {
+            let tokens: Vec<&str> = command.split_whitespace().collect();
+            assert_eq!(judge(command, &tokens, Path::new("/tmp"), &config), None, "{}", command);
+        }
+        let tokens = ["sudo", "apt", "install", "jq"];
+        assert!(judge("sudo apt install jq", &tokens, Path::new("/tmp"), &config).is_some());
+    }
+}
@@ -0,0 +1,453 @@
+//! Process and service control: `kill`, `pkill`, `killall`, `systemctl`, `launchctl` and
+//! `crontab`. Signals are resolved against the process table: signalling a process this
+//! session runs in (an ancestor of the hook, like the `claude` process) is denied, broad
+//! patterns ask, and so does signalling any other process that exists. Service managers
+//! and crontab are judged by verb.
+
+use std::process::Command;
+
+use super::{operands, Judgment};
+use crate::paths;
+
+/// systemctl verbs that only read
+const SYSTEMCTL_READS: &[&str] = &[
+    "status", "show", "cat", "list-units", "list-unit-files", "list-sockets", "list-timers", "list-jobs",
+    "list-dependencies", "list-machines", "list-paths", "list-automounts", "is-active", "is-enabled",
+    "is-failed", "is-system-running", "get-default", "show-environment", "help",
+];
+
+/// systemctl verbs that stop, restart or switch the whole system
+const SYSTEMCTL_POWER: &[&str] = &[
+    "poweroff", "reboot", "soft-reboot", "halt", "kexec", "suspend", "hibernate", "hybrid-sleep",
+    "suspend-then-hibernate", "rescue", "emergency", "default", "isolate", "exit", "switch-root",
+];
+
+/// systemctl flags that take a value
+const SYSTEMCTL_VALUE_FLAGS: &[&str] = &[
+    "-t", "--type", "-p", "--property", "-H", "--host", "-M", "--machine", "--state", "-n", "--lines",
+    "-o", "--output", "-s", "--signal", "--root", "--kill-whom", "--kill-value", "--job-mode", "--what",
+    "--when", "--timestamp", "--reboot-argument", "--image", "--preset-mode", "--message", "--drop-in",
+];
+
+/// launchctl subcommands that only read
+const LAUNCHCTL_READS: &[&str] = &[
+    "list", "print", "print-cache", "print-disabled", "print-token", "blame", "dumpstate", "dumpjpcategory",
+    "help", "version", "managerpid", "manageruid", "managername", "error", "hostinfo", "resolveport",
+    "examine", "getenv", "plist", "procinfo", "variant",
+];
+
+/// pkill flags that take a value
+const PKILL_VALUE_FLAGS: &[&str] = &[
+    "-u", "--euid", "-U", "--uid", "-g", "--pgroup", "-G", "--group", "-P", "--parent", "-s", "--session",
+    "-t", "--terminal", "-F", "--pidfile", "--signal", "--ns", "--nslist", "-J", "-M", "-N", "-q",
+];
+
+/// pkill and killall flags that pick processes without a name
+const SELECTOR_FLAGS: &[&str] = &["-u", "--euid", "-U", "--uid", "-g", "--pgroup", "-G", "--group", "-P", "--parent", "-s", "--session", "-t", "--terminal", "--user"];
+
+/// killall flags that take a value
+const KILLALL_VALUE_FLAGS: &[&str] = &["-s", "--signal", "-u", "--user", "-t", "-c", "-o", "--older-than", "-y", "--younger-than", "-n", "--ns"];
+
+/// A running process
+#[derive(Debug, Clone)]
+pub struct Process {
+    pub pid: i64,
+    pub ppid: i64,
+    /// The executable name, as `pkill` and `killall` match it
+    pub name: String,
+    /// The full command line, as `pkill -f` matches it
+    pub args: String,
+}
+
+/// The processes running now, and which of them this session runs in
+#[derive(Debug)]
+pub struct ProcessTable {
+    processes: Vec<Process>,
+    /// The hook itself, then its parent, grandparent, ...
+    ancestors: Vec<i64>,
+}
+
+impl ProcessTable {
+    /// Read the process table with `ps`, or `None` if it can't be read
+    fn load() -> Option<Self> {
+        let output = Command::new("ps").args(["-A", "-o", "pid=", "-o", "ppid=", "-o", "ucomm=", "-o", "args="]).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout), std::process::id() as i64)
+    }
+
+    /// A table from `ps` output, or `None` if the output doesn't list the hook itself
+    fn parse(output: &str, hook: i64) -> Option<Self> {
+        let processes: Vec<Process> = output.lines().filter_map(parse_ps_line).collect();
+        processes.iter().any(|p| p.pid == hook).then(|| Self::new(processes, hook))
+    }
+
+    fn new(processes: Vec<Process>, hook: i64) -> Self {
+        let mut ancestors = vec![hook];
+        while let Some(parent) = processes.iter().find(|p| Some(&p.pid) == ancestors.last()).map(|p| p.ppid) {
+            if parent <= 0 || ancestors.contains(&parent) {
+                break;
+            }
+            ancestors.push(parent);
+        }
+        ProcessTable { processes, ancestors }
+    }
+
+    fn get(&self, pid: i64) -> Option<&Process> {
+        self.processes.iter().find(|p| p.pid == pid)
+    }
+
+    fn is_ancestor(&self, pid: i64) -> bool {
+        self.ancestors.contains(&pid)
+    }
+
+    /// Processes a kill pattern could reach: not the hook or what it spawned (`ps`)
+    fn candidates(&self) -> impl Iterator<Item = &Process> {
+        let hook = self.ancestors[0];
+        self.processes.iter().filter(move |p| p.pid != hook && p.ppid != hook)
+    }
+}
+
+fn parse_ps_line(line: &str) -> Option<Process> {
+    let mut fields = line.split_whitespace();
+    let pid = fields.next()?.parse().ok()?;
+    let ppid = fields.next()?.parse().ok()?;
+    let name = fields.next()?.to_string();
+    let args = fields.collect::<Vec<_>>().join(" ");
+    Some(Process { pid, ppid, name, args })
+}
+
+/// Check if a program signals processes or controls services or scheduled jobs
+pub fn is_process_tool(program: &str) -> bool {
+    matches!(program, "kill" | "pkill" | "killall" | "systemctl" | "launchctl" | "crontab")
+}
+
+/// Judge a process or service control command; `sudo` and other wrappers are already stripped
+pub fn judge(program: &str, args: &[&str]) -> Judgment {
+    match program {
+        "systemctl" => systemctl(args),
+        "launchctl" => launchctl(args),
+        "crontab" => crontab(args),
+        // Without the process table, no signal target can be checked
+        _ => ProcessTable::load().map_or(Judgment::Unknown, |table| signal(program, args, &table)),
+    }
+}
+
+/// `kill`, `pkill` and `killall`, against `table`
+fn signal(program: &str, args: &[&str], table: &ProcessTable) -> Judgment {
+    match program {
+        "kill" => kill(args, table),
+        _ => kill_by_name(program, args, table),
+    }
+}
+
+/// `kill [-SIGNAL | -s SIGNAL] PID...`: negative PIDs are process groups, `%N` the
+/// shell's jobs
+fn kill(args: &[&str], table: &ProcessTable) -> Judgment {
+    let mut signal = None;
+    let mut targets = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        match arg {
+            "-l" | "-L" | "--list" | "--table" => return Judgment::Safe,
+            "-s" | "-n" | "--signal" => {
+                signal = args.get(i).copied();
+                i += 1;
+            }
+            "--" => {
+                targets.extend_from_slice(&args[i..]);
+                break;
+            }
+            // The signal comes first; later dashes are process groups
+            _ if arg.starts_with('-') && targets.is_empty() && signal.is_none() => signal = Some(&arg[1..]),
+            _ => targets.push(arg),
+        }
+    }
+    // Signal 0 only checks that the process exists
+    if matches!(signal, Some("0")) {
+        return Judgment::Safe;
+    }
+
+    let mut signalled = Vec::new();
+    let mut unresolved = false;
+    for target in targets {
+        if matches!(target, "$PPID" | "${PPID}") {
+            return Judgment::Deny("kill `$PPID` signals the process running this session".to_string());
+        }
+        if target.starts_with('%') {
+            continue;
+        }
+        let Ok(pid) = target.parse::<i64>() else {
+            unresolved = true;
+            continue;
+        };
+        match pid {
+            -1 => return Judgment::Deny("kill -1 signals every process you can reach".to_string()),
+            0 => return Judgment::Ask("kill 0 signals every process in the shell's process group".to_string()),
+            _ if pid < 0 => return Judgment::Ask(format!("kill signals process group {}", -pid)),
+            _ if table.is_ancestor(pid) => {
+                let name = table.get(pid).map(|p| p.name.as_str()).unwrap_or("?");
+                return Judgment::Deny(format!("kill signals PID {} (`{}`), a process this session runs in", pid, name));
+            }
+            _ => signalled.extend(table.get(pid)),
+        }
+    }
+    if unresolved {
+        return Judgment::Ask("kill targets PIDs computed at run time, which can't be checked ahead of time".to_string());
+    }
+    if signalled.is_empty() {
+        return Judgment::Safe;
+    }
+    Judgment::Ask(format!("kill signals {}", describe(&signalled)))
+}
+
+/// `pkill [flags] PATTERN` (a regular expression) and `killall [flags] NAME...`
+fn kill_by_name(program: &str, args: &[&str], table: &ProcessTable) -> Judgment {
+    let value_flags = if program == "pkill" { PKILL_VALUE_FLAGS } else { KILLALL_VALUE_FLAGS };
+    let mut flags = Vec::new();
+    let mut names = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        if arg == "--" {
+            names.extend_from_slice(&args[i..]);
+            break;
+        }
+        if !arg.starts_with('-') || arg.len() == 1 {
+            names.push(arg);
+            continue;
+        }
+        let flag = arg.split('=').next().unwrap_or(arg);
+        if value_flags.contains(&flag) && !arg.contains('=') {
+            // macOS `killall -c NAME` names the process with a flag
+            if flag == "-c" {
+                names.extend(args.get(i).copied());
+            }
+            i += 1;
+        }
+        flags.push(flag);
+    }
+    let has = |names: &[&str]| flags.iter().any(|f| names.contains(f));
+
+    if names.is_empty() {
+        return match flags.iter().find(|f| SELECTOR_FLAGS.contains(f)) {
+            Some(selector) => Judgment::Ask(format!("{} {} signals every process it selects", program, selector)),
+            None if program == "killall" => Judgment::Ask("killall without a process name signals every process it can".to_string()),
+            None => Judgment::Unknown,
+        };
+    }
+    let full = program == "pkill" && has(&["-f", "--full"]);
+    let exact = has(&["-x", "--exact", "-e"]);
+    let ignore_case = has(&["-i", "--ignore-case", "-I"]);
+    // killall matches names exactly unless told to use regular expressions
+    let regex = program == "pkill" || has(&["-m", "-r", "--regexp"]);
+    if has(&["-v", "--inverse"]) {
+        return Judgment::Ask(format!("{} -v signals every process that doesn't match", program));
+    }
+
+    let mut signalled = Vec::new();
+    let mut unresolved = None;
+    for name in &names {
+        let glob = if regex { pattern_glob(name, exact) } else { Some(name.to_string()) };
+        let Some(glob) = glob else {
+            unresolved.get_or_insert(*name);
+            continue;
+        };
+        if glob.chars().all(|c| c == '*' || c == '?') {
+            return Judgment::Ask(format!("{} `{}` matches every process", program, name));
+        }
+        let glob = if ignore_case { glob.to_lowercase() } else { glob };
+        for process in table.candidates() {
+            let text = if full { &process.args } else { &process.name };
+            let text = if ignore_case { text.to_lowercase() } else { text.to_string() };
+            // Process names are cut to 15 characters on Linux
+            let matched = paths::glob_match(&glob, &text) || (!regex && glob.len() > 15 && glob.starts_with(text.as_str()) && text.len() == 15);
+            if matched {
+                if table.is_ancestor(process.pid) {
+                    return Judgment::Deny(format!(
+                        "{} `{}` would signal PID {} (`{}`), a process this session runs in",
+                        program, name, process.pid, process.name
+                    ));
+                }
+                signalled.push(process);
+            }
+        }
+    }
+    if let Some(name) = unresolved {
+        return Judgment::Ask(format!("{} `{}` is a pattern that can't be checked ahead of time", program, name));
+    }
+    if signalled.is_empty() {
+        return Judgment::Safe;
+    }
+    Judgment::Ask(format!("{} `{}` signals {}", program, names.join(" "), describe(&signalled)))
+}
+
+/// A pkill pattern as a glob, if it's simple enough: literal text, `.` wildcards and
+/// `^`/`$` anchors
+fn pattern_glob(pattern: &str, exact: bool) -> Option<String> {
+    let start = exact || pattern.starts_with('^');
+    let end = exact || (pattern.ends_with('$') && pattern.len() > 1);
+    let literal = pattern.strip_prefix('^').unwrap_or(pattern);
+    let literal = if end { literal.strip_suffix('$').unwrap_or(literal) } else { literal };
+    if literal.contains(['[', ']', '(', ')', '{', '}', '|', '\\', '+', '?', '*', '$', '^']) && literal != ".*" {
+        return None;
+    }
+    let body = if literal == ".*" { "*".to_string() } else { literal.replace('.', "?") };
+    Some(format!("{}{}{}", if start { "" } else { "*" }, body, if end { "" } else { "*" }))
+}
+
+/// `` `node` (PID 4242), `node` (PID 4243) and 3 more ``
+fn describe(processes: &[&Process]) -> String {
+    let mut listed: Vec<String> = processes.iter().take(3).map(|p| format!("`{}` (PID {})", p.name, p.pid)).collect();
+    if processes.len() > 3 {
+        listed.push(format!("{} more", processes.len() - 3));
+    }
+    listed.join(", ")
+}
+
+fn systemctl(args: &[&str]) -> Judgment {
+    let operands = operands(args, SYSTEMCTL_VALUE_FLAGS);
+    let Some((&verb, units)) = operands.split_first() else {
+        // Plain `systemctl` lists units
+        return Judgment::Safe;
+    };
+    match verb {
+        _ if SYSTEMCTL_READS.contains(&verb) => Judgment::Safe,
+        _ if SYSTEMCTL_POWER.contains(&verb) => Judgment::Deny(format!("systemctl {} stops, restarts or switches the whole system", verb)),
+        _ => Judgment::Ask(with_names(format!("systemctl {} changes services", verb), units)),
+    }
+}
+
+fn launchctl(args: &[&str]) -> Judgment {
+    let operands = operands(args, &[]);
+    let Some((&verb, targets)) = operands.split_first() else {
+        return Judgment::Unknown;
+    };
+    match verb {
+        _ if LAUNCHCTL_READS.contains(&verb) => Judgment::Safe,
+        "reboot" => Judgment::Deny("launchctl reboot restarts the system".to_string()),
+        _ => Judgment::Ask(with_names(format!("launchctl {} changes launchd services", verb), targets)),
+    }
+}
+
+/// `crontab -l` lists jobs; `-e` edits them and `crontab FILE` replaces them all
+/// (`-r` is a catastrophic rule)
+fn crontab(args: &[&str]) -> Judgment {
+    let operands = operands(args, &["-u"]);
+    let has = |flag: char| args.iter().any(|a| a.starts_with('-') && !a.starts_with("--") && a.contains(flag));
+    match operands.first() {
+        _ if has('l') || has('T') => Judgment::Safe,
+        _ if has('e') => Judgment::Ask("crontab -e edits the scheduled jobs".to_string()),
+        None => Judgment::Ask("crontab replaces every scheduled job with its input".to_string()),
+        Some(file) => Judgment::Ask(format!("crontab replaces every scheduled job with `{}`", file)),
+    }
+}
+
+fn with_names(reason: String, names: &[&str]) -> String {
+    if names.is_empty() {
+        return reason;
+    }
+    let names = names.iter().map(|n| format!("`{}`", n)).collect::<Vec<_>>().join(", ");
+    format!("{} ({})", reason, names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// launchd → zsh → claude (node) → the hook, plus a dev server and postgres
+    fn table() -> ProcessTable {
+        let process = |pid, ppid, name: &str, args: &str| Process { pid, ppid, name: name.to_string(), args: args.to_string() };
+        ProcessTable::new(
+            vec![
+                process(1, 0, "launchd", "/sbin/launchd"),
+                process(500, 1, "zsh", "-zsh"),
+                process(600, 500, "node", "node /usr/local/bin/claude"),
+                process(700, 600, "claude-hook", "claude-hook bash"),
+                process(710, 700, "ps", "ps -A"),
+                process(800, 1, "node", "node server.js --port 3000"),
+                process(900, 1, "postgres", "postgres -D /usr/local/var/postgres"),
+            ],
+            700,
+        )
+    }
+
+    fn judge_signal(command: &str) -> Judgment {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        signal(tokens[0], &tokens[1..], &table())
+    }
+
+    fn judge_command(command: &str) -> Judgment {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        judge(tokens[0], &tokens[1..])
+    }
+
+    #[test]
+    fn test_kill() {
+        assert_eq!(judge_signal("kill -l"), Judgment::Safe);
+        assert_eq!(judge_signal("kill -0 800"), Judgment::Safe);
+        assert_eq!(judge_signal("kill 4242"), Judgment::Safe);
+        assert_eq!(judge_signal("kill -9 800"), Judgment::Ask("kill signals `node` (PID 800)".to_string()));
+        assert_eq!(
+            judge_signal("kill -s KILL 600"),
+            Judgment::Deny("kill signals PID 600 (`node`), a process this session runs in".to_string())
+        );
+        assert_eq!(judge_signal("kill -9 $PPID"), Judgment::Deny("kill `$PPID` signals the process running this session".to_string()));
+        assert_eq!(judge_signal("kill -9 -1"), Judgment::Deny("kill -1 signals every process you can reach".to_string()));
+        assert_eq!(judge_signal("kill -TERM -- -800"), Judgment::Ask("kill signals process group 800".to_string()));
+        assert_eq!(
+            judge_signal("kill $(lsof -ti:3000)"),
+            Judgment::Ask("kill targets PIDs computed at run time, which can't be checked ahead of time".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pkill_and_killall() {
+        assert_eq!(
+            judge_signal("pkill node"),
+            Judgment::Deny("pkill `node` would signal PID 600 (`node`), a process this session runs in".to_string())
+        );
+        assert_eq!(judge_signal("pkill -f server.js"), Judgment::Ask("pkill `server.js` signals `node` (PID 800)".to_string()));
+        assert_eq!(judge_signal("pkill -9 -x postgres"), Judgment::Ask("pkill `postgres` signals `postgres` (PID 900)".to_string()));
+        assert_eq!(judge_signal("pkill -x post"), Judgment::Safe);
+        assert_eq!(judge_signal("pkill -f claude-hook"), Judgment::Safe);
+        assert_eq!(judge_signal("pkill -f ."), Judgment::Ask("pkill `.` matches every process".to_string()));
+        assert_eq!(judge_signal("pkill -u alice"), Judgment::Ask("pkill -u signals every process it selects".to_string()));
+        assert_eq!(judge_signal("pkill -f vite|next"), Judgment::Ask("pkill `vite|next` is a pattern that can't be checked ahead of time".to_string()));
+
+        assert_eq!(judge_signal("killall zsh"), Judgment::Deny("killall `zsh` would signal PID 500 (`zsh`), a process this session runs in".to_string()));
+        assert_eq!(judge_signal("killall -9 postgres"), Judgment::Ask("killall `postgres` signals `postgres` (PID 900)".to_string()));
+        assert_eq!(judge_signal("killall post"), Judgment::Safe);
+        assert_eq!(judge_signal("killall"), Judgment::Ask("killall without a process name signals every process it can".to_string()));
+    }
+
+    #[test]
+    fn test_services_and_crontab() {
+        for command in ["systemctl", "systemctl status nginx", "systemctl --user list-units --type=service", "systemctl is-active -q nginx", "launchctl list", "launchctl print gui/501", "crontab -l", "crontab -u www -l"] {
+            assert_eq!(judge_command(command), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_command("systemctl restart nginx"), Judgment::Ask("systemctl restart changes services (`nginx`)".to_string()));
+        assert_eq!(judge_command("systemctl --user enable --now syncthing"), Judgment::Ask("systemctl enable changes services (`syncthing`)".to_string()));
+        assert_eq!(judge_command("systemctl reboot"), Judgment::Deny("systemctl reboot stops, restarts or switches the whole system".to_string()));
+        assert_eq!(
+            judge_command("launchctl bootout gui/501/com.example.agent"),
+            Judgment::Ask("launchctl bootout changes launchd services (`gui/501/com.example.agent`)".to_string())
+        );
+        assert_eq!(judge_command("crontab -e"), Judgment::Ask("crontab -e edits the scheduled jobs".to_string()));
+        assert_eq!(judge_command("crontab jobs.txt"), Judgment::Ask("crontab replaces every scheduled job with `jobs.txt`".to_string()));
+        assert_eq!(judge_command("crontab -"), Judgment::Ask("crontab replaces every scheduled job with its input".to_string()));
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let table = ProcessTable::parse("  1     0 launchd /sbin/launchd\n 600     1 node node claude\n 700   600 claude-hook claude-hook bash\n", 700);
+        assert_eq!(table.map(|t| t.ancestors), Some(vec![700, 600, 1]));
+        // Output that doesn't list the hook (or no output at all) isn't a process table
+        assert!(ProcessTable::parse(" 600     1 node node claude\n", 700).is_none());
+        assert!(ProcessTable::parse("", 700).is_none());
+    }
+}
@@ -0,0 +1,635 @@
+//! Python tooling: `uv`, `poetry`, `pdm`, `hatch`, `pip`/`pipx`, virtualenv binaries
+//! and `python` itself.
+//!
+//! Runners (`uv run`, `poetry run`, ...) are unwrapped to the command they run, installs
+//! go through the package policy, and `python script.py` is scanned (with the local
+//! modules it imports) for process, deletion and network calls. A scan can only find
+//! reasons to ask: a script that shows none still isn't vouched for.
+
+use std::path::{Path, PathBuf};
+
+use super::{judge_packages, strictest, Judgment};
+use crate::config::Config;
+use crate::package_policy::{self, Verdict, POPULAR_PYPI_PACKAGES};
+use crate::paths;
+use crate::toml::{self, Toml};
+
+/// Python tools with a model here, besides `python` and virtualenv binaries
+const PYTHON_TOOLS: &[&str] = &["uv", "poetry", "pdm", "hatch", "pip", "pip3", "pipx"];
+
+/// `python -m` modules that only test, type-check, lint or format
+const SAFE_PYTHON_MODULES: &[&str] = &[
+    "pytest", "mypy", "ruff", "black", "unittest", "flake8", "pylint", "venv",
+];
+
+/// Flags that only print information, before any subcommand
+const INFO_FLAGS: &[&str] = &["-V", "--version", "-h", "--help"];
+
+/// Global flags (before the subcommand) that take a separate value
+const GLOBAL_VALUE_FLAGS: &[&str] = &[
+    "--directory", "--project", "--config-file", "--cache-dir", "--color", "-C", "-P",
+    "--python", "-p", "--log", "--proxy", "--allow-insecure-host", "--cert",
+];
+
+/// `uv run`/`poetry run`/`pdm run`/`hatch run` flags that take a separate value
+const RUN_VALUE_FLAGS: &[&str] = &[
+    "--with", "--with-editable", "--with-requirements", "--python", "-p", "--package",
+    "--extra", "--group", "--only-group", "--no-group", "--env-file", "--directory",
+    "--project", "--index", "--default-index", "-e", "--venv",
+];
+
+/// pip subcommands that only report on the environment or the index
+const PIP_READ_ONLY_SUBCOMMANDS: &[&str] = &["list", "show", "freeze", "check", "help", "inspect", "debug", "tree", "index"];
+
+/// Install flags that take a separate value (besides requirement files)
+const INSTALL_VALUE_FLAGS: &[&str] = &[
+    "-c", "--constraint", "--constraints", "-t", "--target", "--prefix", "--root", "--python",
+    "-p", "--platform", "--python-version", "--implementation", "--abi", "--only-binary",
+    "--no-binary", "--progress-bar", "--src", "--upgrade-strategy", "-C", "--config-settings",
+    "--report", "--python-platform", "--extra", "-E", "--extras", "--group", "-G",
+    "--optional", "--tag", "--branch", "--rev", "--package", "--marker", "-m", "--bounds",
+    "--suffix", "--source",
+];
+
+/// Install flags that pull code from somewhere other than a named package on the
+/// default index: local checkouts, URLs, alternative indexes
+const INSTALL_SOURCE_FLAGS: &[&str] = &[
+    "-e", "--editable", "-i", "--index-url", "--extra-index-url", "-f", "--find-links",
+    "--index", "--default-index", "--path", "--git", "--url", "--spec",
+];
+
+/// Install flags naming a requirements file to read
+const REQUIREMENT_FILE_FLAGS: &[&str] = &["-r", "--requirement", "--requirements", "--with-requirements"];
+
+/// Substrings in a Python script and the capability they indicate
+const SCRIPT_CAPABILITIES: &[(&str, &str)] = &[
+    ("subprocess", "runs processes"), ("os.system", "runs processes"), ("os.popen", "runs processes"),
+    ("os.exec", "runs processes"), ("os.spawn", "runs processes"), ("pty.spawn", "runs processes"),
+    ("shutil.rmtree", "deletes files"), ("os.remove", "deletes files"), ("os.unlink", "deletes files"),
+    ("os.rmdir", "deletes files"), (".unlink(", "deletes files"), (".rmdir(", "deletes files"),
+    ("socket", "uses the network"), ("requests", "uses the network"), ("urllib", "uses the network"),
+    ("http.client", "uses the network"), ("httpx", "uses the network"), ("aiohttp", "uses the network"),
+    ("ftplib", "uses the network"), ("smtplib", "uses the network"), ("paramiko", "uses the network"),
+    ("eval(", "runs dynamic code"), ("exec(", "runs dynamic code"), ("__import__", "runs dynamic code"),
+    ("importlib", "runs dynamic code"), ("ctypes", "runs dynamic code"), ("pickle.load", "runs dynamic code"),
+    ("load_dotenv", "reads secrets files"), (".env\"", "reads secrets files"), (".env'", "reads secrets files"),
+    (".dev.vars", "reads secrets files"),
+];
+
+/// Scripts larger than this aren't scanned
+const MAX_SCRIPT_BYTES: u64 = 512 * 1024;
+
+/// Cap on local modules scanned per script
+const MAX_SCRIPT_FILES: usize = 32;
+
+/// Check if a program is handled by this model
+pub fn is_python_tool(program: &str) -> bool {
+    PYTHON_TOOLS.contains(&program) || is_python(program) || venv_tool(program).is_some()
+}
+
+/// `python`, `python3`, `python3.12`
+fn is_python(program: &str) -> bool {
+    program == "python"
+        || program
+            .strip_prefix("python3")
+            .is_some_and(|version| version.bytes().all(|b| b.is_ascii_digit() || b == b'.'))
+}
+
+/// The tool a virtualenv binary (`.venv/bin/pytest`) runs
+fn venv_tool(program: &str) -> Option<&str> {
+    let (dir, tool) = program.rsplit_once('/')?;
+    let root = dir.strip_suffix("/bin").or_else(|| dir.strip_suffix("/Scripts"))?;
+    root.split('/').any(|component| component.contains("venv")).then_some(tool)
+}
+
+/// Judge a Python tool invocation
+pub fn judge(program: &str, args: &[&str], current_dir: &Path, config: &Config) -> Judgment {
+    if let Some(tool) = venv_tool(program) {
+        return nested(program, tool, args);
+    }
+    if is_python(program) {
+        return judge_python(args, current_dir, config);
+    }
+    if args.first().is_some_and(|a| INFO_FLAGS.contains(a)) {
+        return Judgment::Safe;
+    }
+    let Some((sub, rest)) = split_subcommand(args) else {
+        // Bare `uv`/`pip` prints usage
+        return Judgment::Safe;
+    };
+    match program {
+        "uv" => judge_uv(sub, rest, current_dir, config),
+        "poetry" => judge_poetry(sub, rest, current_dir, config),
+        "pdm" => judge_pdm(sub, rest, current_dir, config),
+        "hatch" => judge_hatch(sub, rest, current_dir, config),
+        "pipx" => judge_pipx(sub, rest, current_dir, config),
+        _ => judge_pip(program, sub, rest, current_dir, config),
+    }
+}
+
+fn judge_uv(sub: &str, args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    let first = args.first().copied();
+    match sub {
+        "run" => unwrap_run("uv run", args, dir, config),
+        "pip" => match split_subcommand(args) {
+            Some((pip_sub, rest)) => judge_pip("uv pip", pip_sub, rest, dir, config),
+            None => Judgment::Safe,
+        },
+        "add" => judge_install(args, dir, config),
+        "sync" if has_any(args, &["--locked", "--frozen"]) || has_lockfile(dir, "uv.lock") => Judgment::Safe,
+        "lock" if has_any(args, &["--check", "--locked", "--dry-run"]) => Judgment::Safe,
+        "tree" | "venv" | "help" => Judgment::Safe,
+        "version" if !args.iter().any(|a| !a.starts_with('-') || a.starts_with("--bump")) => Judgment::Safe,
+        "python" if matches!(first, Some("list" | "find" | "dir")) => Judgment::Safe,
+        "cache" if first == Some("dir") => Judgment::Safe,
+        "tool" => match split_subcommand(args) {
+            Some(("list" | "dir", _)) => Judgment::Safe,
+            Some(("install", rest)) => judge_install(rest, dir, config),
+            Some(("run", rest)) => unwrap_runner("uv tool run", rest, config),
+            _ => Judgment::Unknown,
+        },
+        _ => Judgment::Unknown,
+    }
+}
+
+fn judge_poetry(sub: &str, args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    match sub {
+        "run" => unwrap_run("poetry run", args, dir, config),
+        "install" | "sync" if has_lockfile(dir, "poetry.lock") => Judgment::Safe,
+        "add" => judge_install(args, dir, config),
+        "show" | "check" | "about" | "list" | "search" | "build" => Judgment::Safe,
+        "env" if matches!(args.first(), Some(&("info" | "list"))) => Judgment::Safe,
+        "config" if args.is_empty() || args.contains(&"--list") => Judgment::Safe,
+        "version" if args.iter().all(|a| a.starts_with('-')) => Judgment::Safe,
+        _ => Judgment::Unknown,
+    }
+}
+
+fn judge_pdm(sub: &str, args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    match sub {
+        "run" if has_any(args, &["-l", "--list"]) => Judgment::Safe,
+        "run" => match project_script(args, dir, "pdm", &["tool", "pdm", "scripts"]) {
+            Some(judgment) => judgment,
+            None => unwrap_run("pdm run", args, dir, config),
+        },
+        "install" | "sync" if has_lockfile(dir, "pdm.lock") => Judgment::Safe,
+        "add" => judge_install(args, dir, config),
+        "list" | "show" | "info" | "build" => Judgment::Safe,
+        "config" if args.is_empty() => Judgment::Safe,
+        _ => Judgment::Unknown,
+    }
+}
+
+fn judge_hatch(sub: &str, args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    match sub {
+        "run" => {
+            // `hatch run [env:]script`: scripts live under the environment's table
+            let target = args.iter().find(|a| !a.starts_with('-')).copied().unwrap_or_default();
+            let env = target.split_once(':').map_or("default", |(env, _)| env);
+            let table = ["tool", "hatch", "envs", env, "scripts"];
+            let args: Vec<&str> = args.iter().map(|a| if *a == target { a.split_once(':').map_or(*a, |(_, s)| s) } else { a }).collect();
+            match project_script(&args, dir, "hatch", &table) {
+                Some(judgment) => judgment,
+                None => unwrap_run("hatch run", &args, dir, config),
+            }
+        }
+        "build" | "status" => Judgment::Safe,
+        "version" if args.is_empty() => Judgment::Safe,
+        "env" if args.first() == Some(&"show") => Judgment::Safe,
+        "project" if args.first() == Some(&"metadata") => Judgment::Safe,
+        _ => Judgment::Unknown,
+    }
+}
+
+fn judge_pipx(sub: &str, args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    match sub {
+        "list" | "environment" => Judgment::Safe,
+        "install" | "inject" => {
+            // `pipx inject <venv> <packages>`: the first operand is the app to add to
+            let operands = if sub == "inject" { args.iter().filter(|a| !a.starts_with('-')).skip(1).copied().collect() } else { args.to_vec() };
+            judge_install(&operands, dir, config)
+        }
+        "run" => unwrap_runner("pipx run", args, config),
+        _ => Judgment::Unknown,
+    }
+}
+
+fn judge_pip(label: &str, sub: &str, args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    match sub {
+        _ if PIP_READ_ONLY_SUBCOMMANDS.contains(&sub) => Judgment::Safe,
+        "config" | "cache" if matches!(args.first(), Some(&("list" | "get" | "debug" | "dir" | "info"))) => Judgment::Safe,
+        "install" | "sync" => judge_install(args, dir, config),
+        "uninstall" => Judgment::Ask(format!("{} uninstall removes packages from the environment", label)),
+        _ => Judgment::Unknown,
+    }
+}
+
+fn judge_python(args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        match arg {
+            _ if INFO_FLAGS.contains(&arg) => return Judgment::Safe,
+            "-m" => {
+                let rest = args.get(i + 2..).unwrap_or_default();
+                return match args.get(i + 1).copied() {
+                    Some("pip") => match split_subcommand(rest) {
+                        Some((sub, rest)) => judge_pip("python -m pip", sub, rest, dir, config),
+                        None => Judgment::Safe,
+                    },
+                    Some(module) if SAFE_PYTHON_MODULES.contains(&module) => Judgment::Safe,
+                    _ => Judgment::Unknown,
+                };
+            }
+            "-c" if config.analyze_python_scripts => {
+                let code = args[i + 1..].join(" ");
+                return match capabilities(&[paths::strip_quotes(&code)]).as_slice() {
+                    [] => Judgment::Unknown,
+                    found => Judgment::Ask(format!("python -c code {}", found.join(", "))),
+                };
+            }
+            "-X" | "-W" => i += 2,
+            // `-` reads the program from stdin
+            "-" | "-c" => return Judgment::Unknown,
+            _ if arg.starts_with('-') => i += 1,
+            script if config.analyze_python_scripts => {
+                return match analyze_script(&paths::normalize_path(script, &dir.to_string_lossy())) {
+                    Some(found) if !found.is_empty() => Judgment::Ask(format!("python script `{}` {}", script, found.join(", "))),
+                    _ => Judgment::Unknown,
+                };
+            }
+            _ => return Judgment::Unknown,
+        }
+    }
+    // Bare `python` is an interactive REPL
+    Judgment::Unknown
+}
+
+/// The subcommand and its arguments, skipping global flags
+fn split_subcommand<'a, 'b>(args: &'b [&'a str]) -> Option<(&'a str, &'b [&'a str])> {
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        if !arg.starts_with('-') {
+            return Some((arg, &args[i + 1..]));
+        }
+        i += if GLOBAL_VALUE_FLAGS.contains(&arg) { 2 } else { 1 };
+    }
+    None
+}
+
+fn has_any(args: &[&str], flags: &[&str]) -> bool {
+    args.iter().any(|a| flags.contains(a))
+}
+
+/// Check for a lockfile next to the nearest `pyproject.toml`
+fn has_lockfile(dir: &Path, lockfile: &str) -> bool {
+    project_root(dir).is_some_and(|root| root.join(lockfile).is_file())
+}
+
+fn project_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find(|d| d.join("pyproject.toml").is_file()).map(Path::to_path_buf)
+}
+
+fn nested(label: &str, program: &str, args: &[&str]) -> Judgment {
+    let mut command = program.to_string();
+    for arg in args {
+        command.push(' ');
+        command.push_str(arg);
+    }
+    Judgment::Nested { label: label.to_string(), command }
+}
+
+/// Unwrap `uv run [flags] <command>`: `-m module` and `script.py` run under python.
+/// Packages added with `--with` are checked like an install.
+fn unwrap_run(label: &str, args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    let mut with = Vec::new();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        match arg {
+            "--" => {
+                i += 1;
+                break;
+            }
+            "-m" | "--module" => return nested(label, "python -m", &args[i + 1..]),
+            "--with" => with.extend(args.get(i + 1).copied()),
+            _ if !arg.starts_with('-') => break,
+            _ => {}
+        }
+        i += if RUN_VALUE_FLAGS.contains(&arg) { 2 } else { 1 };
+    }
+    if !with.is_empty() {
+        match judge_install(&with, dir, config) {
+            Judgment::Safe => {}
+            judgment => return judgment,
+        }
+    }
+    match args.get(i..).unwrap_or_default() {
+        [] => Judgment::Unknown,
+        [script, rest @ ..] if script.ends_with(".py") => nested(label, &format!("python {}", script), rest),
+        [program, rest @ ..] => nested(label, program, rest),
+    }
+}
+
+/// Unwrap a runner that fetches a package and runs it (`uv tool run`, `pipx run`),
+/// asking before typosquats of popular packages
+fn unwrap_runner(label: &str, args: &[&str], config: &Config) -> Judgment {
+    let mut i = 0;
+    while args.get(i).is_some_and(|a| a.starts_with('-')) {
+        i += if RUN_VALUE_FLAGS.contains(&args[i]) || args[i] == "--from" || args[i] == "--spec" { 2 } else { 1 };
+    }
+    let Some((&program, rest)) = args.get(i..).and_then(|a| a.split_first()) else {
+        return Judgment::Unknown;
+    };
+    let tool = program.split(['@', '=']).next().unwrap_or(program);
+    let name = package_policy::python_package_name(program);
+    match name.map(|name| package_policy::check(&[&name], POPULAR_PYPI_PACKAGES, &config.packages)) {
+        Some(Verdict::Denied(reason)) => Judgment::Deny(reason),
+        Some(Verdict::Suspicious(reason)) => strictest([Judgment::Ask(reason), nested(label, tool, rest)]),
+        _ => nested(label, tool, rest),
+    }
+}
+
+/// A script from `pyproject.toml` (`[tool.pdm.scripts]`, hatch environment scripts),
+/// or `None` if the first operand isn't one
+fn project_script(args: &[&str], dir: &Path, tool: &str, table: &[&str]) -> Option<Judgment> {
+    let (position, name) = args.iter().enumerate().find(|(_, a)| !a.starts_with('-'))?;
+    let root = project_root(dir)?;
+    let doc = toml::parse(&std::fs::read_to_string(root.join("pyproject.toml")).ok()?)?;
+    let script = table.iter().try_fold(&doc, |node, key| node.get(key))?.get(name)?;
+
+    let command = match script {
+        Toml::Scalar(command) => command.clone(),
+        // hatch: a list of commands run in order
+        Toml::Array(commands) => commands.iter().map(|c| c.as_str().unwrap_or_default()).collect::<Vec<_>>().join(" && "),
+        // pdm: `{cmd = ...}` or `{shell = ...}`; `call` and `composite` can't be judged
+        Toml::Table(_) => match script.get("cmd").or_else(|| script.get("shell")) {
+            Some(Toml::Scalar(command)) => command.clone(),
+            Some(Toml::Array(words)) => words.iter().filter_map(Toml::as_str).collect::<Vec<_>>().join(" "),
+            _ => return Some(Judgment::Unknown),
+        },
+    };
+    let rest = args[position + 1..].join(" ");
+    let command = if command.contains("{args}") {
+        command.replace("{args}", &rest)
+    } else if rest.is_empty() {
+        command
+    } else {
+        format!("{} {}", command, rest)
+    };
+    Some(Judgment::Nested { label: format!("{} script `{}`", tool, name), command })
+}
+
+/// Judge an install's requirements against the package policy
+fn judge_install(args: &[&str], dir: &Path, config: &Config) -> Judgment {
+    match requirement_names(args, dir) {
+        Some(names) if !names.is_empty() => {
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            judge_packages(&names, POPULAR_PYPI_PACKAGES, &config.packages)
+        }
+        _ => Judgment::Unknown,
+    }
+}
+
+/// Normalized names of the packages an install adds, following requirement files;
+/// `None` if any come from a path, URL, editable checkout or alternative index
+fn requirement_names(args: &[&str], dir: &Path) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        i += 1;
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg, None),
+        };
+        if INSTALL_SOURCE_FLAGS.contains(&flag) {
+            return None;
+        }
+        if REQUIREMENT_FILE_FLAGS.contains(&flag) {
+            let file = match inline {
+                Some(file) => file,
+                None => {
+                    i += 1;
+                    args.get(i - 1).copied()?
+                }
+            };
+            names.extend(requirements_file(&paths::normalize_path(file, &dir.to_string_lossy()), 0)?);
+        } else if INSTALL_VALUE_FLAGS.contains(&flag) {
+            i += usize::from(inline.is_none());
+        } else if !arg.starts_with('-') {
+            names.push(package_policy::python_package_name(arg)?);
+        }
+    }
+    Some(names)
+}
+
+/// Package names in a requirements file, following nested `-r` includes
+fn requirements_file(path: &Path, depth: usize) -> Option<Vec<String>> {
+    if depth > 8 {
+        return None;
+    }
+    let text = std::fs::read_to_string(path).ok()?.replace("\\\n", " ");
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut names = Vec::new();
+    for line in text.lines() {
+        let line = line.split(" #").next().unwrap_or_default().trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let first = words.next().unwrap_or_default();
+        if REQUIREMENT_FILE_FLAGS.contains(&first) {
+            let nested = paths::normalize_path(words.next()?, &dir.to_string_lossy());
+            names.extend(requirements_file(&nested, depth + 1)?);
+        } else if INSTALL_SOURCE_FLAGS.iter().any(|f| first.starts_with(f)) || line.contains("://") {
+            return None;
+        } else if !first.starts_with('-') {
+            names.push(package_policy::python_package_name(first)?);
+        }
+    }
+    Some(names)
+}
+
+/// Capabilities found in a script and the local modules it imports; `None` if the
+/// script can't be read
+fn analyze_script(script: &Path) -> Option<Vec<&'static str>> {
+    let root_dir = script.parent()?.to_path_buf();
+    let mut sources = Vec::new();
+    let mut queue = vec![script.to_path_buf()];
+    let mut seen: Vec<PathBuf> = Vec::new();
+    while let Some(file) = queue.pop() {
+        if seen.contains(&file) || seen.len() >= MAX_SCRIPT_FILES {
+            continue;
+        }
+        let readable = std::fs::metadata(&file).is_ok_and(|m| m.is_file() && m.len() <= MAX_SCRIPT_BYTES);
+        let Some(source) = readable.then(|| std::fs::read_to_string(&file).ok()).flatten() else {
+            if seen.is_empty() {
+                return None;
+            }
+            continue;
+        };
+        let file_dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+        for module in local_imports(&source) {
+            let relative = module.replace('.', "/");
+            for base in [&file_dir, &root_dir] {
+                for candidate in [base.join(format!("{}.py", relative)), base.join(&relative).join("__init__.py")] {
+                    if candidate.is_file() {
+                        queue.push(candidate);
+                    }
+                }
+            }
+        }
+        seen.push(file);
+        sources.push(source);
+    }
+    let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+    Some(capabilities(&sources))
+}
+
+/// Capabilities the sources use, each listed once
+fn capabilities(sources: &[&str]) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    for (needle, capability) in SCRIPT_CAPABILITIES {
+        if !found.contains(capability) && sources.iter().any(|s| s.contains(needle)) {
+            found.push(*capability);
+        }
+    }
+    found
+}
+
+/// Modules named by `import a.b, c` and `from .d import e` lines
+fn local_imports(source: &str) -> Vec<String> {
+    let mut modules = Vec::new();
+    for line in source.lines().map(str::trim_start) {
+        if let Some(rest) = line.strip_prefix("import ") {
+            modules.extend(rest.split(',').filter_map(|part| part.split_whitespace().next()).map(str::to_string));
+        } else if let Some(rest) = line.strip_prefix("from ") {
+            if let Some(module) = rest.split_whitespace().next() {
+                modules.push(module.trim_start_matches('.').to_string());
+            }
+        }
+    }
+    modules.retain(|m| !m.is_empty());
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    fn nested_in(label: &str, command: &str) -> Judgment {
+        Judgment::Nested { label: label.to_string(), command: command.to_string() }
+    }
+
+    #[test]
+    fn test_runners_unwrapped() {
+        let dir = TempDir::new("python_runners");
+        assert_eq!(judge_in("uv run --frozen pytest -x", dir.path()), nested_in("uv run", "pytest -x"));
+        assert_eq!(judge_in("uv run -p 3.12 scripts/gen.py --out x", dir.path()), nested_in("uv run", "python scripts/gen.py --out x"));
+        assert_eq!(judge_in("poetry run -- mypy src", dir.path()), nested_in("poetry run", "mypy src"));
+        assert_eq!(judge_in("uv run -m pytest", dir.path()), nested_in("uv run", "python -m pytest"));
+        assert_eq!(judge_in("hatch run test:pytest", dir.path()), nested_in("hatch run", "pytest"));
+        assert_eq!(judge_in(".venv/bin/pytest -q", dir.path()), nested_in(".venv/bin/pytest", "pytest -q"));
+        assert_eq!(judge_in("/home/me/venvs/app/bin/python -m mypy", dir.path()), nested_in("/home/me/venvs/app/bin/python", "python -m mypy"));
+        assert_eq!(
+            judge_in("uv run --with reqeusts python x.py", dir.path()),
+            Judgment::Ask("Package `reqeusts` is one edit away from popular package `requests` (possible typosquat)".to_string())
+        );
+        assert_eq!(judge_in("uv tool run ruff@0.5 check", dir.path()), nested_in("uv tool run", "ruff check"));
+        assert!(!is_python_tool("bin/pytest"));
+    }
+
+    #[test]
+    fn test_project_scripts() {
+        let dir = TempDir::new("python_scripts");
+        dir.write_file(
+            "pyproject.toml",
+            "[tool.pdm.scripts]\nlint = \"ruff check {args} src\"\ntest = { cmd = [\"pytest\", \"-q\"] }\nhook = { call = \"pkg:main\" }\n\n[tool.hatch.envs.default.scripts]\ncheck = [\"ruff check\", \"mypy src\"]\n",
+        );
+        assert_eq!(judge_in("pdm run lint --fix", dir.path()), nested_in("pdm script `lint`", "ruff check --fix src"));
+        assert_eq!(judge_in("pdm run test tests/a.py", dir.path()), nested_in("pdm script `test`", "pytest -q tests/a.py"));
+        assert_eq!(judge_in("pdm run hook", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("pdm run black .", dir.path()), nested_in("pdm run", "black ."));
+        assert_eq!(judge_in("hatch run check", dir.path()), nested_in("hatch script `check`", "ruff check && mypy src"));
+    }
+
+    #[test]
+    fn test_pip_and_installs() {
+        let dir = TempDir::new("python_pip");
+        for command in ["pip list", "pip3 show requests", "python3 -m pip freeze", "uv pip tree", "uv --version", "pipx list", "poetry show --tree"] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_in("pip install requests", dir.path()), Judgment::Unknown);
+        assert_eq!(
+            judge_in("pip install -U numpyy", dir.path()),
+            Judgment::Ask("Package `numpyy` is one edit away from popular package `numpy` (possible typosquat)".to_string())
+        );
+        dir.write_file("requirements.txt", "# deps\nflask>=3 \\\n  --hash=sha256:abc\n-r dev.txt\n");
+        dir.write_file("dev.txt", "pytset==8\n");
+        assert_eq!(
+            judge_in("pip install -r requirements.txt", dir.path()),
+            Judgment::Ask("Package `pytset` is one edit away from popular package `pytest` (possible typosquat)".to_string())
+        );
+        assert_eq!(judge_in("pip install -e .", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("uv add httpx --index-url https://mirror", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("pip uninstall flask", dir.path()), Judgment::Ask("pip uninstall removes packages from the environment".to_string()));
+
+        let config = Config {
+            packages: package_policy::PackagePolicy { allow: vec!["flask".to_string(), "pytest".to_string()], ..Default::default() },
+            ..Config::default()
+        };
+        dir.write_file("dev.txt", "pytest==8\n");
+        assert_eq!(judge("pip", &["install", "-r", "requirements.txt"], dir.path(), &config), Judgment::Safe);
+        assert_eq!(judge("uv", &["add", "Flask[async]>=3"], dir.path(), &config), Judgment::Safe);
+        assert_eq!(judge("hatch", &["run", "--with", "flask", "pytest"], dir.path(), &config), nested_in("hatch run", "pytest"));
+        dir.write_file("tools/requirements.txt", "pytset==8\n");
+        assert_eq!(
+            judge("pipx", &["install", "-r", "requirements.txt"], &dir.path().join("tools"), &config),
+            Judgment::Ask("Package `pytset` is one edit away from popular package `pytest` (possible typosquat)".to_string())
+        );
+        dir.write_file("tools/requirements.txt", "pytest==8\n");
+        assert_eq!(judge("pipx", &["install", "-r", "requirements.txt"], &dir.path().join("tools"), &config), Judgment::Safe);
+    }
+
+    #[test]
+    fn test_lockfile_syncs() {
+        let dir = TempDir::new("python_sync");
+        dir.write_file("pyproject.toml", "[project]\nname = \"app\"\n");
+        dir.create_dir("src");
+        assert_eq!(judge_in("uv sync", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("uv sync --locked", dir.path()), Judgment::Safe);
+        dir.create_file("uv.lock");
+        dir.create_file("poetry.lock");
+        assert_eq!(judge_in("uv sync", &dir.path().join("src")), Judgment::Safe);
+        assert_eq!(judge_in("poetry install", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("pdm install", dir.path()), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_script_analysis() {
+        let dir = TempDir::new("python_analysis");
+        dir.write_file("report.py", "import json\nfrom lib import helpers\nprint(json.dumps({}))\n");
+        dir.write_file("lib/__init__.py", "");
+        dir.write_file("lib/helpers.py", "import os\n");
+        assert_eq!(judge_in("python report.py --json", dir.path()), Judgment::Unknown);
+        dir.write_file("clean.py", "data = open(\"in.txt\").read()\n");
+        assert_eq!(judge_in("python3 clean.py", dir.path()), Judgment::Unknown);
+
+        dir.write_file("lib/helpers.py", "import subprocess, shutil\nshutil.rmtree('out')\n");
+        dir.write_file("report.py", "import lib.helpers\nimport requests\n");
+        assert_eq!(
+            judge_in("python3 -u report.py", dir.path()),
+            Judgment::Ask("python script `report.py` runs processes, deletes files, uses the network".to_string())
+        );
+        assert_eq!(judge_in("python missing.py", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("python -c 'print(1)'", dir.path()), Judgment::Unknown);
+        assert_eq!(
+            judge_in("python -c 'import os; os.system(\"ls\")'", dir.path()),
+            Judgment::Ask("python -c code runs processes".to_string())
+        );
+        assert_eq!(judge_in("python", dir.path()), Judgment::Unknown);
+
+        let config = Config { analyze_python_scripts: false, ..Config::default() };
+        assert_eq!(judge("python", &["report.py"], dir.path(), &config), Judgment::Unknown);
+    }
+}
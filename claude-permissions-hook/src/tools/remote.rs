@@ -0,0 +1,371 @@
+//! Remote execution and file transfer: `ssh`, `scp` and `rsync`. The command `ssh` runs
+//! is judged like any other, but in a remote context where local paths mean nothing.
+//! Hosts not on `remote.allowed_hosts` ask, as do options that run commands of their own
+//! (`-o ProxyCommand=...`, `rsync -e`). Transfers deny secrets files as sources (and
+//! directories holding them, when they leave the machine); `rsync --delete`,
+//! `--remove-source-files` and local destinations outside the repo ask.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::Judgment;
+use crate::config::Config;
+use crate::paths;
+use crate::rules;
+
+/// ssh flags that take a value
+const SSH_VALUE_FLAGS: &str = "BbcDEeFIiJLlmOopQRSWw";
+
+/// scp flags that take a value
+const SCP_VALUE_FLAGS: &str = "cDFiJlPoSX";
+
+/// rsync short flags that take a value
+const RSYNC_VALUE_FLAGS: &str = "efTBM";
+
+/// rsync long flags that take a value
+const RSYNC_LONG_VALUE_FLAGS: &[&str] = &[
+    "--rsh", "--exclude", "--include", "--exclude-from", "--include-from", "--files-from", "--filter",
+    "--rsync-path", "--port", "--temp-dir", "--partial-dir", "--backup-dir", "--suffix", "--chmod",
+    "--chown", "--bwlimit", "--timeout", "--contimeout", "--compare-dest", "--copy-dest", "--link-dest",
+    "--log-file", "--log-file-format", "--password-file", "--block-size", "--max-size", "--min-size",
+    "--max-delete", "--out-format", "--usermap", "--groupmap", "--iconv", "--sockopts", "--outbuf",
+    "--info", "--debug", "--skip-compress", "--compress-choice", "--checksum-choice", "--remote-option",
+    "--stop-after", "--stop-at", "--modify-window", "--protocol", "--address", "--write-batch",
+    "--only-write-batch", "--read-batch",
+];
+
+/// ssh options (`-o Name=value`, compared lowercased) that run a command of their own,
+/// locally or on the remote host
+const SSH_COMMAND_OPTIONS: &[&str] = &["proxycommand", "localcommand", "permitlocalcommand", "remotecommand", "knownhostscommand"];
+
+/// Hosts remote commands may reach without asking
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RemotePolicy {
+    /// Host name patterns (`*`, `?`), e.g. `*.dev.internal`; the user and port are ignored
+    pub allowed_hosts: Vec<String>,
+}
+
+impl RemotePolicy {
+    fn allows(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|pattern| paths::glob_match(pattern, host))
+    }
+}
+
+/// Check if a program runs commands on, or copies files to and from, another machine
+pub fn is_remote_tool(program: &str) -> bool {
+    matches!(program, "ssh" | "scp" | "rsync")
+}
+
+/// Judge an ssh, scp or rsync command; `segment` is the unsplit command, for its quoting
+pub fn judge(segment: &str, current_dir: &Path, config: &Config) -> Judgment {
+    let words = paths::shell_words(segment);
+    let start = words.iter().position(|w| !rules::is_env_assignment(w)).unwrap_or(words.len());
+    let Some((program, args)) = words[start..].split_first() else {
+        return Judgment::Unknown;
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match program.as_str() {
+        "ssh" => ssh(&args, &config.remote),
+        program => transfer(program, &args, current_dir, &config.remote),
+    }
+}
+
+/// `ssh [flags] HOST [CMD...]`: the words after the host are joined and run by the
+/// remote shell. Without a command ssh opens an interactive login.
+fn ssh(args: &[&str], policy: &RemotePolicy) -> Judgment {
+    let (flags, positionals) = parse(args, SSH_VALUE_FLAGS, &[], true);
+    if let Some(reason) = command_option("ssh", &flags) {
+        return Judgment::Ask(reason);
+    }
+    // A control socket sends the command over a connection opened to who knows where
+    if flags.iter().any(|(flag, _)| flag == "-S") {
+        return Judgment::Unknown;
+    }
+    let Some((target, command)) = positionals.split_first() else {
+        return Judgment::Unknown;
+    };
+    if command.is_empty() {
+        return Judgment::Unknown;
+    }
+    let host = host_name(target);
+    Judgment::Remote { label: format!("ssh `{}`", host), command: command.join(" "), ask: host_ask("ssh", host, policy) }
+}
+
+/// `scp`/`rsync SRC... DEST`
+fn transfer(program: &str, args: &[&str], current_dir: &Path, policy: &RemotePolicy) -> Judgment {
+    let (short, long) = if program == "scp" { (SCP_VALUE_FLAGS, &[][..]) } else { (RSYNC_VALUE_FLAGS, RSYNC_LONG_VALUE_FLAGS) };
+    let (flags, operands) = parse(args, short, long, false);
+    let has = |names: &[&str]| flags.iter().any(|(flag, _)| names.contains(&flag.as_str()));
+    if let Some(reason) = command_option(program, &flags).or_else(|| transport_command(program, &flags)) {
+        return Judgment::Ask(reason);
+    }
+    let Some((dest, sources)) = operands.split_last() else {
+        return Judgment::Unknown;
+    };
+    // `rsync host:dir/` with no destination lists the remote files
+    let (dest, sources) = if sources.is_empty() { (None, operands.as_slice()) } else { (Some(*dest), sources) };
+    let dry_run = has(&["-n", "--dry-run", "--list-only"]);
+    let leaves = !dry_run && dest.is_some_and(|d| remote_path(d).is_some());
+    let recursive = has(&["-r", "-a", "--recursive", "--archive"]);
+    let excludes: Vec<&str> = flags
+        .iter()
+        .filter_map(|(flag, value)| match (flag.as_str(), value.as_deref()) {
+            ("--exclude", Some(pattern)) => Some(pattern),
+            ("-f" | "--filter", Some(rule)) => rule.strip_prefix("- ").or_else(|| rule.strip_prefix("exclude ")),
+            _ => None,
+        })
+        .collect();
+
+    for source in sources {
+        let path = remote_path(source).map(|(_, path)| path).unwrap_or(source);
+        if paths::is_secrets_file(path) {
+            return Judgment::Deny(format!("{} copies the secrets file `{}`", program, source));
+        }
+        if leaves && recursive && remote_path(source).is_none() {
+            if let Some(secret) = secrets_in(&paths::normalize_path(source, &current_dir.to_string_lossy()), &excludes) {
+                return Judgment::Deny(format!("{} copies `{}`, which holds the secrets file `{}`", program, source, secret));
+            }
+        }
+    }
+
+    if let Some((flag, _)) = flags.iter().find(|(flag, _)| flag.starts_with("--del") && !dry_run) {
+        let dest = dest.unwrap_or_default();
+        let dest_path = remote_path(dest).map(|(_, path)| path).unwrap_or(dest);
+        if matches!(dest_path.trim_end_matches('/'), "" | "~") {
+            return Judgment::Deny(format!("{} {} into `{}` deletes everything there that isn't in the source", program, flag, dest));
+        }
+        return Judgment::Ask(format!("{} {} deletes files in `{}` that aren't in the source", program, flag, dest));
+    }
+    if has(&["--remove-source-files", "--remove-sent-files"]) && !dry_run {
+        return Judgment::Ask(format!("{} --remove-source-files deletes the source files it copies", program));
+    }
+    if let Some(dest) = dest.filter(|d| remote_path(d).is_none() && !dry_run) {
+        if super::is_outside_repo(dest, current_dir) {
+            return Judgment::Ask(format!("{} writes `{}`, outside the repo", program, dest));
+        }
+    }
+    match operands.iter().filter_map(|o| remote_path(o)).find_map(|(host, _)| host_ask(program, host, policy)) {
+        Some(reason) => Judgment::Ask(reason),
+        None => Judgment::Safe,
+    }
+}
+
+/// Why an ssh or scp `-o` option runs a command of its own (`-o ProxyCommand=...`)
+fn command_option(program: &str, flags: &[(String, Option<String>)]) -> Option<String> {
+    flags.iter().filter(|(flag, _)| flag == "-o").find_map(|(_, value)| {
+        let value = value.as_deref()?;
+        let name = value.split(['=', ' ', '\t']).next().unwrap_or(value);
+        SSH_COMMAND_OPTIONS
+            .contains(&name.to_lowercase().as_str())
+            .then(|| format!("{} -o {} runs a command of its own", program, name))
+    })
+}
+
+/// Why scp's or rsync's transport runs something other than plain ssh: `scp -S prog`,
+/// `rsync -e cmd` (unless it's `ssh` with harmless flags) or `rsync --rsync-path cmd`
+fn transport_command(program: &str, flags: &[(String, Option<String>)]) -> Option<String> {
+    flags.iter().find_map(|(flag, value)| {
+        let value = value.as_deref().unwrap_or_default();
+        match (program, flag.as_str()) {
+            ("scp", "-S") => Some(format!("scp -S `{}` runs its own connection program", value)),
+            ("rsync", "-e" | "--rsh") if !is_plain_ssh(value) => Some(format!("rsync {} `{}` runs its own remote shell", flag, value)),
+            ("rsync", "--rsync-path") => Some(format!("rsync --rsync-path `{}` runs its own command on the remote host", value)),
+            _ => None,
+        }
+    })
+}
+
+/// `ssh -p 2222 -i key`: ssh with flags but no command and no command options
+fn is_plain_ssh(shell: &str) -> bool {
+    let words: Vec<&str> = shell.split_whitespace().collect();
+    let Some((&"ssh", args)) = words.split_first() else {
+        return false;
+    };
+    let (flags, positionals) = parse(args, SSH_VALUE_FLAGS, &[], true);
+    positionals.is_empty() && command_option("ssh", &flags).is_none() && !flags.iter().any(|(flag, _)| flag == "-S")
+}
+
+/// Split flags (with their values, if they take one) from positionals. With
+/// `stop_at_positional`, everything from the first positional on is positional (ssh's
+/// command is its own).
+fn parse<'a>(args: &[&'a str], short: &str, long: &[&str], stop_at_positional: bool) -> (Vec<(String, Option<String>)>, Vec<&'a str>) {
+    let mut flags = Vec::new();
+    let mut positionals = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        if arg == "--" {
+            positionals.extend_from_slice(&args[i..]);
+            break;
+        }
+        if let Some(name) = arg.strip_prefix("--") {
+            match name.split_once('=') {
+                Some((name, value)) => flags.push((format!("--{}", name), Some(value.to_string()))),
+                None if long.contains(&arg) => {
+                    flags.push((arg.to_string(), args.get(i).map(|v| v.to_string())));
+                    i += 1;
+                }
+                None => flags.push((arg.to_string(), None)),
+            }
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            // Flags cluster (`-avz`); a value flag takes the rest of the word or the next one
+            for (at, letter) in arg.char_indices().skip(1) {
+                if !short.contains(letter) {
+                    flags.push((format!("-{}", letter), None));
+                    continue;
+                }
+                let rest = &arg[at + 1..];
+                let value = if rest.is_empty() {
+                    i += 1;
+                    args.get(i - 1).map(|v| v.to_string())
+                } else {
+                    Some(rest.to_string())
+                };
+                flags.push((format!("-{}", letter), value));
+                break;
+            }
+        } else if stop_at_positional {
+            positionals.extend_from_slice(&args[i - 1..]);
+            break;
+        } else {
+            positionals.push(arg);
+        }
+    }
+    (flags, positionals)
+}
+
+/// The host and path of a remote operand: `[user@]host:path`, `host::module/path`,
+/// `rsync://host/path` or `scp://host/path`. Local paths that contain a colon have a
+/// slash before it.
+fn remote_path(operand: &str) -> Option<(&str, &str)> {
+    if let Some(rest) = operand.strip_prefix("rsync://").or_else(|| operand.strip_prefix("scp://")) {
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        return Some((host_name(authority), path));
+    }
+    let colon = operand.find(':')?;
+    if operand[..colon].contains('/') || colon == 0 {
+        return None;
+    }
+    let path = &operand[colon + 1..];
+    Some((host_name(&operand[..colon]), path.strip_prefix(':').unwrap_or(path)))
+}
+
+/// `user@host:port` → `host`
+fn host_name(target: &str) -> &str {
+    let target = target.strip_prefix("ssh://").unwrap_or(target);
+    let host = target.rsplit('@').next().unwrap_or(target);
+    host.split(':').next().unwrap_or(host)
+}
+
+fn host_ask(program: &str, host: &str, policy: &RemotePolicy) -> Option<String> {
+    (!policy.allows(host)).then(|| format!("{} connects to `{}`, which isn't on remote.allowed_hosts", program, host))
+}
+
+/// A secrets file directly inside `dir` that no exclude pattern covers
+fn secrets_in(dir: &Path, excludes: &[&str]) -> Option<String> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| paths::is_secrets_file(name))
+        .find(|name| !excludes.iter().any(|pattern| paths::glob_match(pattern.trim_start_matches('/'), name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    fn remote(label: &str, command: &str, host: &str) -> Judgment {
+        Judgment::Remote {
+            label: label.to_string(),
+            command: command.to_string(),
+            ask: Some(format!("ssh connects to `{}`, which isn't on remote.allowed_hosts", host)),
+        }
+    }
+
+    #[test]
+    fn test_ssh() {
+        let dir = Path::new("/nonexistent");
+        assert_eq!(judge_in("ssh deploy@web1 'rm -rf /srv'", dir), remote("ssh `web1`", "rm -rf /srv", "web1"));
+        assert_eq!(judge_in("ssh -p 2222 -i ~/.ssh/id web1 ls -la /var/log", dir), remote("ssh `web1`", "ls -la /var/log", "web1"));
+        assert_eq!(judge_in("ssh -tt -o StrictHostKeyChecking=no web1.example.com:22 uptime", dir), remote("ssh `web1.example.com`", "uptime", "web1.example.com"));
+        assert_eq!(judge_in("ssh web1", dir), Judgment::Unknown);
+        assert_eq!(
+            judge_in("ssh -o ProxyCommand='sh -c payload' web1 uptime", dir),
+            Judgment::Ask("ssh -o ProxyCommand runs a command of its own".to_string())
+        );
+        assert_eq!(
+            judge_in("ssh -oPermitLocalCommand=yes -o LocalCommand=payload web1 uptime", dir),
+            Judgment::Ask("ssh -o PermitLocalCommand runs a command of its own".to_string())
+        );
+        assert_eq!(judge_in("ssh -S /tmp/ctl web1 uptime", dir), Judgment::Unknown);
+
+        let config = Config { remote: RemotePolicy { allowed_hosts: vec!["*.dev.internal".to_string()] }, ..Config::default() };
+        assert_eq!(
+            judge("ssh api.dev.internal systemctl status api", dir, &config),
+            Judgment::Remote { label: "ssh `api.dev.internal`".to_string(), command: "systemctl status api".to_string(), ask: None }
+        );
+    }
+
+    #[test]
+    fn test_transfers() {
+        let dir = TempDir::new("remote_transfers");
+        dir.create_dir("app");
+        dir.create_file("app/main.rs");
+        dir.create_file("app/.env");
+        let config = Config { remote: RemotePolicy { allowed_hosts: vec!["web1".to_string()] }, ..Config::default() };
+        let judge_with = |command: &str| judge(command, dir.path(), &config);
+
+        assert_eq!(judge_with("scp -P 2222 build.tar.gz deploy@web1:/tmp/"), Judgment::Safe);
+        assert_eq!(judge_with("rsync -avz web1:/var/log/app/ logs/"), Judgment::Safe);
+        assert_eq!(
+            judge_with("scp build.tar.gz db1:/tmp/"),
+            Judgment::Ask("scp connects to `db1`, which isn't on remote.allowed_hosts".to_string())
+        );
+        assert_eq!(judge_with("scp web1:/srv/app/.env ."), Judgment::Deny("scp copies the secrets file `web1:/srv/app/.env`".to_string()));
+        assert_eq!(
+            judge_with("rsync -az app/ web1:/srv/app/"),
+            Judgment::Deny("rsync copies `app/`, which holds the secrets file `.env`".to_string())
+        );
+        assert_eq!(judge_with("rsync -az --exclude '.env*' app/ web1:/srv/app/"), Judgment::Safe);
+        assert_eq!(judge_with("rsync -a app/ backup/"), Judgment::Safe);
+        assert_eq!(
+            judge_with("rsync -a --delete --exclude .env app/ web1:/srv/app/"),
+            Judgment::Ask("rsync --delete deletes files in `web1:/srv/app/` that aren't in the source".to_string())
+        );
+        assert_eq!(
+            judge_with("rsync -a --delete-after --exclude=.env app/ web1:/"),
+            Judgment::Deny("rsync --delete-after into `web1:/` deletes everything there that isn't in the source".to_string())
+        );
+        assert_eq!(judge_with("rsync -an --delete app/ web1:/srv/app/"), Judgment::Safe);
+        assert_eq!(
+            judge_with("rsync --remove-source-files -a logs/ web1:/archive/"),
+            Judgment::Ask("rsync --remove-source-files deletes the source files it copies".to_string())
+        );
+        assert_eq!(judge_with("rsync -a -e 'ssh -p 2222 -i ~/.ssh/deploy' app/main.rs web1:/srv/"), Judgment::Safe);
+        assert_eq!(
+            judge_with("rsync -a -e 'sh -c payload' app/main.rs web1:/srv/"),
+            Judgment::Ask("rsync -e `sh -c payload` runs its own remote shell".to_string())
+        );
+        assert_eq!(
+            judge_with("rsync -a --rsh='ssh -o ProxyCommand=payload' app/main.rs web1:/srv/"),
+            Judgment::Ask("rsync --rsh `ssh -o ProxyCommand=payload` runs its own remote shell".to_string())
+        );
+        assert_eq!(
+            judge_with("rsync -a --rsync-path='sudo rsync' app/main.rs web1:/srv/"),
+            Judgment::Ask("rsync --rsync-path `sudo rsync` runs its own command on the remote host".to_string())
+        );
+        assert_eq!(judge_with("scp -S ./payload app/main.rs web1:/srv/"), Judgment::Ask("scp -S `./payload` runs its own connection program".to_string()));
+        assert_eq!(
+            judge_with("scp -o ProxyCommand=payload app/main.rs web1:/srv/"),
+            Judgment::Ask("scp -o ProxyCommand runs a command of its own".to_string())
+        );
+        assert_eq!(judge_with("rsync -a payload ~/.bashrc"), Judgment::Ask("rsync writes `~/.bashrc`, outside the repo".to_string()));
+        assert_eq!(judge_with("scp web1:/etc/hosts /etc/hosts"), Judgment::Ask("scp writes `/etc/hosts`, outside the repo".to_string()));
+    }
+}
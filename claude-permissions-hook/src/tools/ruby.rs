@@ -0,0 +1,201 @@
+//! Ruby tooling: Bundler, Rake, Rails, RSpec and RubyGems, including `bin/` binstubs.
+
+use std::path::Path;
+
+use super::{operands, strictest, Judgment};
+
+/// Bundler subcommands that only report on the bundle
+const SAFE_BUNDLE_SUBCOMMANDS: &[&str] = &["check", "list", "show", "info", "outdated", "platform", "version", "doctor", "licenses", "help", "exec"];
+
+/// Rake flags that take a separate value
+const RAKE_VALUE_FLAGS: &[&str] = &["-f", "--rakefile", "-C", "--directory", "-I", "--libdir", "-r", "--require", "-j", "--jobs", "-E", "--execute", "-e", "-p"];
+
+/// Flags that make rake list or describe tasks, or only trace what would run
+const RAKE_INFO_FLAGS: &[&str] = &["-T", "--tasks", "-D", "--describe", "-P", "--prereqs", "-W", "--where", "-n", "--dry-run", "-V", "--version", "-h", "--help", "-AT"];
+
+/// Rake and Rails tasks that run tests, linters or builds, or report on the app.
+/// `namespace:*` entries cover every task in the namespace.
+const SAFE_TASKS: &[&str] = &[
+    "test", "test:*", "spec", "spec:*", "build", "rubocop", "rubocop:*", "standard", "routes",
+    "about", "stats", "notes", "middleware", "initializers", "zeitwerk:check", "secret", "version",
+    "log:clear", "tmp:clear", "assets:precompile", "assets:clean", "t",
+];
+
+/// Rake and Rails tasks that print or edit decrypted secrets
+const SECRET_TASKS: &[&str] = &["credentials:show", "credentials:edit", "secrets:show", "secrets:edit", "encrypted:show", "encrypted:edit"];
+
+/// RubyGems subcommands that only read
+const SAFE_GEM_SUBCOMMANDS: &[&str] = &[
+    "list", "search", "info", "specification", "contents", "query", "which", "environment", "help",
+    "outdated", "dependency", "build", "check", "fetch", "sources",
+];
+
+/// Check if a program is a Ruby tool, or a `bin/` binstub for one
+pub fn is_ruby_tool(program: &str) -> bool {
+    let name = program.strip_prefix("./").unwrap_or(program);
+    matches!(name.strip_prefix("bin/").unwrap_or(name), "bundle" | "rake" | "rails" | "rspec" | "gem")
+}
+
+/// Judge a Bundler, Rake, Rails, RSpec or RubyGems command
+pub fn judge(program: &str, args: &[&str], current_dir: &Path) -> Judgment {
+    match program.rsplit('/').next().unwrap_or(program) {
+        "bundle" => judge_bundle(args, current_dir),
+        "rake" => judge_rake(args),
+        "rails" => judge_rails(args),
+        "rspec" => judge_rspec(args),
+        _ => judge_gem(args),
+    }
+}
+
+fn judge_bundle(args: &[&str], current_dir: &Path) -> Judgment {
+    let Some((&sub, rest)) = args.split_first() else {
+        // Bare `bundle` is `bundle install`
+        return judge_bundle(&["install"], current_dir);
+    };
+    match sub {
+        "exec" | "ex" => match rest.split_first() {
+            Some((program, args)) => {
+                let mut command = program.to_string();
+                for arg in args {
+                    command.push(' ');
+                    command.push_str(arg);
+                }
+                Judgment::Nested { label: "bundle exec".to_string(), command }
+            }
+            None => Judgment::Unknown,
+        },
+        "-v" | "--version" | "-h" | "--help" => Judgment::Safe,
+        "config" if rest.is_empty() || matches!(rest.first(), Some(&("list" | "get"))) => Judgment::Safe,
+        // With a lockfile, install only fetches what it pins
+        "install" if !rest.contains(&"--update") && has_lockfile(current_dir) => Judgment::Safe,
+        _ if SAFE_BUNDLE_SUBCOMMANDS.contains(&sub) => Judgment::Safe,
+        _ => Judgment::Unknown,
+    }
+}
+
+fn has_lockfile(dir: &Path) -> bool {
+    dir.ancestors().find(|d| d.join("Gemfile").is_file()).is_some_and(|root| root.join("Gemfile.lock").is_file())
+}
+
+fn judge_rake(args: &[&str]) -> Judgment {
+    if args.iter().any(|a| RAKE_INFO_FLAGS.contains(a)) {
+        return Judgment::Safe;
+    }
+    let tasks = operands(args, RAKE_VALUE_FLAGS);
+    if tasks.is_empty() {
+        // Bare `rake` runs the default task, which the Rakefile chooses
+        return Judgment::Unknown;
+    }
+    strictest(tasks.iter().filter(|t| !t.contains('=')).map(|task| judge_task("rake", task)))
+}
+
+fn judge_rails(args: &[&str]) -> Judgment {
+    let Some((&sub, _)) = args.split_first() else {
+        return Judgment::Safe;
+    };
+    match sub {
+        "-v" | "--version" | "-h" | "--help" => Judgment::Safe,
+        // `rails console`, `server`, `runner`, `dbconsole` and generators run arbitrary code or never exit
+        _ => judge_task("rails", sub),
+    }
+}
+
+/// `rspec` runs the project's specs, like `rake spec`; `--init` writes config files into the project
+fn judge_rspec(args: &[&str]) -> Judgment {
+    if args.contains(&"--init") {
+        Judgment::Unknown
+    } else {
+        Judgment::Safe
+    }
+}
+
+/// Judge one Rake or Rails task, ignoring its `[args]`
+fn judge_task(program: &str, task: &str) -> Judgment {
+    let name = task.split('[').next().unwrap_or(task);
+    let in_namespace = |entry: &&str| entry.strip_suffix('*').is_some_and(|ns| name.starts_with(ns));
+    match name {
+        _ if SECRET_TASKS.contains(&name) => Judgment::Deny(format!("{} {} exposes decrypted credentials", program, name)),
+        _ if SAFE_TASKS.contains(&name) || SAFE_TASKS.iter().any(in_namespace) => Judgment::Safe,
+        "release" | "release:rubygem_push" => Judgment::Ask(format!("{} {} pushes the gem to rubygems.org, which can't be undone", program, name)),
+        "install" | "install:local" => Judgment::Ask(format!("{} {} installs the gem into the system gem directory", program, name)),
+        _ => Judgment::Unknown,
+    }
+}
+
+fn judge_gem(args: &[&str]) -> Judgment {
+    let Some((&sub, rest)) = args.split_first() else {
+        return Judgment::Safe;
+    };
+    let gems = operands(rest, &["-v", "--version", "-s", "--source", "-i", "--install-dir", "-n", "--bindir", "-k", "--key", "--host", "--otp"]);
+    let named = gems.iter().map(|g| format!("`{}`", g)).collect::<Vec<_>>().join(", ");
+    match sub {
+        "-v" | "--version" | "-h" | "--help" => Judgment::Safe,
+        _ if SAFE_GEM_SUBCOMMANDS.contains(&sub) => Judgment::Safe,
+        "owner" if !rest.iter().any(|a| matches!(*a, "-a" | "--add" | "-r" | "--remove")) => Judgment::Safe,
+        "push" | "yank" | "owner" => Judgment::Ask(format!("gem {} changes what's published on rubygems.org", sub)),
+        "install" => Judgment::Ask(format!("gem install installs {} into the system gem directory", named)),
+        "uninstall" => Judgment::Ask(format!("gem uninstall removes {} from the system gem directory", named)),
+        "signin" | "signout" => Judgment::Ask(format!("gem {} changes the stored rubygems.org credentials", sub)),
+        _ => Judgment::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    #[test]
+    fn test_bundler() {
+        let dir = TempDir::new("ruby_bundler");
+        dir.write_file("Gemfile", "source 'https://rubygems.org'\n");
+        assert_eq!(judge_in("bundle install", dir.path()), Judgment::Unknown);
+        dir.create_file("Gemfile.lock");
+        assert_eq!(judge_in("bundle install --jobs 4", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("bin/bundle check", dir.path()), Judgment::Safe);
+        assert_eq!(
+            judge_in("bundle exec rspec spec/models", dir.path()),
+            Judgment::Nested { label: "bundle exec".to_string(), command: "rspec spec/models".to_string() }
+        );
+        assert_eq!(judge_in("bundle update rails", dir.path()), Judgment::Unknown);
+    }
+
+    #[test]
+    fn test_rake_and_rails_tasks() {
+        let dir = TempDir::new("ruby_tasks");
+        for command in [
+            "rake -T", "rake test spec:models", "bin/rake rubocop", "rails test test/models", "./bin/rails routes", "rails --version",
+            "rspec", "bin/rspec spec/models/user_spec.rb:12 --format documentation",
+        ] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(
+            judge_in("rake build release", dir.path()),
+            Judgment::Ask("rake release pushes the gem to rubygems.org, which can't be undone".to_string())
+        );
+        assert_eq!(
+            judge_in("bin/rails credentials:show", dir.path()),
+            Judgment::Deny("rails credentials:show exposes decrypted credentials".to_string())
+        );
+        assert_eq!(judge_in("rails console", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("rake", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("rspec --init", dir.path()), Judgment::Unknown);
+        assert!(!is_ruby_tool("vendor/bin/rake"));
+    }
+
+    #[test]
+    fn test_gem() {
+        let dir = TempDir::new("ruby_gem");
+        assert_eq!(judge_in("gem build app.gemspec", dir.path()), Judgment::Safe);
+        assert_eq!(judge_in("gem owner mygem", dir.path()), Judgment::Safe);
+        assert_eq!(
+            judge_in("gem push pkg/app-1.0.gem", dir.path()),
+            Judgment::Ask("gem push changes what's published on rubygems.org".to_string())
+        );
+        assert_eq!(
+            judge_in("gem install rails -v 7.1", dir.path()),
+            Judgment::Ask("gem install installs `rails` into the system gem directory".to_string())
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! swift: SwiftPM's `build`/`test`/`run` and `swift package` subcommands.
+
+use std::path::Path;
+
+use super::{check_path_flag, operands, Judgment};
+
+/// Flags (of any subcommand) that take a separate value
+const VALUE_FLAGS: &[&str] = &[
+    "-c", "--configuration", "--package-path", "--scratch-path", "--build-path", "--cache-path",
+    "--triple", "--sdk", "--swift-sdk", "--jobs", "-j", "--product", "--target", "--filter",
+    "--skip", "-Xswiftc", "-Xcc", "-Xlinker", "-Xcxx",
+];
+
+/// `swift package` subcommands that inspect the package or only touch its build state
+const SAFE_PACKAGE_SUBCOMMANDS: &[&str] = &[
+    "describe", "dump-package", "show-dependencies", "clean", "reset", "init", "compute-checksum",
+    "archive-source", "diagnose-api-breaking-changes", "generate-documentation", "dump-symbol-graph",
+    "completion-tool", "tools-version", "help", "purge-cache",
+];
+
+/// Judge `swift <subcommand> [args]`
+pub fn judge(args: &[&str], current_dir: &Path) -> Judgment {
+    for flag in ["--package-path", "--scratch-path", "--build-path"] {
+        if let Some(judgment) = check_path_flag(args, flag, "swift", current_dir) {
+            return judgment;
+        }
+    }
+    let words = operands(args, VALUE_FLAGS);
+    match words.as_slice() {
+        _ if args.first().is_some_and(|a| matches!(*a, "--version" | "-version" | "--help" | "-h")) => Judgment::Safe,
+        // `run` builds and starts one of the package's executables
+        ["build" | "test" | "run" | "format", ..] => Judgment::Safe,
+        ["package", sub, ..] if SAFE_PACKAGE_SUBCOMMANDS.contains(sub) => Judgment::Safe,
+        // With Package.resolved, resolve only fetches the pinned versions
+        ["package", "resolve", ..] if has_resolved(current_dir) => Judgment::Safe,
+        ["package-registry", "publish", ..] if args.contains(&"--dry-run") => Judgment::Safe,
+        ["package-registry", "publish", ..] => {
+            Judgment::Ask("swift package-registry publish uploads a release to the registry, which can't be undone".to_string())
+        }
+        ["package-registry", sub @ ("login" | "logout"), ..] => {
+            Judgment::Ask(format!("swift package-registry {} changes the stored registry credentials", sub))
+        }
+        ["sdk", "install" | "remove", ..] => Judgment::Ask("swift sdk changes the installed Swift SDKs".to_string()),
+        ["sdk", "list", ..] => Judgment::Safe,
+        // Bare `swift` is the REPL; `swift file.swift` runs a script; `swift package <plugin>` runs plugin code
+        _ => Judgment::Unknown,
+    }
+}
+
+fn has_resolved(dir: &Path) -> bool {
+    dir.ancestors().find(|d| d.join("Package.swift").is_file()).is_some_and(|root| root.join("Package.resolved").is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::tools::judge_in;
+
+    #[test]
+    fn test_swift_subcommands() {
+        let dir = TempDir::new("swift_subcommands");
+        dir.create_dir(".git");
+        dir.write_file("Package.swift", "// swift-tools-version:5.9\n");
+        for command in ["swift build -c release", "swift test --filter AppTests", "swift package describe --type json", "swift --version"] {
+            assert_eq!(judge_in(command, dir.path()), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_in("swift package resolve", dir.path()), Judgment::Unknown);
+        dir.create_file("Package.resolved");
+        assert_eq!(judge_in("swift package resolve", dir.path()), Judgment::Safe);
+        assert_eq!(
+            judge_in("swift package-registry publish acme.app 1.0.0", dir.path()),
+            Judgment::Ask("swift package-registry publish uploads a release to the registry, which can't be undone".to_string())
+        );
+        assert_eq!(
+            judge_in("swift build --scratch-path /tmp/build", dir.path()),
+            Judgment::Ask("swift --scratch-path `/tmp/build` is outside the repo".to_string())
+        );
+        assert_eq!(judge_in("swift package plugin --allow-writing-to-package-directory format", dir.path()), Judgment::Unknown);
+        assert_eq!(judge_in("swift ", dir.path()), Judgment::Unknown);
+    }
+}
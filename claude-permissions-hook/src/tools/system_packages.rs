@@ -0,0 +1,191 @@
+//! System package managers: brew, apt/apt-get/apt-cache, dnf/yum, pacman (and the yay
+//! and paru AUR helpers) and MacPorts. Queries are allowed; anything that installs,
+//! removes or upgrades packages, refreshes package lists or changes services asks,
+//! unless it only installs packages on the `system_packages.allow` list.
+
+use serde::Deserialize;
+
+use super::Judgment;
+use crate::paths;
+
+/// brew commands that only query
+const BREW_QUERIES: &[&str] = &[
+    "list", "ls", "info", "abv", "search", "outdated", "deps", "uses", "leaves", "desc", "config",
+    "doctor", "dr", "log", "cat", "formulae", "casks", "options", "missing", "commands", "tap-info",
+    "shellenv", "--prefix", "--cellar", "--cache", "--repository", "--repo", "--env", "--version",
+    "-v", "help", "--caskroom",
+];
+
+/// brew commands that install the packages they name
+const BREW_INSTALLS: &[&str] = &["install", "reinstall", "upgrade", "fetch"];
+
+/// apt and apt-get commands that only query
+const APT_QUERIES: &[&str] = &["list", "search", "show", "showsrc", "policy", "depends", "rdepends", "changelog", "madison", "pkgnames", "showpkg", "stats", "help"];
+
+/// dnf and yum commands that only query
+const DNF_QUERIES: &[&str] = &[
+    "list", "info", "search", "provides", "whatprovides", "repolist", "repoinfo", "repoquery",
+    "check-update", "deplist", "help", "--version",
+];
+
+/// port commands that only query
+const PORT_QUERIES: &[&str] = &[
+    "list", "installed", "search", "info", "deps", "rdeps", "variants", "contents", "outdated",
+    "provides", "echo", "dependents", "rdependents", "location", "version", "platform", "file",
+    "dir", "help", "usage", "notes", "log",
+];
+
+/// Flags that only simulate a change
+const SIMULATE_FLAGS: &[&str] = &["-s", "--simulate", "--dry-run", "--just-print", "--no-act", "--recon", "--assumeno"];
+
+/// Packages the system package managers may install without asking
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SystemPackagePolicy {
+    /// Formula, cask and package name patterns (`*`, `?`), e.g. `ripgrep` or `python@*`
+    pub allow: Vec<String>,
+}
+
+impl SystemPackagePolicy {
+    /// Names may carry a tap or repository (`homebrew/core/jq`, `extra/jq`) or a
+    /// version (`jq=1.6-2`)
+    fn allows(&self, name: &str) -> bool {
+        let name = name.split('=').next().unwrap_or(name);
+        let short = name.rsplit('/').next().unwrap_or(name);
+        self.allow.iter().any(|pattern| paths::glob_match(pattern, name) || paths::glob_match(pattern, short))
+    }
+}
+
+/// Check if a program is a system package manager
+pub fn is_system_package_manager(program: &str) -> bool {
+    matches!(program, "brew" | "apt" | "apt-get" | "apt-cache" | "dnf" | "yum" | "pacman" | "yay" | "paru" | "port")
+}
+
+/// Judge a package manager command; `sudo` and other wrappers are already stripped
+pub fn judge(program: &str, args: &[&str], policy: &SystemPackagePolicy) -> Judgment {
+    let (flags, operands): (Vec<&str>, Vec<&str>) = args.iter().partition(|a| a.starts_with('-'));
+    match program {
+        "pacman" | "yay" | "paru" => return pacman(program, &flags, &operands, policy),
+        // Flags like `--prefix` are brew's queries
+        "brew" if args.first().is_some_and(|a| a.starts_with('-')) => {
+            return if BREW_QUERIES.contains(&args[0]) { Judgment::Safe } else { Judgment::Unknown };
+        }
+        "apt-cache" => return Judgment::Safe,
+        _ => {}
+    }
+    let Some((&verb, names)) = operands.split_first() else {
+        return Judgment::Unknown;
+    };
+    let display = format!("{} {}", program, verb);
+    let (queries, installs): (&[&str], &[&str]) = match program {
+        "brew" => (BREW_QUERIES, BREW_INSTALLS),
+        "apt" | "apt-get" => (APT_QUERIES, &["install", "reinstall"]),
+        "dnf" | "yum" => (DNF_QUERIES, &["install", "reinstall"]),
+        _ => (PORT_QUERIES, &["install"]),
+    };
+    match (program, verb, names.first().copied()) {
+        _ if queries.contains(&verb) => Judgment::Safe,
+        ("brew", "services" | "bundle" | "tap", None) | ("brew", "services", Some("list" | "info")) | ("brew", "bundle", Some("check" | "list")) => Judgment::Safe,
+        ("brew", "services", Some(action)) => Judgment::Ask(format!("{} {} changes background services", display, action)),
+        ("dnf" | "yum", "history" | "module" | "group" | "groups", None | Some("list" | "info" | "summary")) => Judgment::Safe,
+        ("dnf" | "yum", "history" | "module" | "group" | "groups", Some(sub)) => ask(&format!("{} {}", display, sub), &names[1..]),
+        _ if SIMULATE_FLAGS.iter().any(|f| flags.contains(f)) && program != "brew" && program != "port" => Judgment::Safe,
+        _ if installs.contains(&verb) && !names.is_empty() && names.iter().all(|n| policy.allows(n)) => Judgment::Safe,
+        _ => ask(&display, names),
+    }
+}
+
+/// pacman's operation is a flag: `-Q` queries, `-Ss` searches, `-S` installs, `-R` removes
+fn pacman(program: &str, flags: &[&str], operands: &[&str], policy: &SystemPackagePolicy) -> Judgment {
+    let Some(operation) = flags.iter().find(|f| !f.starts_with("--") || matches!(**f, "--query" | "--sync" | "--remove" | "--upgrade" | "--database" | "--files" | "--deptest")) else {
+        return if operands.is_empty() && program != "pacman" { Judgment::Ask(format!("{} upgrades the system's packages", program)) } else { Judgment::Unknown };
+    };
+    let letters = match *operation {
+        "--query" => "Q",
+        "--sync" => "S",
+        "--remove" => "R",
+        "--upgrade" => "U",
+        "--database" => "D",
+        "--files" => "F",
+        "--deptest" => "T",
+        short => short.trim_start_matches('-'),
+    };
+    let all_letters: String = flags.iter().filter(|f| !f.starts_with("--")).map(|f| f.trim_start_matches('-')).collect();
+    let has_long = |names: &[&str]| flags.iter().any(|f| names.contains(f));
+    let display = format!("{} {}", program, operation);
+    let refreshes = all_letters.contains('y') || all_letters.contains('u') || has_long(&["--refresh", "--sysupgrade"]);
+    let query = match letters.chars().next() {
+        Some('Q' | 'T') => true,
+        Some('F') => !refreshes,
+        Some('S') => !refreshes && (all_letters.chars().any(|c| matches!(c, 's' | 'i' | 'l' | 'g' | 'p')) || has_long(&["--search", "--info", "--list", "--groups", "--print"])),
+        _ => false,
+    };
+    if query || has_long(&["--print"]) {
+        return Judgment::Safe;
+    }
+    if letters.starts_with('S') && !refreshes && !all_letters.contains('c') && !operands.is_empty() && operands.iter().all(|n| policy.allows(n)) {
+        return Judgment::Safe;
+    }
+    ask(&display, operands)
+}
+
+fn ask(display: &str, names: &[&str]) -> Judgment {
+    if names.is_empty() {
+        return Judgment::Ask(format!("{} changes the system's packages", display));
+    }
+    let names = names.iter().map(|n| format!("`{}`", n)).collect::<Vec<_>>().join(", ");
+    Judgment::Ask(format!("{} changes the system's packages ({})", display, names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn judge_with(command: &str, policy: &SystemPackagePolicy) -> Judgment {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        judge(tokens[0], &tokens[1..], policy)
+    }
+
+    fn judge_default(command: &str) -> Judgment {
+        judge_with(command, &SystemPackagePolicy::default())
+    }
+
+    #[test]
+    fn test_brew() {
+        for command in ["brew list --versions", "brew info jq", "brew search ripgrep", "brew outdated", "brew deps --tree node", "brew --prefix openssl", "brew services list", "brew tap"] {
+            assert_eq!(judge_default(command), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_default("brew install jq"), Judgment::Ask("brew install changes the system's packages (`jq`)".to_string()));
+        assert_eq!(
+            judge_default("brew uninstall --force node"),
+            Judgment::Ask("brew uninstall changes the system's packages (`node`)".to_string())
+        );
+        assert_eq!(judge_default("brew services stop postgresql@16"), Judgment::Ask("brew services stop changes background services".to_string()));
+        assert_eq!(judge_default("brew upgrade"), Judgment::Ask("brew upgrade changes the system's packages".to_string()));
+
+        let policy = SystemPackagePolicy { allow: vec!["jq".to_string(), "python@*".to_string()] };
+        assert_eq!(judge_with("brew install jq python@3.12", &policy), Judgment::Safe);
+        assert_eq!(judge_with("brew install --cask homebrew/cask/jq", &policy), Judgment::Safe);
+        assert_eq!(judge_with("brew install jq wget", &policy), Judgment::Ask("brew install changes the system's packages (`jq`, `wget`)".to_string()));
+        assert_eq!(judge_with("brew uninstall jq", &policy), Judgment::Ask("brew uninstall changes the system's packages (`jq`)".to_string()));
+    }
+
+    #[test]
+    fn test_linux_package_managers() {
+        for command in ["apt list --installed", "apt-cache policy nginx", "apt-get install -s nginx", "dnf info httpd", "yum check-update", "dnf history list", "pacman -Qi linux", "pacman -Ss ripgrep", "port installed", "yay -Ss neovim"] {
+            assert_eq!(judge_default(command), Judgment::Safe, "{}", command);
+        }
+        assert_eq!(judge_default("apt-get install -y nginx"), Judgment::Ask("apt-get install changes the system's packages (`nginx`)".to_string()));
+        assert_eq!(judge_default("apt update"), Judgment::Ask("apt update changes the system's packages".to_string()));
+        assert_eq!(judge_default("dnf history undo 12"), Judgment::Ask("dnf history undo changes the system's packages (`12`)".to_string()));
+        assert_eq!(judge_default("dnf remove httpd"), Judgment::Ask("dnf remove changes the system's packages (`httpd`)".to_string()));
+        assert_eq!(judge_default("pacman -Syu"), Judgment::Ask("pacman -Syu changes the system's packages".to_string()));
+        assert_eq!(judge_default("pacman -Rns foo"), Judgment::Ask("pacman -Rns changes the system's packages (`foo`)".to_string()));
+        assert_eq!(judge_default("port install wget"), Judgment::Ask("port install changes the system's packages (`wget`)".to_string()));
+
+        let policy = SystemPackagePolicy { allow: vec!["ripgrep".to_string(), "jq".to_string()] };
+        assert_eq!(judge_with("apt-get install -y ripgrep jq=1.6-2", &policy), Judgment::Safe);
+        assert_eq!(judge_with("pacman -S --needed extra/ripgrep", &policy), Judgment::Safe);
+        assert_eq!(judge_with("pacman -Sy ripgrep", &policy), Judgment::Ask("pacman -Sy changes the system's packages (`ripgrep`)".to_string()));
+    }
+}
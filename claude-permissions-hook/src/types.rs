@@ -10,6 +10,8 @@ pub struct HookInput {
     pub tool_input: Option<HashMap<String, serde_json::Value>>,
     /// Current working directory
     pub cwd: Option<String>,
+    /// Claude Code session identifier
+    pub session_id: Option<String>,
 }
 
 impl HookInput {
@@ -20,6 +22,16 @@ impl HookInput {
             .get(key)?
             .as_str()
     }
+
+    /// Get a boolean field from tool_input
+    pub fn get_input_bool(&self, key: &str) -> Option<bool> {
+        self.tool_input.as_ref()?.get(key)?.as_bool()
+    }
+
+    /// Get a non-negative integer field from tool_input
+    pub fn get_input_u64(&self, key: &str) -> Option<u64> {
+        self.tool_input.as_ref()?.get(key)?.as_u64()
+    }
 }
 
 /// The decision a hook can make
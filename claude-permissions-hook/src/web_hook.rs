@@ -21,6 +21,7 @@ mod tests {
             tool_name: Some(tool.to_string()),
             tool_input: Some(tool_input),
             cwd: Some("/tmp".to_string()),
+            session_id: None,
         }
     }
 
@@ -1,5 +1,6 @@
 use crate::paths;
 use crate::types::{Decision, HookInput};
+use std::path::{Path, PathBuf};
 
 /// Evaluate an Edit or Write tool invocation
 pub fn evaluate(input: &HookInput) -> Decision {
@@ -21,6 +22,34 @@ pub fn evaluate(input: &HookInput) -> Decision {
     Decision::Abstain
 }
 
+/// The file a Write or Edit would overwrite, if it exists and git can't restore it
+/// (untracked, or outside any repo), so it can be checkpointed first
+pub fn checkpoint_targets(input: &HookInput) -> Vec<PathBuf> {
+    let cwd = input.cwd.as_deref().unwrap_or("");
+    let Some(file_path) = input.get_input_str("file_path") else {
+        return Vec::new();
+    };
+
+    let normalized = paths::normalize_path(file_path, cwd);
+    if !normalized.is_file() || is_git_tracked(&normalized) {
+        return Vec::new();
+    }
+    vec![normalized]
+}
+
+/// Check if git tracks a file (so its committed content can be recovered)
+fn is_git_tracked(file: &Path) -> bool {
+    let Some(dir) = file.parent() else {
+        return false;
+    };
+    std::process::Command::new("git")
+        .args(["ls-files", "--error-unmatch", "--"])
+        .arg(file)
+        .current_dir(dir)
+        .output()
+        .is_ok_and(|out| out.status.success())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,6 +62,7 @@ mod tests {
             tool_name: Some(tool.to_string()),
             tool_input: Some(tool_input),
             cwd: Some(cwd.to_string()),
+            session_id: None,
         }
     }
 
@@ -40,6 +70,18 @@ mod tests {
         "/Users/araftery/workspace/project"
     }
 
+    #[test]
+    fn test_checkpoint_targets_untracked_file() {
+        let dir = crate::test_util::TempDir::new("write_checkpoint");
+        let file = dir.write_file("notes.txt", "keep me");
+        let input = make_write_input("Write", "notes.txt", &dir.path_str());
+        assert_eq!(checkpoint_targets(&input), vec![file]);
+
+        // New files have nothing to lose
+        let input = make_write_input("Write", "new.txt", &dir.path_str());
+        assert!(checkpoint_targets(&input).is_empty());
+    }
+
     #[test]
     fn test_write_env() {
         assert_eq!(
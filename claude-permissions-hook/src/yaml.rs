@@ -0,0 +1,322 @@
+//! A small YAML subset reader for config files the hook inspects (Taskfiles and the
+//! like): block maps and lists, flow lists and maps, quoted and plain scalars, and
+//! `|`/`>` block scalars. Anchors, tags and multi-document streams aren't supported.
+
+/// A parsed YAML node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Yaml {
+    Null,
+    Scalar(String),
+    List(Vec<Yaml>),
+    Map(Vec<(String, Yaml)>),
+}
+
+impl Yaml {
+    /// Look up a key in a map
+    pub fn get(&self, key: &str) -> Option<&Yaml> {
+        match self {
+            Yaml::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Yaml::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Yaml]> {
+        match self {
+            Yaml::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(String, Yaml)]> {
+        match self {
+            Yaml::Map(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a YAML document; `None` if it uses syntax outside the supported subset
+pub fn parse(text: &str) -> Option<Yaml> {
+    let mut lines: Vec<String> = text
+        .lines()
+        .filter(|line| line.trim() != "---")
+        .map(|line| line.replace('\t', "  "))
+        .collect();
+    let mut i = 0;
+    skip_blank(&lines, &mut i);
+    if i >= lines.len() {
+        return Some(Yaml::Null);
+    }
+    let indent = indent_of(&lines[i]);
+    let node = parse_block(&mut lines, &mut i, indent)?;
+    skip_blank(&lines, &mut i);
+    (i >= lines.len()).then_some(node)
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_blank(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn skip_blank(lines: &[String], i: &mut usize) {
+    while *i < lines.len() && is_blank(&lines[*i]) {
+        *i += 1;
+    }
+}
+
+fn is_list_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+/// Parse the block starting at line `i`, whose lines sit at `indent`
+fn parse_block(lines: &mut [String], i: &mut usize, indent: usize) -> Option<Yaml> {
+    skip_blank(lines, i);
+    if is_list_item(lines[*i].trim_start()) {
+        parse_list(lines, i, indent)
+    } else {
+        parse_map(lines, i, indent)
+    }
+}
+
+fn parse_list(lines: &mut [String], i: &mut usize, indent: usize) -> Option<Yaml> {
+    let mut items = Vec::new();
+    loop {
+        skip_blank(lines, i);
+        if *i >= lines.len() || indent_of(&lines[*i]) != indent || !is_list_item(lines[*i].trim_start()) {
+            break;
+        }
+        let content = lines[*i].trim_start()[1..].trim_start().to_string();
+        if content.is_empty() {
+            *i += 1;
+            items.push(parse_child(lines, i, indent)?);
+        } else if split_key(&content).is_some() && !content.starts_with(['[', '{', '"', '\'']) {
+            // `- key: value` starts a map nested in the item; re-indent it in place
+            let item_indent = lines[*i].len() - content.len();
+            lines[*i] = format!("{}{}", " ".repeat(item_indent), content);
+            items.push(parse_map(lines, i, item_indent)?);
+        } else {
+            *i += 1;
+            items.push(parse_value(&content, lines, i, indent)?);
+        }
+    }
+    Some(Yaml::List(items))
+}
+
+fn parse_map(lines: &mut [String], i: &mut usize, indent: usize) -> Option<Yaml> {
+    let mut entries = Vec::new();
+    loop {
+        skip_blank(lines, i);
+        if *i >= lines.len() || indent_of(&lines[*i]) != indent || is_list_item(lines[*i].trim_start()) {
+            break;
+        }
+        let content = lines[*i].trim_start().to_string();
+        let (key, value) = split_key(&content)?;
+        *i += 1;
+        let node = if value.is_empty() {
+            parse_child(lines, i, indent)?
+        } else {
+            parse_value(value, lines, i, indent)?
+        };
+        entries.push((unquote(key).to_string(), node));
+    }
+    (indent == 0 || !entries.is_empty()).then_some(Yaml::Map(entries))
+}
+
+/// The nested block after a `key:` or `-` with nothing on the line: deeper lines, or a
+/// list at the same indent (`key:\n- a`), or nothing
+fn parse_child(lines: &mut [String], i: &mut usize, indent: usize) -> Option<Yaml> {
+    skip_blank(lines, i);
+    if *i >= lines.len() {
+        return Some(Yaml::Null);
+    }
+    let next = indent_of(&lines[*i]);
+    if next > indent || (next == indent && is_list_item(lines[*i].trim_start())) {
+        parse_block(lines, i, next)
+    } else {
+        Some(Yaml::Null)
+    }
+}
+
+/// A value written after `key:` or `- `: a block scalar header, flow collection or scalar
+fn parse_value(value: &str, lines: &[String], i: &mut usize, indent: usize) -> Option<Yaml> {
+    let value = strip_comment(value);
+    if value.starts_with('|') || value.starts_with('>') {
+        return Some(Yaml::Scalar(block_scalar(value.starts_with('>'), lines, i, indent)));
+    }
+    parse_flow(value)
+}
+
+/// Collect the lines of a `|` or `>` block scalar: everything indented deeper than `indent`
+fn block_scalar(folded: bool, lines: &[String], i: &mut usize, indent: usize) -> String {
+    let mut body: Vec<&str> = Vec::new();
+    let mut body_indent = None;
+    while *i < lines.len() {
+        let line = &lines[*i];
+        if line.trim().is_empty() {
+            body.push("");
+            *i += 1;
+            continue;
+        }
+        let line_indent = indent_of(line);
+        if line_indent <= indent {
+            break;
+        }
+        let base = *body_indent.get_or_insert(line_indent);
+        body.push(&line[base.min(line_indent)..]);
+        *i += 1;
+    }
+    while body.last() == Some(&"") {
+        body.pop();
+    }
+    body.join(if folded { " " } else { "\n" })
+}
+
+/// Parse an inline value: `[a, b]`, `{k: v}`, a quoted string or a plain scalar
+fn parse_flow(value: &str) -> Option<Yaml> {
+    let value = value.trim();
+    if let Some(inner) = value.strip_prefix('[') {
+        let inner = inner.strip_suffix(']')?;
+        let items = split_flow(inner).into_iter().map(parse_flow).collect::<Option<Vec<_>>>()?;
+        return Some(Yaml::List(items));
+    }
+    if let Some(inner) = value.strip_prefix('{') {
+        let inner = inner.strip_suffix('}')?;
+        let mut entries = Vec::new();
+        for item in split_flow(inner) {
+            let (key, value) = split_key(item)?;
+            entries.push((unquote(key).to_string(), parse_flow(value)?));
+        }
+        return Some(Yaml::Map(entries));
+    }
+    if value.is_empty() || value == "~" || value == "null" {
+        return Some(Yaml::Null);
+    }
+    Some(Yaml::Scalar(unquote(value).to_string()))
+}
+
+/// Split a flow collection's contents on top-level commas
+fn split_flow(inner: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (idx, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(inner[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+/// Split `key: value` at the first `:` followed by a space or the end, outside quotes
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (idx, c) in content.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if idx == 0 => quote = Some(c),
+            (None, ':') => {
+                let rest = &content[idx + 1..];
+                if rest.is_empty() || rest.starts_with(' ') {
+                    return Some((content[..idx].trim(), rest.trim()));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Drop a trailing ` # comment` from an unquoted value
+fn strip_comment(value: &str) -> &str {
+    if value.starts_with(['"', '\'']) {
+        return value;
+    }
+    match value.find(" #") {
+        Some(idx) => value[..idx].trim_end(),
+        None => value,
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    crate::paths::strip_quotes(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(s: &str) -> Yaml {
+        Yaml::Scalar(s.to_string())
+    }
+
+    #[test]
+    fn test_maps_and_lists() {
+        let doc = parse(
+            "version: '3'\n\
+             # comment\n\
+             vars:\n  NAME: app  # trailing\n\
+             tasks:\n  build:\n    deps: [lint, 'test']\n    cmds:\n      - go build -o {{.NAME}}\n      - task: lint\n        vars: {X: 1}\n  lint:\n    cmds:\n    - golangci-lint run\n",
+        )
+        .unwrap();
+        assert_eq!(doc.get("version"), Some(&scalar("3")));
+        assert_eq!(doc.get("vars").and_then(|v| v.get("NAME")), Some(&scalar("app")));
+        let build = doc.get("tasks").and_then(|t| t.get("build")).unwrap();
+        assert_eq!(build.get("deps"), Some(&Yaml::List(vec![scalar("lint"), scalar("test")])));
+        let cmds = build.get("cmds").and_then(Yaml::as_list).unwrap();
+        assert_eq!(cmds[0], scalar("go build -o {{.NAME}}"));
+        assert_eq!(cmds[1].get("task"), Some(&scalar("lint")));
+        assert_eq!(cmds[1].get("vars").and_then(|v| v.get("X")), Some(&scalar("1")));
+        let lint = doc.get("tasks").and_then(|t| t.get("lint")).unwrap();
+        assert_eq!(lint.get("cmds"), Some(&Yaml::List(vec![scalar("golangci-lint run")])));
+    }
+
+    #[test]
+    fn test_block_scalars() {
+        let doc = parse("cmds:\n  - |\n    echo one\n    echo two\n\n  - >\n    folded\n    line\nafter: x\n").unwrap();
+        assert_eq!(
+            doc.get("cmds"),
+            Some(&Yaml::List(vec![scalar("echo one\necho two"), scalar("folded line")]))
+        );
+        assert_eq!(doc.get("after"), Some(&scalar("x")));
+    }
+
+    #[test]
+    fn test_quoted_keys_and_colons() {
+        let doc = parse("\"a:b\": 'c: d'\nurl: http://x\nempty:\n").unwrap();
+        assert_eq!(doc.get("a:b"), Some(&scalar("c: d")));
+        assert_eq!(doc.get("url"), Some(&scalar("http://x")));
+        assert_eq!(doc.get("empty"), Some(&Yaml::Null));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert_eq!(parse("key: [unclosed\n"), None);
+        assert_eq!(parse("a: 1\n  b: 2\n"), None);
+    }
+}